@@ -0,0 +1,460 @@
+use crate::lexer::{parse_bool, parse_u64, ConfigError, ErrorKind, Line, Lines};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn parse(s: &str) -> Option<Self> {
+        let lvl = match s {
+            "error" => LogLevel::Error,
+            "warn" => LogLevel::Warn,
+            "info" => LogLevel::Info,
+            "debug" => LogLevel::Debug,
+            "trace" => LogLevel::Trace,
+            _ => return None,
+        };
+        Some(lvl)
+    }
+}
+
+/// How the boot splash draws the logo (`splash = off|nearest|bilinear`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplashMode {
+    Off,
+    Nearest,
+    Bilinear,
+}
+
+impl SplashMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        let mode = match s {
+            "off" => SplashMode::Off,
+            "nearest" => SplashMode::Nearest,
+            "bilinear" => SplashMode::Bilinear,
+            _ => return None,
+        };
+        Some(mode)
+    }
+}
+
+/// Upper bound on `[[entry]]` tables; extra ones are reported and ignored.
+pub const MAX_ENTRIES: usize = 16;
+
+/// One `[[entry]]` table: a kernel the boot menu can offer, or an EFI
+/// application (`efi = path`) to chainload instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BootEntry<'a> {
+    /// Menu label; falls back to the image path when unset.
+    pub title: Option<&'a str>,
+    /// Kernel image path; empty for `efi` entries.
+    pub kernel: &'a str,
+    /// EFI application to chainload; `cmdline` becomes its load options.
+    pub efi: Option<&'a str>,
+    /// Where `kernel` and `initrd` are read from; see `BootConfig::device`.
+    pub device: Option<&'a str>,
+    pub cmdline: Option<&'a str>,
+    pub initrd: Option<&'a str>,
+}
+
+impl<'a> BootEntry<'a> {
+    pub fn label(&self) -> &'a str {
+        self.title.unwrap_or(self.path())
+    }
+
+    /// The image this entry boots: the EFI application or the kernel.
+    pub fn path(&self) -> &'a str {
+        self.efi.unwrap_or(self.kernel)
+    }
+}
+
+/// Settings from `boot.cfg`. Every field is optional; `None` means "use the
+/// bootloader's built-in default".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BootConfig<'a> {
+    /// Kernel image path on the boot volume.
+    pub kernel: Option<&'a str>,
+    /// Initial ramdisk path on the boot volume.
+    pub initrd: Option<&'a str>,
+    /// Kernel command line.
+    pub cmdline: Option<&'a str>,
+    /// Volume the kernel and initrd are read from (`device = any`,
+    /// `partuuid:GUID`, ...). Interpreted by the bootloader.
+    pub device: Option<&'a str>,
+    /// Kernel of A/B slot A; with `slot_b`, replaces `kernel` and the
+    /// bootloader picks the slot (see `boot_tries`).
+    pub slot_a: Option<&'a str>,
+    /// Kernel of A/B slot B.
+    pub slot_b: Option<&'a str>,
+    /// Unconfirmed boots of a slot before falling back to the other one.
+    pub boot_tries: Option<u64>,
+    /// Preferred GOP resolution (`resolution = 1920x1080`).
+    pub resolution: Option<(u32, u32)>,
+    /// Preferred aspect ratio when no resolution is given (`aspect = 16:9`).
+    pub aspect: Option<(u32, u32)>,
+    /// GOP mode request (`video = edid`, `max:2560x1440`, ...); overrides
+    /// `resolution` and `aspect`. Interpreted by the bootloader.
+    pub video: Option<&'a str>,
+    /// Kernel stack size in bytes (`stack_size = 64K`).
+    pub stack_size: Option<u64>,
+    pub log_level: Option<LogLevel>,
+    /// Boot splash logo scaling, or `off` for the plain text console.
+    pub splash: Option<SplashMode>,
+    /// Show the hardware inventory screen before booting (`inventory = true`).
+    pub inventory: Option<bool>,
+    /// Boot menu countdown in seconds.
+    pub timeout: Option<u64>,
+    /// Boot menu entries, in file order; see `entries()`.
+    pub entries: [BootEntry<'a>; MAX_ENTRIES],
+    pub entry_count: usize,
+}
+
+/// Table the parser is currently filling.
+enum Table {
+    Global,
+    /// Entry being built and the line of its `[[entry]]` header.
+    Entry(usize),
+    /// Past `MAX_ENTRIES`: keys are swallowed without further errors.
+    Overflow,
+}
+
+fn parse_pair(s: &str, sep: char) -> Option<(u32, u32)> {
+    let (a, b) = s.split_once(sep)?;
+    let a = a.trim().parse::<u32>().ok()?;
+    let b = b.trim().parse::<u32>().ok()?;
+    if a == 0 || b == 0 { return None; }
+    Some((a, b))
+}
+
+fn set<T>(slot: &mut Option<T>, value: T) -> Result<(), ErrorKind> {
+    if slot.is_some() { return Err(ErrorKind::DuplicateKey); }
+    *slot = Some(value);
+    Ok(())
+}
+
+impl<'a> BootConfig<'a> {
+    /// Parses `text`, calling `on_error` for every bad line. Bad lines are
+    /// skipped, so the result still holds every setting that parsed.
+    pub fn parse(text: &'a str, on_error: &mut dyn FnMut(ConfigError)) -> Self {
+        let mut cfg = BootConfig::default();
+        let mut table = Table::Global;
+        // Kernel path of the entry being built; checked when the entry closes.
+        let mut pending_kernel: Option<&'a str> = None;
+
+        for (line, item) in Lines::new(text) {
+            let res = match item {
+                Ok(Line::Pair(key, value)) => match table {
+                    Table::Global => cfg.apply(key, value),
+                    Table::Entry(_) if key == "kernel" => set(&mut pending_kernel, value),
+                    Table::Entry(_) => cfg.apply_entry(key, value),
+                    Table::Overflow => Ok(()),
+                },
+                Ok(Line::ArrayTable("entry")) => {
+                    cfg.close_entry(&mut table, pending_kernel.take(), on_error);
+                    if cfg.entry_count < MAX_ENTRIES {
+                        cfg.entries[cfg.entry_count] = BootEntry::default();
+                        table = Table::Entry(line);
+                        Ok(())
+                    } else {
+                        table = Table::Overflow;
+                        Err(ErrorKind::TooManyEntries)
+                    }
+                }
+                Ok(Line::ArrayTable(_)) | Ok(Line::Section(_)) => Err(ErrorKind::UnknownSection),
+                Err(e) => Err(e),
+            };
+            if let Err(kind) = res {
+                on_error(ConfigError { line, kind });
+            }
+        }
+        cfg.close_entry(&mut table, pending_kernel, on_error);
+        cfg
+    }
+
+    /// Entries that parsed successfully.
+    pub fn entries(&self) -> &[BootEntry<'a>] {
+        &self.entries[..self.entry_count]
+    }
+
+    fn close_entry(&mut self, table: &mut Table, kernel: Option<&'a str>, on_error: &mut dyn FnMut(ConfigError)) {
+        if let Table::Entry(line) = *table {
+            let efi = self.entries[self.entry_count].efi;
+            match (kernel, efi) {
+                (Some(_), Some(_)) => on_error(ConfigError { line, kind: ErrorKind::KernelAndEfi }),
+                (None, None) => on_error(ConfigError { line, kind: ErrorKind::EntryWithoutKernel }),
+                (k, _) => {
+                    self.entries[self.entry_count].kernel = k.unwrap_or("");
+                    self.entry_count += 1;
+                }
+            }
+        }
+        *table = Table::Global;
+    }
+
+    fn apply_entry(&mut self, key: &str, value: &'a str) -> Result<(), ErrorKind> {
+        let e = &mut self.entries[self.entry_count];
+        match key {
+            "title" => set(&mut e.title, value),
+            "efi" => set(&mut e.efi, value),
+            "device" => set(&mut e.device, value),
+            "cmdline" => set(&mut e.cmdline, value),
+            "initrd" => set(&mut e.initrd, value),
+            _ => Err(ErrorKind::UnknownKey),
+        }
+    }
+
+    fn apply(&mut self, key: &str, value: &'a str) -> Result<(), ErrorKind> {
+        match key {
+            "kernel" => set(&mut self.kernel, value),
+            "initrd" => set(&mut self.initrd, value),
+            "cmdline" => set(&mut self.cmdline, value),
+            "device" => set(&mut self.device, value),
+            "slot_a" => set(&mut self.slot_a, value),
+            "slot_b" => set(&mut self.slot_b, value),
+            "boot_tries" => {
+                // 0xFF is the variable's "unset" marker.
+                let n = parse_u64(value).filter(|n| (1..0xFF).contains(n)).ok_or(ErrorKind::InvalidNumber)?;
+                set(&mut self.boot_tries, n)
+            }
+            "resolution" => {
+                let r = parse_pair(value, 'x').ok_or(ErrorKind::InvalidResolution)?;
+                set(&mut self.resolution, r)
+            }
+            "aspect" => {
+                let a = parse_pair(value, ':').ok_or(ErrorKind::InvalidAspect)?;
+                set(&mut self.aspect, a)
+            }
+            "video" => set(&mut self.video, value),
+            "stack_size" => {
+                let n = parse_u64(value).filter(|&n| n > 0).ok_or(ErrorKind::InvalidNumber)?;
+                set(&mut self.stack_size, n)
+            }
+            "log_level" => {
+                let l = LogLevel::parse(value).ok_or(ErrorKind::InvalidLogLevel)?;
+                set(&mut self.log_level, l)
+            }
+            "splash" => {
+                let m = SplashMode::parse(value).ok_or(ErrorKind::InvalidSplash)?;
+                set(&mut self.splash, m)
+            }
+            "inventory" => {
+                let b = parse_bool(value).ok_or(ErrorKind::InvalidBool)?;
+                set(&mut self.inventory, b)
+            }
+            "timeout" => {
+                let t = parse_u64(value).ok_or(ErrorKind::InvalidNumber)?;
+                set(&mut self.timeout, t)
+            }
+            _ => Err(ErrorKind::UnknownKey),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+    use super::*;
+
+    fn parse(text: &str) -> (BootConfig<'_>, Vec<ConfigError>) {
+        let mut errors = Vec::new();
+        let cfg = BootConfig::parse(text, &mut |e| errors.push(e));
+        (cfg, errors)
+    }
+
+    fn err(line: usize, kind: ErrorKind) -> ConfigError {
+        ConfigError { line, kind }
+    }
+
+    #[test]
+    fn every_global_key() {
+        let text = "\
+kernel = \\EFI\\RTOS\\kernel.rtosk
+initrd = \\EFI\\RTOS\\initrd
+cmdline = \"log_level=4 quiet\"
+device = partuuid:0FC63DAF-8483-4772-8E79-3D69D8477DE4
+slot_a = \\EFI\\RTOS\\a.rtosk
+slot_b = \\EFI\\RTOS\\b.rtosk
+boot_tries = 3
+resolution = 1920x1080
+aspect = 16:10
+video = max:2560x1440
+stack_size = 64K
+log_level = debug
+splash = bilinear
+inventory = yes
+timeout = 5
+";
+        let (cfg, errors) = parse(text);
+        assert_eq!(errors, []);
+        assert_eq!(cfg.kernel, Some("\\EFI\\RTOS\\kernel.rtosk"));
+        assert_eq!(cfg.initrd, Some("\\EFI\\RTOS\\initrd"));
+        assert_eq!(cfg.cmdline, Some("log_level=4 quiet"));
+        assert_eq!(cfg.device, Some("partuuid:0FC63DAF-8483-4772-8E79-3D69D8477DE4"));
+        assert_eq!(cfg.slot_a, Some("\\EFI\\RTOS\\a.rtosk"));
+        assert_eq!(cfg.slot_b, Some("\\EFI\\RTOS\\b.rtosk"));
+        assert_eq!(cfg.boot_tries, Some(3));
+        assert_eq!(cfg.resolution, Some((1920, 1080)));
+        assert_eq!(cfg.aspect, Some((16, 10)));
+        assert_eq!(cfg.video, Some("max:2560x1440"));
+        assert_eq!(cfg.stack_size, Some(64 << 10));
+        assert_eq!(cfg.log_level, Some(LogLevel::Debug));
+        assert_eq!(cfg.splash, Some(SplashMode::Bilinear));
+        assert_eq!(cfg.inventory, Some(true));
+        assert_eq!(cfg.timeout, Some(5));
+        assert!(cfg.entries().is_empty());
+    }
+
+    #[test]
+    fn empty_file_uses_defaults() {
+        let (cfg, errors) = parse("# nothing here\n\n");
+        assert_eq!(errors, []);
+        assert_eq!(cfg, BootConfig::default());
+    }
+
+    #[test]
+    fn bad_values() {
+        let text = "\
+boot_tries = 0
+boot_tries = 255
+boot_tries = many
+resolution = 1920
+resolution = 0x1080
+aspect = 16/9
+aspect = 0:9
+stack_size = 0
+log_level = loud
+splash = sideways
+inventory = maybe
+timeout = -1
+colour = blue
+[video]
+";
+        let (cfg, errors) = parse(text);
+        assert_eq!(errors, [
+            err(1, ErrorKind::InvalidNumber),
+            err(2, ErrorKind::InvalidNumber),
+            err(3, ErrorKind::InvalidNumber),
+            err(4, ErrorKind::InvalidResolution),
+            err(5, ErrorKind::InvalidResolution),
+            err(6, ErrorKind::InvalidAspect),
+            err(7, ErrorKind::InvalidAspect),
+            err(8, ErrorKind::InvalidNumber),
+            err(9, ErrorKind::InvalidLogLevel),
+            err(10, ErrorKind::InvalidSplash),
+            err(11, ErrorKind::InvalidBool),
+            err(12, ErrorKind::InvalidNumber),
+            err(13, ErrorKind::UnknownKey),
+            err(14, ErrorKind::UnknownSection),
+        ]);
+        assert_eq!(cfg, BootConfig::default());
+    }
+
+    #[test]
+    fn boot_tries_range() {
+        assert_eq!(parse("boot_tries = 1").0.boot_tries, Some(1));
+        assert_eq!(parse("boot_tries = 0xFE").0.boot_tries, Some(0xFE));
+        assert_eq!(parse("boot_tries = 0xFF").1, [err(1, ErrorKind::InvalidNumber)]);
+    }
+
+    #[test]
+    fn duplicate_keys_keep_the_first() {
+        let (cfg, errors) = parse("kernel = a\n\nkernel = b\ntimeout = 1\ntimeout = 2\n");
+        assert_eq!(errors, [err(3, ErrorKind::DuplicateKey), err(5, ErrorKind::DuplicateKey)]);
+        assert_eq!(cfg.kernel, Some("a"));
+        assert_eq!(cfg.timeout, Some(1));
+    }
+
+    #[test]
+    fn bad_lines_are_skipped() {
+        let (cfg, errors) = parse("kernel = a\nthis is not toml\ntimeout = 3\n");
+        assert_eq!(errors, [err(2, ErrorKind::MissingEquals)]);
+        assert_eq!(cfg.kernel, Some("a"));
+        assert_eq!(cfg.timeout, Some(3));
+    }
+
+    #[test]
+    fn entries() {
+        let text = "\
+timeout = 3
+
+[[entry]]
+title = \"RTOS (debug)\"
+kernel = \\EFI\\RTOS\\kernel.rtosk
+cmdline = log_level=4
+initrd = \\EFI\\RTOS\\initrd
+device = any
+
+[[entry]]
+title = UEFI Shell
+efi = \\EFI\\tools\\shell.efi
+";
+        let (cfg, errors) = parse(text);
+        assert_eq!(errors, []);
+        assert_eq!(cfg.timeout, Some(3));
+        assert_eq!(cfg.kernel, None);
+        assert_eq!(cfg.entries(), [
+            BootEntry {
+                title: Some("RTOS (debug)"),
+                kernel: "\\EFI\\RTOS\\kernel.rtosk",
+                efi: None,
+                device: Some("any"),
+                cmdline: Some("log_level=4"),
+                initrd: Some("\\EFI\\RTOS\\initrd"),
+            },
+            BootEntry {
+                title: Some("UEFI Shell"),
+                efi: Some("\\EFI\\tools\\shell.efi"),
+                ..BootEntry::default()
+            },
+        ]);
+        assert_eq!(cfg.entries()[1].label(), "UEFI Shell");
+        assert_eq!(cfg.entries()[1].path(), "\\EFI\\tools\\shell.efi");
+    }
+
+    #[test]
+    fn bad_entries_are_skipped() {
+        let text = "\
+[[entry]]
+title = no image
+[[entry]]
+kernel = a
+efi = b
+[[entry]]
+kernel = c
+kernel = d
+timeout = 3
+[[section]]
+";
+        let (cfg, errors) = parse(text);
+        assert_eq!(errors, [
+            err(1, ErrorKind::EntryWithoutKernel),
+            err(3, ErrorKind::KernelAndEfi),
+            err(8, ErrorKind::DuplicateKey),
+            err(9, ErrorKind::UnknownKey),
+            err(10, ErrorKind::UnknownSection),
+        ]);
+        assert_eq!(cfg.entries().len(), 1);
+        assert_eq!(cfg.entries()[0].kernel, "c");
+        assert_eq!(cfg.entries()[0].label(), "c");
+        assert_eq!(cfg.timeout, None);
+    }
+
+    #[test]
+    fn too_many_entries() {
+        let mut text = std::string::String::new();
+        for i in 0..MAX_ENTRIES + 2 {
+            text += &std::format!("[[entry]]\nkernel = k{}\n", i);
+        }
+        let (cfg, errors) = parse(&text);
+        assert_eq!(errors, [err(2 * MAX_ENTRIES + 1, ErrorKind::TooManyEntries), err(2 * MAX_ENTRIES + 3, ErrorKind::TooManyEntries)]);
+        assert_eq!(cfg.entries().len(), MAX_ENTRIES);
+        assert_eq!(cfg.entries()[MAX_ENTRIES - 1].kernel, "k15");
+    }
+}
@@ -0,0 +1,234 @@
+/// One meaningful line of a config file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Line<'a> {
+    /// `[name]`
+    Section(&'a str),
+    /// `[[name]]`: starts a new element of an array of tables.
+    ArrayTable(&'a str),
+    /// `key = value`, with surrounding quotes already removed from `value`.
+    Pair(&'a str, &'a str),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    MissingEquals,
+    EmptyKey,
+    InvalidKey,
+    UnterminatedString,
+    TrailingCharacters,
+    UnterminatedSection,
+    UnknownKey,
+    UnknownSection,
+    InvalidNumber,
+    InvalidResolution,
+    InvalidAspect,
+    InvalidLogLevel,
+    InvalidSplash,
+    InvalidBool,
+    DuplicateKey,
+    TooManyEntries,
+    EntryWithoutKernel,
+    KernelAndEfi,
+}
+
+impl ErrorKind {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::MissingEquals => "expected `key = value`",
+            ErrorKind::EmptyKey => "empty key",
+            ErrorKind::InvalidKey => "key may only contain letters, digits, `_` and `-`",
+            ErrorKind::UnterminatedString => "unterminated string",
+            ErrorKind::TrailingCharacters => "unexpected characters after value",
+            ErrorKind::UnterminatedSection => "expected `]`",
+            ErrorKind::UnknownKey => "unknown key",
+            ErrorKind::UnknownSection => "unknown section",
+            ErrorKind::InvalidNumber => "invalid number",
+            ErrorKind::InvalidResolution => "expected resolution as WIDTHxHEIGHT",
+            ErrorKind::InvalidAspect => "expected aspect ratio as W:H",
+            ErrorKind::InvalidLogLevel => "expected one of error, warn, info, debug, trace",
+            ErrorKind::InvalidSplash => "expected one of off, nearest, bilinear",
+            ErrorKind::InvalidBool => "expected true or false",
+            ErrorKind::DuplicateKey => "key set twice",
+            ErrorKind::TooManyEntries => "too many [[entry]] tables",
+            ErrorKind::EntryWithoutKernel => "[[entry]] has no kernel or efi; skipped",
+            ErrorKind::KernelAndEfi => "[[entry]] sets both kernel and efi; skipped",
+        }
+    }
+}
+
+/// A syntax or value error at a 1-based line number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+/// Iterator over the meaningful lines of a config file, yielding the 1-based
+/// line number with each item. Comments and blank lines are skipped.
+pub struct Lines<'a> {
+    rest: &'a str,
+    line: usize,
+}
+
+impl<'a> Lines<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Lines { rest: text, line: 0 }
+    }
+}
+
+fn valid_key(key: &str) -> bool {
+    key.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+/// Drops a `#` comment that is not inside a quoted string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = false;
+    for (i, b) in line.bytes().enumerate() {
+        match b {
+            b'"' => in_str = !in_str,
+            b'#' if !in_str => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(raw: &str) -> Result<&str, ErrorKind> {
+    let raw = raw.trim();
+    if let Some(body) = raw.strip_prefix('"') {
+        let end = body.find('"').ok_or(ErrorKind::UnterminatedString)?;
+        if !body[end + 1..].trim().is_empty() { return Err(ErrorKind::TrailingCharacters); }
+        Ok(&body[..end])
+    } else {
+        Ok(raw)
+    }
+}
+
+fn parse_line(line: &str) -> Result<Option<Line<'_>>, ErrorKind> {
+    let line = strip_comment(line).trim();
+    if line.is_empty() { return Ok(None); }
+
+    if let Some(body) = line.strip_prefix("[[") {
+        let name = body.strip_suffix("]]").ok_or(ErrorKind::UnterminatedSection)?.trim();
+        if name.is_empty() || !valid_key(name) { return Err(ErrorKind::InvalidKey); }
+        return Ok(Some(Line::ArrayTable(name)));
+    }
+    if let Some(body) = line.strip_prefix('[') {
+        let name = body.strip_suffix(']').ok_or(ErrorKind::UnterminatedSection)?.trim();
+        if name.is_empty() || !valid_key(name) { return Err(ErrorKind::InvalidKey); }
+        return Ok(Some(Line::Section(name)));
+    }
+
+    let eq = line.find('=').ok_or(ErrorKind::MissingEquals)?;
+    let key = line[..eq].trim();
+    if key.is_empty() { return Err(ErrorKind::EmptyKey); }
+    if !valid_key(key) { return Err(ErrorKind::InvalidKey); }
+    Ok(Some(Line::Pair(key, parse_value(&line[eq + 1..])?)))
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = (usize, Result<Line<'a>, ErrorKind>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.rest.is_empty() {
+            let (line, rest) = match self.rest.find('\n') {
+                Some(i) => (&self.rest[..i], &self.rest[i + 1..]),
+                None => (self.rest, ""),
+            };
+            self.rest = rest;
+            self.line += 1;
+            match parse_line(line.trim_end_matches('\r')) {
+                Ok(None) => continue,
+                Ok(Some(l)) => return Some((self.line, Ok(l))),
+                Err(e) => return Some((self.line, Err(e))),
+            }
+        }
+        None
+    }
+}
+
+/// Parses a decimal or `0x` hexadecimal integer, with an optional `K`/`M`/`G`
+/// binary suffix (`64K` = 65536).
+pub fn parse_u64(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (num, mul) = match s.as_bytes().last()? {
+        b'K' | b'k' => (&s[..s.len() - 1], 1u64 << 10),
+        b'M' | b'm' => (&s[..s.len() - 1], 1u64 << 20),
+        b'G' | b'g' => (&s[..s.len() - 1], 1u64 << 30),
+        _ => (s, 1),
+    };
+    let v = match num.strip_prefix("0x").or_else(|| num.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => num.parse::<u64>().ok()?,
+    };
+    v.checked_mul(mul)
+}
+
+/// Parses `true`/`false`, also accepting `yes`/`no`, `on`/`off` and `1`/`0`.
+pub fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_sections_and_comments() {
+        let text = "# comment\n\nkernel = \\EFI\\RTOS\\kernel.rtosk\ncmdline = \"a # b\"  # trailing\r\n[video]\n[[entry]]\n";
+        let mut it = Lines::new(text);
+        assert_eq!(it.next(), Some((3, Ok(Line::Pair("kernel", "\\EFI\\RTOS\\kernel.rtosk")))));
+        assert_eq!(it.next(), Some((4, Ok(Line::Pair("cmdline", "a # b")))));
+        assert_eq!(it.next(), Some((5, Ok(Line::Section("video")))));
+        assert_eq!(it.next(), Some((6, Ok(Line::ArrayTable("entry")))));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn syntax_errors_carry_their_line() {
+        let text = "a = 1\nno equals\n = 1\nb c = 1\nd = \"open\ne = \"x\" y\n[sec\n[[arr]\n[]\n";
+        let errors: [(usize, Result<Line, ErrorKind>); 9] = [
+            (1, Ok(Line::Pair("a", "1"))),
+            (2, Err(ErrorKind::MissingEquals)),
+            (3, Err(ErrorKind::EmptyKey)),
+            (4, Err(ErrorKind::InvalidKey)),
+            (5, Err(ErrorKind::UnterminatedString)),
+            (6, Err(ErrorKind::TrailingCharacters)),
+            (7, Err(ErrorKind::UnterminatedSection)),
+            (8, Err(ErrorKind::UnterminatedSection)),
+            (9, Err(ErrorKind::InvalidKey)),
+        ];
+        assert!(Lines::new(text).eq(errors));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_u64("42"), Some(42));
+        assert_eq!(parse_u64(" 0x1F "), Some(0x1F));
+        assert_eq!(parse_u64("64K"), Some(64 << 10));
+        assert_eq!(parse_u64("2m"), Some(2 << 20));
+        assert_eq!(parse_u64("0x10G"), Some(16 << 30));
+        assert_eq!(parse_u64(""), None);
+        assert_eq!(parse_u64("K"), None);
+        assert_eq!(parse_u64("-1"), None);
+        assert_eq!(parse_u64("12x"), None);
+        assert_eq!(parse_u64("0xZZ"), None);
+        assert_eq!(parse_u64("99999999999999999999"), None);
+        assert_eq!(parse_u64("17179869184G"), None);
+    }
+
+    #[test]
+    fn bools() {
+        for s in ["true", "yes", "on", "1"] {
+            assert_eq!(parse_bool(s), Some(true));
+        }
+        for s in ["false", "no", "off", "0"] {
+            assert_eq!(parse_bool(s), Some(false));
+        }
+        assert_eq!(parse_bool("maybe"), None);
+    }
+}
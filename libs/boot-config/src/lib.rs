@@ -0,0 +1,15 @@
+#![no_std]
+
+//! Parser for the bootloader's `\EFI\RTOS\boot.cfg`.
+//!
+//! The format is a small TOML subset: `key = value` lines, `#` comments,
+//! blank lines, and values that are either bare (up to end of line) or
+//! double-quoted. Backslashes are literal, so UEFI paths need no escaping.
+//! `[[entry]]` tables (`title`, `kernel` or `efi`, `cmdline`, `initrd`)
+//! describe boot menu entries. Nothing allocates: parsed strings borrow from the input text.
+
+pub mod lexer;
+pub mod config;
+
+pub use config::{BootConfig, BootEntry, LogLevel, SplashMode, MAX_ENTRIES};
+pub use lexer::{ConfigError, ErrorKind, Line, Lines};
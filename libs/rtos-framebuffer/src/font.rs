@@ -0,0 +1,112 @@
+//! 5×7 bitmap font shared by the bootloader splash and the kernel console.
+
+/// Glyph width in pixels.
+pub const GLYPH_W: u32 = 5;
+/// Glyph height in pixels.
+pub const GLYPH_H: u32 = 7;
+
+/// 5×7 bitmap glyphs (MSB..LSB = left..right).
+/// Minimal but practical set; unknown chars map to '?'.
+pub fn glyph_5x7(ch: char) -> [u8; 7] {
+    match ch {
+        ' ' => [0,0,0,0,0,0,0],
+        '!' => [0b00100,0b00100,0b00100,0b00100,0b00100,0,0b00100],
+        '.' => [0,0,0,0,0,0b00110,0b00110],
+        ',' => [0,0,0,0,0,0b00110,0b00100],
+        ':' => [0,0b00110,0b00110,0,0b00110,0b00110,0],
+        ';' => [0,0b00110,0b00110,0,0b00110,0b00100,0],
+        '-' => [0,0,0,0b11111,0,0,0],
+        '_' => [0,0,0,0,0,0,0b11111],
+        '/' => [0b00001,0b00010,0b00100,0b01000,0b10000,0,0],
+        '\\'=> [0b10000,0b01000,0b00100,0b00010,0b00001,0,0],
+
+        '0' => [0b01110,0b10001,0b10011,0b10101,0b11001,0b10001,0b01110],
+        '1' => [0b00100,0b01100,0b00100,0b00100,0b00100,0b00100,0b01110],
+        '2' => [0b01110,0b10001,0b00001,0b00010,0b00100,0b01000,0b11111],
+        '3' => [0b11110,0b00001,0b00001,0b00110,0b00001,0b00001,0b11110],
+        '4' => [0b00010,0b00110,0b01010,0b10010,0b11111,0b00010,0b00010],
+        '5' => [0b11111,0b10000,0b11110,0b00001,0b00001,0b10001,0b01110],
+        '6' => [0b00110,0b01000,0b10000,0b11110,0b10001,0b10001,0b01110],
+        '7' => [0b11111,0b00001,0b00010,0b00100,0b01000,0b01000,0b01000],
+        '8' => [0b01110,0b10001,0b10001,0b01110,0b10001,0b10001,0b01110],
+        '9' => [0b01110,0b10001,0b10001,0b01111,0b00001,0b00010,0b01100],
+
+        'A' => [0b00100,0b01010,0b10001,0b11111,0b10001,0b10001,0b10001],
+        'B' => [0b11110,0b10001,0b10001,0b11110,0b10001,0b10001,0b11110],
+        'C' => [0b01110,0b10001,0b10000,0b10000,0b10000,0b10001,0b01110],
+        'D' => [0b11110,0b10001,0b10001,0b10001,0b10001,0b10001,0b11110],
+        'E' => [0b11111,0b10000,0b10000,0b11110,0b10000,0b10000,0b11111],
+        'F' => [0b11111,0b10000,0b10000,0b11110,0b10000,0b10000,0b10000],
+        'G' => [0b01110,0b10001,0b10000,0b10111,0b10001,0b10001,0b01110],
+        'H' => [0b10001,0b10001,0b10001,0b11111,0b10001,0b10001,0b10001],
+        'I' => [0b01110,0b00100,0b00100,0b00100,0b00100,0b00100,0b01110],
+        'J' => [0b00111,0b00010,0b00010,0b00010,0b10010,0b10010,0b01100],
+        'K' => [0b10001,0b10010,0b10100,0b11000,0b10100,0b10010,0b10001],
+        'L' => [0b10000,0b10000,0b10000,0b10000,0b10000,0b10000,0b11111],
+        'M' => [0b10001,0b11011,0b10101,0b10101,0b10001,0b10001,0b10001],
+        'N' => [0b10001,0b11001,0b10101,0b10011,0b10001,0b10001,0b10001],
+        'O' => [0b01110,0b10001,0b10001,0b10001,0b10001,0b10001,0b01110],
+        'P' => [0b11110,0b10001,0b10001,0b11110,0b10000,0b10000,0b10000],
+        'Q' => [0b01110,0b10001,0b10001,0b10001,0b10101,0b10010,0b01101],
+        'R' => [0b11110,0b10001,0b10001,0b11110,0b10100,0b10010,0b10001],
+        'S' => [0b01111,0b10000,0b10000,0b01110,0b00001,0b00001,0b11110],
+        'T' => [0b11111,0b00100,0b00100,0b00100,0b00100,0b00100,0b00100],
+        'U' => [0b10001,0b10001,0b10001,0b10001,0b10001,0b10001,0b01110],
+        'V' => [0b10001,0b10001,0b10001,0b10001,0b01010,0b01010,0b00100],
+        'W' => [0b10001,0b10001,0b10101,0b10101,0b10101,0b11011,0b10001],
+        'X' => [0b10001,0b10001,0b01010,0b00100,0b01010,0b10001,0b10001],
+        'Y' => [0b10001,0b10001,0b01010,0b00100,0b00100,0b00100,0b00100],
+        'Z' => [0b11111,0b00001,0b00010,0b00100,0b01000,0b10000,0b11111],
+
+        'a' => [0,0,0b01110,0b00001,0b01111,0b10001,0b01111],
+        'b' => [0b10000,0b10000,0b11110,0b10001,0b10001,0b10001,0b11110],
+        'c' => [0,0,0b01110,0b10000,0b10000,0b10000,0b01110],
+        'd' => [0b00001,0b00001,0b01111,0b10001,0b10001,0b10001,0b01111],
+        'e' => [0,0,0b01110,0b10001,0b11111,0b10000,0b01110],
+        'f' => [0b00110,0b01001,0b01000,0b11100,0b01000,0b01000,0b01000],
+        'g' => [0,0,0b01111,0b10001,0b10001,0b01111,0b00001,],
+        'h' => [0b10000,0b10000,0b11110,0b10001,0b10001,0b10001,0b10001],
+        'i' => [0b00100,0,0b01100,0b00100,0b00100,0b00100,0b01110],
+        'j' => [0b00010,0,0b00110,0b00010,0b00010,0b10010,0b01100],
+        'k' => [0b10000,0b10010,0b10100,0b11000,0b10100,0b10010,0b10001],
+        'l' => [0b01100,0b00100,0b00100,0b00100,0b00100,0b00100,0b01110],
+        'm' => [0,0,0b11010,0b10101,0b10101,0b10101,0b10101],
+        'n' => [0,0,0b11110,0b10001,0b10001,0b10001,0b10001],
+        'o' => [0,0,0b01110,0b10001,0b10001,0b10001,0b01110],
+        'p' => [0,0,0b11110,0b10001,0b10001,0b11110,0b10000],
+        'q' => [0,0,0b01111,0b10001,0b10001,0b01111,0b00001],
+        'r' => [0,0,0b10110,0b11001,0b10000,0b10000,0b10000],
+        's' => [0,0,0b01111,0b10000,0b01110,0b00001,0b11110],
+        't' => [0b01000,0b01000,0b11100,0b01000,0b01000,0b01001,0b00110],
+        'u' => [0,0,0b10001,0b10001,0b10001,0b10011,0b01101],
+        'v' => [0,0,0b10001,0b10001,0b01010,0b01010,0b00100],
+        'w' => [0,0,0b10001,0b10101,0b10101,0b10101,0b01010],
+        'x' => [0,0,0b10001,0b01010,0b00100,0b01010,0b10001],
+        'y' => [0,0,0b10001,0b10001,0b01111,0b00001,0b01110],
+        'z' => [0,0,0b11111,0b00010,0b00100,0b01000,0b11111],
+
+        '(' => [0b00010,0b00100,0b01000,0b01000,0b01000,0b00100,0b00010],
+        ')' => [0b01000,0b00100,0b00010,0b00010,0b00010,0b00100,0b01000],
+        '[' => [0b00110,0b00100,0b00100,0b00100,0b00100,0b00100,0b00110],
+        ']' => [0b01100,0b00100,0b00100,0b00100,0b00100,0b00100,0b01100],
+        '{' => [0b00010,0b00100,0b00100,0b01000,0b00100,0b00100,0b00010],
+        '}' => [0b01000,0b00100,0b00100,0b00010,0b00100,0b00100,0b01000],
+        '<' => [0b00010,0b00100,0b01000,0b01000,0b01000,0b00100,0b00010],
+        '>' => [0b01000,0b00100,0b00010,0b00010,0b00010,0b00100,0b01000],
+        '+' => [0,0b00100,0b00100,0b11111,0b00100,0b00100,0],
+        '*' => [0,0b10101,0b01110,0b11111,0b01110,0b10101,0],
+        '=' => [0,0b11111,0,0b11111,0,0,0],
+        '?' => [0b01110,0b10001,0b00010,0b00100,0b00100,0,0b00100],
+        '\''=> [0b00100,0b00100,0,0,0,0,0],
+        '"' => [0b01010,0b01010,0,0,0,0,0],
+        '|' => [0b00100,0b00100,0b00100,0b00100,0b00100,0b00100,0b00100],
+        '@' => [0b01110,0b10001,0b00001,0b01101,0b10101,0b10101,0b01110],
+        '#' => [0b01010,0b11111,0b01010,0b01010,0b11111,0b01010,0b01010],
+        '%' => [0b11001,0b11010,0b00100,0b01000,0b10110,0b00110,0],
+        '^' => [0b00100,0b01010,0b10001,0,0,0,0],
+        '~' => [0,0b01101,0b10110,0,0,0,0],
+        '`' => [0b01000,0b00100,0,0,0,0,0],
+
+        _ => glyph_5x7('?'),
+    }
+}
@@ -1,9 +1,13 @@
 pub mod mode;
+pub mod shadow;
 
 use crate::framebuffer::mode::aspect::AspectRatio;
-use crate::framebuffer::mode::pick::pick_highest_for_ratio;
+use crate::framebuffer::mode::edid;
+use crate::framebuffer::mode::pick::{list_modes, pick_highest_for_ratio, select};
+use crate::framebuffer::mode::request::ModeRequest;
 
-use uefi::{Error, Identify, Status};
+use log::{debug, info};
+use uefi::{Error, Handle, Identify, Status};
 use uefi::boot::{self, SearchType, ScopedProtocol};
 use uefi::proto::console::gop::{GraphicsOutput, Mode, PixelFormat};
 use rtos_types::{framebuffer_info::FramebufferInfo, framebuffer_format::FramebufferFormat};
@@ -27,15 +31,57 @@ impl Framebuffer {
         Self::init_gop_with_mode(mode)
     }
 
+    /// Initialize a framebuffer from a `ModeRequest`, logging every mode the
+    /// firmware offers. When the request resolves to nothing (see
+    /// `pick::select`) the current mode is kept.
+    pub fn new_from_request(req: ModeRequest) -> Result<FramebufferInfo, Status> {
+        let (handle, mut gop_handle) = Self::open_gop_with_handle()?;
+        let gop: &mut GraphicsOutput = &mut gop_handle;
+
+        for (i, mode) in list_modes(gop).enumerate() {
+            let info = mode.info();
+            let (w, h) = info.resolution();
+            debug!("gop mode {}: {}x{} {:?} stride {}", i, w, h, info.pixel_format(), info.stride());
+        }
+
+        let edid = match req {
+            ModeRequest::EdidPreferred => {
+                let pref = edid::preferred_resolution(handle);
+                match pref {
+                    Some((w, h)) => info!("EDID preferred mode {}x{}", w, h),
+                    None => info!("no EDID preferred mode; keeping current mode"),
+                }
+                pref
+            }
+            _ => None,
+        };
+
+        match select(gop, req, edid) {
+            Some(mode) => {
+                let (w, h) = mode.info().resolution();
+                info!("video {}: setting {}x{}", req, w, h);
+                gop.set_mode(&mode).map_err(|e: Error| e.status())?;
+            }
+            None => info!("video {}: keeping current mode", req),
+        }
+        Self::make_info(gop)
+    }
+
     // ---- internals ---------------------------------------------------------
 
     /// Open the first GraphicsOutput protocol we can find.
-    fn open_gop() -> Result<ScopedProtocol<GraphicsOutput>, Status> {
+    pub(crate) fn open_gop() -> Result<ScopedProtocol<GraphicsOutput>, Status> {
+        Self::open_gop_with_handle().map(|(_, gop)| gop)
+    }
+
+    /// Like `open_gop`, also returning the handle (EDID protocols live there).
+    fn open_gop_with_handle() -> Result<(Handle, ScopedProtocol<GraphicsOutput>), Status> {
         let handles = boot::locate_handle_buffer(SearchType::ByProtocol(&GraphicsOutput::GUID))
             .map_err(|e: Error| e.status())?;
         let handle = *handles.first().ok_or(Status::NOT_FOUND)?;
-        boot::open_protocol_exclusive::<GraphicsOutput>(handle)
-            .map_err(|e: Error| e.status())
+        let gop = boot::open_protocol_exclusive::<GraphicsOutput>(handle)
+            .map_err(|e: Error| e.status())?;
+        Ok((handle, gop))
     }
 
 
@@ -63,25 +109,37 @@ impl Framebuffer {
         Self::make_info(gop)
     }
 
-    /// Build a `FramebufferInfo` from the current GOP mode.
+    /// Build a `FramebufferInfo` from the current GOP mode. Blt-only modes
+    /// get no base address; draw into a `ShadowBuffer` for those instead.
     fn make_info(gop: &mut GraphicsOutput) -> Result<FramebufferInfo, Status> {
         let info = gop.current_mode_info();
         let (w, h) = info.resolution();
-        let mut fb = gop.frame_buffer();
 
-        let fmt = match info.pixel_format() {
-            PixelFormat::Bgr => FramebufferFormat::Bgr,
-            PixelFormat::Rgb => FramebufferFormat::Rgb,
-            _ => FramebufferFormat::BltOnly,
+        let (fmt, (r, g, b, x)) = match (info.pixel_format(), info.pixel_bitmask()) {
+            (PixelFormat::Rgb, _) => (FramebufferFormat::Rgb, (0x0000_00FF, 0x0000_FF00, 0x00FF_0000, 0xFF00_0000)),
+            (PixelFormat::Bgr, _) => (FramebufferFormat::Bgr, (0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0xFF00_0000)),
+            (PixelFormat::Bitmask, Some(m)) => (FramebufferFormat::Bitmask, (m.red, m.green, m.blue, m.reserved)),
+            _ => (FramebufferFormat::BltOnly, (0, 0, 0, 0)),
+        };
+        let (base, size) = if fmt == FramebufferFormat::BltOnly {
+            (0, 0)
+        } else {
+            let mut fb = gop.frame_buffer();
+            (fb.as_mut_ptr() as u64, fb.size())
         };
 
         Ok(FramebufferInfo {
-            base:   fb.as_mut_ptr() as u64,
-            size:   fb.size(),
+            base,
+            size,
             width:  w as u32,
             height: h as u32,
             stride: info.stride() as u32, // pixels per scanline
             format: fmt,
+            red_mask: r,
+            green_mask: g,
+            blue_mask: b,
+            reserved_mask: x,
+            bpp: 32, // GOP pixels are always 32-bit
         })
     }
-}
\ No newline at end of file
+}
@@ -45,6 +45,30 @@ impl AspectRatio {
         }
     }
 
+    /// Maps a (numerator, denominator) pair back to a named ratio, reducing it
+    /// first so `1920:1080` is accepted as 16:9 and `1680:1050` as 16:10.
+    pub fn from_tuple(n: u32, d: u32) -> Option<Self> {
+        use AspectRatio::*;
+        if n == 0 || d == 0 { return None; }
+        let (mut a, mut b) = (n, d);
+        while b != 0 { let t = a % b; a = b; b = t; }
+        let ratio = match (n / a, d / a) {
+            (16, 9)  => Ratio16_9,
+            (8, 5)   => Ratio16_10,
+            (7, 3)   => Ratio21_9,
+            (32, 9)  => Ratio32_9,
+            (4, 3)   => Ratio4_3,
+            (5, 4)   => Ratio5_4,
+            (3, 2)   => Ratio3_2,
+            (19, 9)  => Ratio19_9,
+            (20, 9)  => Ratio20_9,
+            (17, 9)  => Ratio17_9,
+            (2, 1)   => Ratio2_1,
+            _ => return None,
+        };
+        Some(ratio)
+    }
+
     /// Update self to the next best fallback and return its tuple.
     /// Returns None when no fallback remains (self becomes Unspecified).
     pub fn get_fallback(&mut self) -> Option<(u32, u32)> {
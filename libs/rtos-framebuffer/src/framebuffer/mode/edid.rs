@@ -0,0 +1,65 @@
+use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams};
+use uefi::proto::unsafe_protocol;
+use uefi::Handle;
+
+/// EFI_EDID_ACTIVE_PROTOCOL: the EDID the display is being driven with.
+#[derive(Debug)]
+#[repr(C)]
+#[unsafe_protocol("bd8c1056-9f36-44ec-92a8-a6337f817986")]
+pub struct EdidActive {
+    size: u32,
+    edid: *const u8,
+}
+
+/// EFI_EDID_DISCOVERED_PROTOCOL: the EDID as read from the display.
+#[derive(Debug)]
+#[repr(C)]
+#[unsafe_protocol("1c0c34f6-d380-41fa-a049-8ad06c1a66aa")]
+pub struct EdidDiscovered {
+    size: u32,
+    edid: *const u8,
+}
+
+const EDID_HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+
+/// Preferred resolution from the EDID on the GOP handle, trying the Active
+/// protocol before Discovered. None when neither is installed or the block
+/// has no usable first detailed timing.
+pub fn preferred_resolution(gop_handle: Handle) -> Option<(u32, u32)> {
+    let params = OpenProtocolParams {
+        handle: gop_handle,
+        agent: boot::image_handle(),
+        controller: None,
+    };
+    // GetProtocol: the GOP driver owns these; we only peek at the bytes.
+    let active = unsafe { boot::open_protocol::<EdidActive>(params, OpenProtocolAttributes::GetProtocol) };
+    if let Ok(p) = active {
+        if let Some(res) = parse_preferred(unsafe { raw_bytes(p.size, p.edid) }) {
+            return Some(res);
+        }
+    }
+    let discovered = unsafe { boot::open_protocol::<EdidDiscovered>(params, OpenProtocolAttributes::GetProtocol) };
+    let p = discovered.ok()?;
+    parse_preferred(unsafe { raw_bytes(p.size, p.edid) })
+}
+
+unsafe fn raw_bytes<'a>(size: u32, ptr: *const u8) -> &'a [u8] {
+    if ptr.is_null() || size == 0 { return &[]; }
+    core::slice::from_raw_parts(ptr, size as usize)
+}
+
+/// Reads the active area of the first detailed timing descriptor (bytes
+/// 54..72 of the base block), which EDID 1.3+ defines as the preferred mode.
+fn parse_preferred(edid: &[u8]) -> Option<(u32, u32)> {
+    if edid.len() < 128 || edid[..8] != EDID_HEADER {
+        return None;
+    }
+    let d = &edid[54..72];
+    if d[0] == 0 && d[1] == 0 {
+        return None; // pixel clock 0: a display descriptor, not a timing
+    }
+    let w = d[2] as u32 | ((d[4] as u32 & 0xF0) << 4);
+    let h = d[5] as u32 | ((d[7] as u32 & 0xF0) << 4);
+    if w == 0 || h == 0 { return None; }
+    Some((w, h))
+}
@@ -1,2 +1,4 @@
 pub mod aspect;
+pub mod edid;
 pub mod pick;
+pub mod request;
@@ -1,5 +1,6 @@
 use uefi::proto::console::gop::{GraphicsOutput, Mode, PixelFormat};
 use crate::framebuffer::mode::aspect::AspectRatio;
+use crate::framebuffer::mode::request::ModeRequest;
 
 /// Collects all available GOP modes into a static slice.
 /// (The Vec is intentionally leaked since the mode list lives for the program lifetime.)
@@ -16,23 +17,48 @@ fn ratio_diff(w: u32, h: u32, target: (u32, u32)) -> u64 {
         .abs_diff((h as u64).saturating_mul(tn as u64))
 }
 
+/// Which pixel formats a pick considers. Every picker tries `Linear` first
+/// and only falls back to Blt-only modes (drawn through a `ShadowBuffer`)
+/// when no directly addressable mode qualifies.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Formats {
+    Linear,
+    BltOnly,
+}
+
+impl Formats {
+    fn accepts(self, mode: &Mode) -> bool {
+        (mode.info().pixel_format() == PixelFormat::BltOnly) == (self == Formats::BltOnly)
+    }
+}
+
+const PASSES: [Formats; 2] = [Formats::Linear, Formats::BltOnly];
+
 /// Picks the highest-resolution GOP mode that matches or approximates
 /// the given `AspectRatio`, with fallback to similar ratios if no mode matches.
 /// Returns the chosen mode and its aspect difference value.
 pub fn pick_highest_for_ratio(
+    gop: &mut GraphicsOutput,
+    aspect: AspectRatio,
+) -> Option<(Mode, u64)> {
+    PASSES.into_iter().find_map(|f| highest_for_ratio(gop, aspect, f))
+}
+
+fn highest_for_ratio(
     gop: &mut GraphicsOutput,
     mut aspect: AspectRatio,
+    formats: Formats,
 ) -> Option<(Mode, u64)> {
     while let Some(target) = aspect.as_tuple() {
         let mut best: Option<(Mode, u64, u64)> = None;
 
         // <— FIXED: create fresh iterator each time
         for mode in list_modes(gop) {
-            let info = mode.info();
-            if info.pixel_format() == PixelFormat::BltOnly {
+            if !formats.accepts(&mode) {
                 continue;
             }
 
+            let info = mode.info();
             let (wu, hu) = info.resolution();
             let diff = ratio_diff(wu as u32, hu as u32, target);
             let area = (wu as u64) * (hu as u64);
@@ -60,8 +86,85 @@ pub fn pick_highest_for_ratio(
 }
 
 
+/// Picks the GOP mode with exactly the given resolution, preferring a
+/// linear framebuffer over a Blt-only mode of the same size.
+pub fn pick_resolution(gop: &mut GraphicsOutput, width: usize, height: usize) -> Option<Mode> {
+    PASSES.into_iter().find_map(|f| {
+        list_modes(gop).find(|m| f.accepts(m) && m.info().resolution() == (width, height))
+    })
+}
+
 /// Picks a specific GOP mode by index (safe lookup).
 /// Returns the mode if valid, or None if out of range or unsupported.
 pub fn pick_mode(gop: &mut GraphicsOutput, index: usize) -> Option<Mode> {
     list_modes(gop).nth(index)
 }
+
+/// Picks the mode nearest to `width`x`height` (sum of the per-axis
+/// distances), preferring the larger mode on a tie and then the lower index.
+pub fn pick_closest(gop: &mut GraphicsOutput, width: u32, height: u32) -> Option<Mode> {
+    PASSES.into_iter().find_map(|f| closest(gop, width, height, f))
+}
+
+fn closest(gop: &mut GraphicsOutput, width: u32, height: u32, formats: Formats) -> Option<Mode> {
+    let mut best: Option<(Mode, u64, u64)> = None;
+    for mode in list_modes(gop) {
+        if !formats.accepts(&mode) {
+            continue;
+        }
+        let (w, h) = mode.info().resolution();
+        let dist = (w as u64).abs_diff(width as u64) + (h as u64).abs_diff(height as u64);
+        let area = (w as u64) * (h as u64);
+        let better = match best {
+            None => true,
+            Some((_, bd, ba)) => dist < bd || (dist == bd && area > ba),
+        };
+        if better {
+            best = Some((mode, dist, area));
+        }
+    }
+    best.map(|(m, _, _)| m)
+}
+
+/// Picks the largest-area mode no bigger than `max_w`x`max_h`, lower index first on a tie.
+pub fn pick_max_area(gop: &mut GraphicsOutput, max_w: u32, max_h: u32) -> Option<Mode> {
+    PASSES.into_iter().find_map(|f| max_area(gop, max_w, max_h, f))
+}
+
+fn max_area(gop: &mut GraphicsOutput, max_w: u32, max_h: u32, formats: Formats) -> Option<Mode> {
+    let mut best: Option<(Mode, u64)> = None;
+    for mode in list_modes(gop) {
+        if !formats.accepts(&mode) {
+            continue;
+        }
+        let (w, h) = mode.info().resolution();
+        if w as u64 > max_w as u64 || h as u64 > max_h as u64 {
+            continue;
+        }
+        let area = (w as u64) * (h as u64);
+        if best.as_ref().is_none_or(|&(_, ba)| area > ba) {
+            best = Some((mode, area));
+        }
+    }
+    best.map(|(m, _)| m)
+}
+
+/// Resolves a `ModeRequest` to a mode, following its fallback chain:
+/// exact → closest; EDID preferred → exact → closest; max area and aspect
+/// stand alone. None means "keep the current mode", which is also what
+/// `Current` and an EDID-less display end up with.
+pub fn select(gop: &mut GraphicsOutput, req: ModeRequest, edid: Option<(u32, u32)>) -> Option<Mode> {
+    match req {
+        ModeRequest::Exact(w, h) => {
+            pick_resolution(gop, w as usize, h as usize).or_else(|| pick_closest(gop, w, h))
+        }
+        ModeRequest::Closest(w, h) => pick_closest(gop, w, h),
+        ModeRequest::MaxArea(w, h) => pick_max_area(gop, w, h),
+        ModeRequest::Aspect(a) => pick_highest_for_ratio(gop, a).map(|(m, _)| m),
+        ModeRequest::Current => None,
+        ModeRequest::EdidPreferred => {
+            let (w, h) = edid?;
+            select(gop, ModeRequest::Exact(w, h), None)
+        }
+    }
+}
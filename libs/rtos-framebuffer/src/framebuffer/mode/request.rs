@@ -0,0 +1,71 @@
+use core::fmt;
+use crate::framebuffer::mode::aspect::AspectRatio;
+
+/// Which GOP mode to switch to. Written as text in boot.cfg (`video = ...`)
+/// or on the command line (`video=...`); see `ModeRequest::parse`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ModeRequest {
+    /// `1920x1080`: that exact resolution, else the closest one.
+    Exact(u32, u32),
+    /// `~1920x1080`: the mode nearest to that resolution.
+    Closest(u32, u32),
+    /// `max` or `max:2560x1440`: the largest mode that fits within the limit.
+    MaxArea(u32, u32),
+    /// `16:9`: the largest mode for the ratio, walking the ratio's fallbacks.
+    Aspect(AspectRatio),
+    /// `current`: leave the firmware's mode alone.
+    Current,
+    /// `edid`: the display's preferred timing, else the current mode.
+    EdidPreferred,
+}
+
+fn parse_pair(s: &str, sep: char) -> Option<(u32, u32)> {
+    let (a, b) = s.split_once(sep)?;
+    let a = a.trim().parse::<u32>().ok()?;
+    let b = b.trim().parse::<u32>().ok()?;
+    if a == 0 || b == 0 { return None; }
+    Some((a, b))
+}
+
+impl ModeRequest {
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let req = match s {
+            "current" => ModeRequest::Current,
+            "edid" => ModeRequest::EdidPreferred,
+            "max" => ModeRequest::MaxArea(u32::MAX, u32::MAX),
+            _ => {
+                if let Some(limit) = s.strip_prefix("max:") {
+                    let (w, h) = parse_pair(limit, 'x')?;
+                    ModeRequest::MaxArea(w, h)
+                } else if let Some(res) = s.strip_prefix('~') {
+                    let (w, h) = parse_pair(res, 'x')?;
+                    ModeRequest::Closest(w, h)
+                } else if let Some((w, h)) = parse_pair(s, 'x') {
+                    ModeRequest::Exact(w, h)
+                } else {
+                    let (n, d) = parse_pair(s, ':')?;
+                    ModeRequest::Aspect(AspectRatio::from_tuple(n, d)?)
+                }
+            }
+        };
+        Some(req)
+    }
+}
+
+impl fmt::Display for ModeRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ModeRequest::Exact(w, h) => write!(f, "{}x{}", w, h),
+            ModeRequest::Closest(w, h) => write!(f, "~{}x{}", w, h),
+            ModeRequest::MaxArea(u32::MAX, u32::MAX) => f.write_str("max"),
+            ModeRequest::MaxArea(w, h) => write!(f, "max:{}x{}", w, h),
+            ModeRequest::Aspect(a) => match a.as_tuple() {
+                Some((n, d)) => write!(f, "{}:{}", n, d),
+                None => f.write_str("aspect"),
+            },
+            ModeRequest::Current => f.write_str("current"),
+            ModeRequest::EdidPreferred => f.write_str("edid"),
+        }
+    }
+}
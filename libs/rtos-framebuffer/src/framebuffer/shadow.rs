@@ -0,0 +1,73 @@
+use core::ptr::NonNull;
+use uefi::{boot, Error, Status};
+use uefi::boot::AllocateType;
+use uefi::mem::memory_map::MemoryType;
+use uefi::proto::console::gop::{BltOp, BltPixel, BltRegion};
+use crate::framebuffer::Framebuffer;
+use rtos_types::{framebuffer_info::FramebufferInfo, framebuffer_format::FramebufferFormat};
+
+/// RAM copy of a Blt-only screen. Draw into `info()` like a linear
+/// framebuffer, then `present` the changed area with `GraphicsOutput::blt`.
+/// Only usable while boot services are alive; the pages are freed on drop.
+pub struct ShadowBuffer {
+    ptr: NonNull<u8>,
+    pages: usize,
+    width: u32,
+    height: u32,
+}
+
+impl ShadowBuffer {
+    pub fn new(width: u32, height: u32) -> Result<Self, Status> {
+        let bytes = width as usize * height as usize * 4;
+        let pages = bytes.div_ceil(4096).max(1);
+        let ptr = boot::allocate_pages(AllocateType::AnyPages, MemoryType::LOADER_DATA, pages)
+            .map_err(|e: Error| e.status())?;
+        Ok(ShadowBuffer { ptr, pages, width, height })
+    }
+
+    /// The buffer as a framebuffer in BltPixel order (BGR, 32-bit, unpadded rows).
+    pub fn info(&self) -> FramebufferInfo {
+        FramebufferInfo {
+            base: self.ptr.as_ptr() as u64,
+            size: self.width as usize * self.height as usize * 4,
+            width: self.width,
+            height: self.height,
+            stride: self.width,
+            format: FramebufferFormat::Bgr,
+            red_mask: 0x00FF_0000,
+            green_mask: 0x0000_FF00,
+            blue_mask: 0x0000_00FF,
+            reserved_mask: 0xFF00_0000,
+            bpp: 32,
+        }
+    }
+
+    /// Copies the `w`x`h` rectangle at (`x`, `y`) to the screen.
+    pub fn present(&self, x: u32, y: u32, w: u32, h: u32) -> Result<(), Status> {
+        let x_end = (x.saturating_add(w)).min(self.width);
+        let y_end = (y.saturating_add(h)).min(self.height);
+        if x >= x_end || y >= y_end {
+            return Ok(());
+        }
+
+        let mut gop = Framebuffer::open_gop()?;
+
+        // BltPixel is repr(C) blue/green/red/reserved: the Bgr u32 layout above.
+        let pixels = unsafe {
+            core::slice::from_raw_parts(self.ptr.as_ptr() as *const BltPixel, (self.width * self.height) as usize)
+        };
+        gop.blt(BltOp::BufferToVideo {
+            buffer: pixels,
+            src: BltRegion::SubRectangle { coords: (x as usize, y as usize), px_stride: self.width as usize },
+            dest: (x as usize, y as usize),
+            dims: ((x_end - x) as usize, (y_end - y) as usize),
+        })
+        .map_err(|e: Error| e.status())
+    }
+}
+
+impl Drop for ShadowBuffer {
+    fn drop(&mut self) {
+        let _ = unsafe { boot::free_pages(self.ptr, self.pages) };
+    }
+}
@@ -1,2 +1,4 @@
 #![no_std]
-pub mod framebuffer;
\ No newline at end of file
+pub mod framebuffer;
+pub mod font;
+pub mod pixel;
@@ -0,0 +1,159 @@
+//! Pixel packing and alpha blending for RGB, BGR and bitmask framebuffers
+//! of 15 to 32 bpp. Shared by the bootloader splash and the kernel's
+//! framebuffer code.
+
+use rtos_types::framebuffer_format::FramebufferFormat;
+use rtos_types::framebuffer_info::FramebufferInfo;
+
+#[inline]
+pub fn pack_rgb_fmt(fmt: FramebufferFormat, r: u8, g: u8, b: u8) -> u32 {
+    match fmt {
+        FramebufferFormat::Rgb => (r as u32) | ((g as u32) << 8) | ((b as u32) << 16),
+        FramebufferFormat::Bgr => (b as u32) | ((g as u32) << 8) | ((r as u32) << 16),
+        _ => 0,
+    }
+}
+
+#[inline]
+pub fn unpack_rgb_fmt(fmt: FramebufferFormat, px: u32) -> (u8, u8, u8) {
+    match fmt {
+        FramebufferFormat::Rgb => (
+            (px & 0xFF) as u8,
+            ((px >> 8) & 0xFF) as u8,
+            ((px >> 16) & 0xFF) as u8,
+        ),
+        _ => (
+            ((px >> 16) & 0xFF) as u8,
+            ((px >> 8) & 0xFF) as u8,
+            (px & 0xFF) as u8,
+        ),
+    }
+}
+
+/// Source-over blend of a straight-alpha RGBA color onto a packed 32-bit
+/// Rgb/Bgr pixel.
+#[inline]
+pub fn blend_over(dst: u32, fmt: FramebufferFormat, r: u8, g: u8, b: u8, a: u8) -> u32 {
+    if a == 0 { return dst; }
+    if a == 255 { return pack_rgb_fmt(fmt, r, g, b); }
+
+    let (dr, dg, db) = unpack_rgb_fmt(fmt, dst);
+    pack_rgb_fmt(fmt, mix(r, dr, a), mix(g, dg, a), mix(b, db, a))
+}
+
+/// Per-channel placement within a pixel, taken from the masks in
+/// `FramebufferInfo`, so Rgb, Bgr and bitmask layouts of any depth
+/// (15/16/24/32 bpp) share one path.
+#[derive(Copy, Clone, Debug)]
+pub struct Channels {
+    red: (u32, u32),   // (shift, bits)
+    green: (u32, u32),
+    blue: (u32, u32),
+}
+
+/// (shift, bits) of the top 8 or fewer bits of a contiguous channel mask;
+/// the low bits of wider (e.g. 10-bit) channels are left at zero.
+fn channel(mask: u32) -> (u32, u32) {
+    if mask == 0 { return (0, 0); }
+    let shift = mask.trailing_zeros();
+    let width = (mask >> shift).trailing_ones();
+    (shift + width.saturating_sub(8), width.min(8))
+}
+
+#[inline]
+fn put_channel(v: u8, (shift, bits): (u32, u32)) -> u32 {
+    if bits == 0 { return 0; }
+    ((v as u32) >> (8 - bits)) << shift
+}
+
+#[inline]
+fn get_channel(px: u32, (shift, bits): (u32, u32)) -> u8 {
+    if bits == 0 { return 0; }
+    let max = (1u32 << bits) - 1;
+    // Rescale so a full-scale channel reads back as 255.
+    ((((px >> shift) & max) * 255 + max / 2) / max) as u8
+}
+
+impl Channels {
+    /// `FramebufferFormat::Rgb`: red in the low byte.
+    pub const RGB: Channels = Channels { red: (0, 8), green: (8, 8), blue: (16, 8) };
+    /// `FramebufferFormat::Bgr`, which is also the `BltPixel` layout.
+    pub const BGR: Channels = Channels { red: (16, 8), green: (8, 8), blue: (0, 8) };
+
+    /// None for Blt-only modes, which have no pixel layout in memory.
+    pub fn of(info: &FramebufferInfo) -> Option<Self> {
+        match info.format {
+            FramebufferFormat::Rgb => Some(Channels::RGB),
+            FramebufferFormat::Bgr => Some(Channels::BGR),
+            FramebufferFormat::Bitmask => Some(Channels {
+                red: channel(info.red_mask),
+                green: channel(info.green_mask),
+                blue: channel(info.blue_mask),
+            }),
+            FramebufferFormat::BltOnly => None,
+        }
+    }
+
+    #[inline]
+    pub fn pack(&self, r: u8, g: u8, b: u8) -> u32 {
+        put_channel(r, self.red) | put_channel(g, self.green) | put_channel(b, self.blue)
+    }
+
+    #[inline]
+    pub fn unpack(&self, px: u32) -> (u8, u8, u8) {
+        (get_channel(px, self.red), get_channel(px, self.green), get_channel(px, self.blue))
+    }
+
+    /// `blend_over` for any channel layout.
+    #[inline]
+    pub fn blend_over(&self, dst: u32, r: u8, g: u8, b: u8, a: u8) -> u32 {
+        if a == 0 { return dst; }
+        if a == 255 { return self.pack(r, g, b); }
+        let (dr, dg, db) = self.unpack(dst);
+        self.pack(mix(r, dr, a), mix(g, dg, a), mix(b, db, a))
+    }
+}
+
+/// `s` over `d` at alpha `a`; integer round-nearest via the *257 >> 16 trick.
+#[inline]
+fn mix(s: u8, d: u8, a: u8) -> u8 {
+    let (s, d, a) = (s as u32, d as u32, a as u32);
+    (((s * a + d * (255 - a) + 127) * 257) >> 16) as u8
+}
+
+/// Stores the low `bytes` (1..=4) bytes of a packed pixel at `p`, little
+/// endian. Volatile, since `p` is normally video memory.
+///
+/// # Safety
+/// `p..p + bytes` must be writable.
+#[inline]
+pub unsafe fn write_pixel(p: *mut u8, bytes: usize, px: u32) {
+    match bytes {
+        4 => core::ptr::write_volatile(p as *mut u32, px),
+        2 => core::ptr::write_volatile(p as *mut u16, px as u16),
+        _ => {
+            for i in 0..bytes {
+                core::ptr::write_volatile(p.add(i), (px >> (8 * i)) as u8);
+            }
+        }
+    }
+}
+
+/// Loads a packed pixel of `bytes` (1..=4) bytes from `p`.
+///
+/// # Safety
+/// `p..p + bytes` must be readable.
+#[inline]
+pub unsafe fn read_pixel(p: *const u8, bytes: usize) -> u32 {
+    match bytes {
+        4 => core::ptr::read_volatile(p as *const u32),
+        2 => core::ptr::read_volatile(p as *const u16) as u32,
+        _ => {
+            let mut px = 0;
+            for i in 0..bytes {
+                px |= (core::ptr::read_volatile(p.add(i)) as u32) << (8 * i);
+            }
+            px
+        }
+    }
+}
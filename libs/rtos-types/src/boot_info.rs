@@ -1,9 +1,40 @@
+use crate::boot_log::BootLog;
+use crate::boot_module::BootModule;
+use crate::memory_map::{MemoryRegion, RuntimeRegion};
 use crate::framebuffer_info::FramebufferInfo;
+use crate::firmware_info::FirmwareInfo;
+use crate::boot_slot::BOOT_SLOT_NONE;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct BootInfo {
     pub framebuffer: FramebufferInfo,
+    /// Physical address of the kernel command line (ASCII, not NUL-terminated).
+    pub cmdline_ptr: u64,
+    /// Length of the command line in bytes; 0 if none was given.
+    pub cmdline_len: u64,
+    /// Physical address of a `[BootModule; module_count]` table.
+    pub modules_ptr: u64,
+    pub module_count: u64,
+    /// Physical address of the bootloader's `BootLog` region; 0 if none.
+    pub boot_log_ptr: u64,
+    /// Physical address of the final `[MemoryRegion; memory_map_len]` table,
+    /// taken at ExitBootServices.
+    pub memory_map_ptr: u64,
+    pub memory_map_len: u64,
+    /// Firmware vendor, UEFI revision, Secure Boot state and table pointers.
+    pub firmware: FirmwareInfo,
+    /// A/B slot the kernel was booted from (`BOOT_SLOT_*`); `BOOT_SLOT_NONE`
+    /// without slots in boot.cfg.
+    pub boot_slot: u32,
+    /// Unconfirmed boots of `boot_slot` left before the bootloader rolls back.
+    pub boot_tries_left: u32,
+    /// Physical address of the UEFI runtime services table; 0 if unknown.
+    pub runtime_services: u64,
+    /// Physical address of a `[RuntimeRegion; runtime_map_len]` table: the
+    /// final map's runtime ranges, taken at ExitBootServices.
+    pub runtime_map_ptr: u64,
+    pub runtime_map_len: u64,
 }
 
 impl BootInfo {
@@ -12,6 +43,19 @@ impl BootInfo {
     pub const fn empty() -> Self {
         BootInfo {
             framebuffer: FramebufferInfo::empty(),
+            cmdline_ptr: 0,
+            cmdline_len: 0,
+            modules_ptr: 0,
+            module_count: 0,
+            boot_log_ptr: 0,
+            memory_map_ptr: 0,
+            memory_map_len: 0,
+            firmware: FirmwareInfo::empty(),
+            boot_slot: BOOT_SLOT_NONE,
+            boot_tries_left: 0,
+            runtime_services: 0,
+            runtime_map_ptr: 0,
+            runtime_map_len: 0,
         }
     }
 
@@ -20,6 +64,19 @@ impl BootInfo {
     pub const fn from_framebuffer(info: FramebufferInfo) -> Self {
         BootInfo {
             framebuffer: info,
+            cmdline_ptr: 0,
+            cmdline_len: 0,
+            modules_ptr: 0,
+            module_count: 0,
+            boot_log_ptr: 0,
+            memory_map_ptr: 0,
+            memory_map_len: 0,
+            firmware: FirmwareInfo::empty(),
+            boot_slot: BOOT_SLOT_NONE,
+            boot_tries_left: 0,
+            runtime_services: 0,
+            runtime_map_ptr: 0,
+            runtime_map_len: 0,
         }
     }
 
@@ -34,6 +91,59 @@ impl BootInfo {
     pub const fn framebuffer(&self) -> &FramebufferInfo {
         &self.framebuffer
     }
+
+    /// Returns what the bootloader learned about the firmware.
+    #[inline]
+    pub const fn firmware(&self) -> &FirmwareInfo {
+        &self.firmware
+    }
+
+    /// Returns the kernel command line ("" if absent or not valid UTF-8).
+    ///
+    /// # Safety
+    /// `cmdline_ptr..cmdline_ptr + cmdline_len` must be mapped and unmodified
+    /// for the returned lifetime.
+    pub unsafe fn cmdline(&self) -> &str {
+        if self.cmdline_ptr == 0 || self.cmdline_len == 0 { return ""; }
+        let bytes = core::slice::from_raw_parts(self.cmdline_ptr as *const u8, self.cmdline_len as usize);
+        core::str::from_utf8(bytes).unwrap_or("")
+    }
+
+    /// Returns the boot module table (empty if none were loaded).
+    ///
+    /// # Safety
+    /// `modules_ptr` must point to `module_count` valid, mapped descriptors.
+    pub unsafe fn modules(&self) -> &[BootModule] {
+        if self.modules_ptr == 0 || self.module_count == 0 { return &[]; }
+        core::slice::from_raw_parts(self.modules_ptr as *const BootModule, self.module_count as usize)
+    }
+
+    /// Returns the physical memory map (empty if none was passed).
+    ///
+    /// # Safety
+    /// `memory_map_ptr` must point to `memory_map_len` valid, mapped regions.
+    pub unsafe fn memory_map(&self) -> &[MemoryRegion] {
+        if self.memory_map_ptr == 0 || self.memory_map_len == 0 { return &[]; }
+        core::slice::from_raw_parts(self.memory_map_ptr as *const MemoryRegion, self.memory_map_len as usize)
+    }
+
+    /// Returns the firmware's runtime ranges (empty if none were passed).
+    ///
+    /// # Safety
+    /// `runtime_map_ptr` must point to `runtime_map_len` valid, mapped regions.
+    pub unsafe fn runtime_map(&self) -> &[RuntimeRegion] {
+        if self.runtime_map_ptr == 0 || self.runtime_map_len == 0 { return &[]; }
+        core::slice::from_raw_parts(self.runtime_map_ptr as *const RuntimeRegion, self.runtime_map_len as usize)
+    }
+
+    /// Returns the bootloader's message log, if it handed one over.
+    ///
+    /// # Safety
+    /// `boot_log_ptr` must point to a mapped `BootLog` region.
+    pub unsafe fn boot_log(&self) -> Option<BootLog> {
+        if self.boot_log_ptr == 0 { return None; }
+        Some(BootLog::from_addr(self.boot_log_ptr))
+    }
 }
 
 impl Default for BootInfo {
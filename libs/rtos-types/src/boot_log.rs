@@ -0,0 +1,94 @@
+use core::{mem, ptr, slice};
+
+/// Start of a boot log region; `capacity` bytes of ring data follow it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct BootLogHeader {
+    /// Size of the ring in bytes.
+    pub capacity: u64,
+    /// Total bytes ever pushed; `written % capacity` is the next write offset.
+    pub written: u64,
+}
+
+/// Ring buffer of boot messages, filled by the bootloader and read by the
+/// kernel (`dmesg`). Once full, the oldest text is overwritten.
+///
+/// The region lives in `RTOS_BOOT_LOG_MEMORY_TYPE` pages so the kernel can
+/// keep it out of its free pool until the log has been consumed.
+pub struct BootLog {
+    base: *mut u8,
+}
+
+impl BootLog {
+    pub const HEADER_LEN: usize = mem::size_of::<BootLogHeader>();
+
+    /// Sets up an empty log over `len` bytes at `base`.
+    ///
+    /// # Safety
+    /// `base` must be 8-byte aligned, writable for `len` bytes, and owned by
+    /// the log for as long as it (or a `from_addr` view of it) is used.
+    pub unsafe fn init(base: *mut u8, len: usize) -> Self {
+        let capacity = len.saturating_sub(Self::HEADER_LEN) as u64;
+        ptr::write(base as *mut BootLogHeader, BootLogHeader { capacity, written: 0 });
+        BootLog { base }
+    }
+
+    /// A view of a log set up by `init`, e.g. from `BootInfo::boot_log_ptr`.
+    ///
+    /// # Safety
+    /// `addr` must point to an initialized log region that stays mapped.
+    pub unsafe fn from_addr(addr: u64) -> Self {
+        BootLog { base: addr as *mut u8 }
+    }
+
+    pub fn addr(&self) -> u64 {
+        self.base as u64
+    }
+
+    fn header(&self) -> BootLogHeader {
+        unsafe { ptr::read(self.base as *const BootLogHeader) }
+    }
+
+    fn data(&self) -> *mut u8 {
+        unsafe { self.base.add(Self::HEADER_LEN) }
+    }
+
+    /// Appends `bytes`, overwriting the oldest text when full.
+    pub fn push(&mut self, bytes: &[u8]) {
+        let h = self.header();
+        let cap = h.capacity as usize;
+        if cap == 0 { return; }
+        // Only the last `cap` bytes of an oversized message can survive.
+        let skipped = bytes.len().saturating_sub(cap);
+        let src = &bytes[skipped..];
+        let mut off = ((h.written + skipped as u64) % h.capacity) as usize;
+        for chunk in [&src[..src.len().min(cap - off)], &src[src.len().min(cap - off)..]] {
+            unsafe { ptr::copy_nonoverlapping(chunk.as_ptr(), self.data().add(off), chunk.len()); }
+            off = 0;
+        }
+        let written = h.written + bytes.len() as u64;
+        unsafe { ptr::addr_of_mut!((*(self.base as *mut BootLogHeader)).written).write(written); }
+    }
+
+    /// The stored text, oldest first, as two pieces; the second is empty
+    /// until the ring has wrapped.
+    pub fn contents(&self) -> (&[u8], &[u8]) {
+        let h = self.header();
+        let cap = h.capacity as usize;
+        let data = self.data() as *const u8;
+        unsafe {
+            if h.written <= h.capacity {
+                (slice::from_raw_parts(data, h.written as usize), &[])
+            } else {
+                let head = (h.written % h.capacity) as usize;
+                (slice::from_raw_parts(data.add(head), cap - head), slice::from_raw_parts(data, head))
+            }
+        }
+    }
+
+    /// Bytes lost to wrap-around.
+    pub fn overwritten(&self) -> u64 {
+        let h = self.header();
+        h.written.saturating_sub(h.capacity)
+    }
+}
@@ -0,0 +1,32 @@
+/// A file the bootloader loaded next to the kernel (initrd, config blobs, ...).
+///
+/// The table of these lives in a page of `RTOS_MODULE_MEMORY_TYPE` memory, as
+/// do the file contents, so the kernel can keep them out of its free pool.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct BootModule {
+    /// Physical address of the file contents (page aligned).
+    pub base: u64,
+    /// File size in bytes.
+    pub size: u64,
+    /// Path the file was loaded from, NUL-padded (truncated if longer).
+    pub name: [u8; BootModule::NAME_LEN],
+}
+
+impl BootModule {
+    pub const NAME_LEN: usize = 48;
+
+    /// Constructs a descriptor, truncating `name` to `NAME_LEN` bytes.
+    pub fn new(base: u64, size: u64, name: &str) -> Self {
+        let mut buf = [0u8; Self::NAME_LEN];
+        let n = name.len().min(Self::NAME_LEN);
+        buf[..n].copy_from_slice(&name.as_bytes()[..n]);
+        BootModule { base, size, name: buf }
+    }
+
+    /// The name up to the first NUL ("" if not valid UTF-8).
+    pub fn name(&self) -> &str {
+        let end = self.name.iter().position(|&b| b == 0).unwrap_or(Self::NAME_LEN);
+        core::str::from_utf8(&self.name[..end]).unwrap_or("")
+    }
+}
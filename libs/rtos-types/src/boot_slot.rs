@@ -0,0 +1,71 @@
+/// Vendor GUID of the bootloader's NV variables (`RtosLastBoot`,
+/// `RtosBootSlot`).
+pub const RTOS_VARIABLE_VENDOR: &str = "6d1f7a3e-2b4c-4e8a-9c51-3f0a7b2d9e14";
+
+/// Name of the UEFI variable holding a `BootSlotState`.
+pub const BOOT_SLOT_VARIABLE: &str = "RtosBootSlot";
+
+/// Value of `BootInfo::boot_slot` when boot.cfg defines no slots.
+pub const BOOT_SLOT_NONE: u32 = 0;
+pub const BOOT_SLOT_A: u32 = 1;
+pub const BOOT_SLOT_B: u32 = 2;
+
+/// Contents of the `RtosBootSlot` variable, shared by the bootloader, the
+/// kernel and update tools.
+///
+/// An update installs a kernel in the inactive slot and writes
+/// `{ active: that slot, tries_left: 0xFF, good: 0 }`. The bootloader takes
+/// one try per boot of a slot that isn't `good` (0xFF becomes boot.cfg
+/// `boot_tries`), and switches to the other slot when none are left. The
+/// kernel sets `good` once it has come up.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BootSlotState {
+    /// `BOOT_SLOT_A` or `BOOT_SLOT_B`.
+    pub active: u8,
+    /// Unconfirmed boots left before the bootloader rolls back.
+    pub tries_left: u8,
+    /// 1 once a kernel from `active` has finished initialising.
+    pub good: u8,
+    pub reserved: u8,
+}
+
+impl BootSlotState {
+    pub const SIZE: usize = 4;
+    /// `tries_left` value meaning "not counted yet; use the configured number".
+    pub const TRIES_UNSET: u8 = 0xFF;
+
+    /// A slot nobody has confirmed yet, with `tries` boots to prove itself.
+    pub const fn fresh(active: u32, tries: u8) -> Self {
+        BootSlotState { active: active as u8, tries_left: tries, good: 0, reserved: 0 }
+    }
+
+    /// Parses the variable's contents; `None` for a wrong size or slot.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let &[active, tries_left, good, reserved] = bytes else { return None };
+        if active as u32 != BOOT_SLOT_A && active as u32 != BOOT_SLOT_B { return None; }
+        Some(BootSlotState { active, tries_left, good, reserved })
+    }
+
+    pub const fn to_bytes(&self) -> [u8; Self::SIZE] {
+        [self.active, self.tries_left, self.good, self.reserved]
+    }
+
+    pub const fn is_good(&self) -> bool {
+        self.good != 0
+    }
+}
+
+/// The slot the other one falls back to.
+pub const fn other_slot(slot: u32) -> u32 {
+    if slot == BOOT_SLOT_B { BOOT_SLOT_A } else { BOOT_SLOT_B }
+}
+
+/// "A", "B", or "-" for `BOOT_SLOT_NONE`.
+pub const fn slot_name(slot: u32) -> &'static str {
+    match slot {
+        BOOT_SLOT_A => "A",
+        BOOT_SLOT_B => "B",
+        _ => "-",
+    }
+}
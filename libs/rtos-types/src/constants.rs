@@ -1,2 +1,21 @@
 pub const RTOSK_MAGIC: [u8; 5] = *b"RTOSK";
-pub const RTOSK_EXEC_FLAG: u32 = 1 << 0;
\ No newline at end of file
+pub const RTOSK_EXEC_FLAG: u32 = 1 << 0;
+pub const RTOSK_READ_FLAG: u32 = 1 << 1;
+pub const RTOSK_WRITE_FLAG: u32 = 1 << 2;
+/// Segment has no file payload; the loader only allocates and zero-fills it.
+pub const RTOSK_NOLOAD: u32 = 1 << 3;
+/// Segment describes the TLS template; it is not mapped on its own.
+pub const RTOSK_TLS: u32 = 1 << 4;
+/// Segment payload is an LZ4 block of `packed_size` bytes that decompresses to `file_size`.
+pub const RTOSK_COMPRESSED: u32 = 1 << 5;
+
+/// Header flag: image ends with an `RtoskSignature` block.
+pub const RTOSK_HDR_SIGNED: u32 = 1 << 0;
+pub const RTOSK_SIG_MAGIC: [u8; 8] = *b"RTOSKSIG";
+
+/// OS-defined UEFI memory type for boot modules (initrd) and their table.
+/// Sits in the 0x8000_0000.. range the spec reserves for OS loaders.
+pub const RTOS_MODULE_MEMORY_TYPE: u32 = 0x8000_0001;
+
+/// OS-defined UEFI memory type for the bootloader's message ring (`BootLog`).
+pub const RTOS_BOOT_LOG_MEMORY_TYPE: u32 = 0x8000_0002;
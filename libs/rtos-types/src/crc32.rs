@@ -0,0 +1,33 @@
+/// CRC-32 (IEEE 802.3, reflected, poly 0xEDB88320) as used for `RtoskHeader::image_crc32`.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !crc32_update(0xFFFF_FFFF, bytes)
+}
+
+/// Feeds `bytes` into a running (non-inverted) CRC state; start from `0xFFFF_FFFF`
+/// and invert the final value. Lets callers hash an image in pieces.
+pub fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for &b in bytes {
+        let mut x = (crc ^ (b as u32)) & 0xFF;
+        for _ in 0..8 {
+            let m = (x & 1).wrapping_neg();
+            x = (x >> 1) ^ (0xEDB88320 & m);
+        }
+        crc = (crc >> 8) ^ x;
+    }
+    crc
+}
+
+/// Byte offset of `image_crc32` inside the on-disk header; the CRC is computed
+/// over the whole image with this field zeroed.
+pub const RTOSK_CRC_FIELD_OFFSET: usize = core::mem::offset_of!(crate::rtosk_header::RtoskHeader, image_crc32);
+
+/// Computes the image CRC of an RTOSK image (starting at the magic), treating the
+/// stored `image_crc32` field as zero. A trailing signature block is not covered.
+pub fn rtosk_image_crc32(image: &[u8]) -> u32 {
+    let field = RTOSK_CRC_FIELD_OFFSET;
+    if image.len() < field + 4 { return crc32(image); }
+    let image = &image[..crate::rtosk_header::signed_len(image)];
+    let crc = crc32_update(0xFFFF_FFFF, &image[..field]);
+    let crc = crc32_update(crc, &[0u8; 4]);
+    !crc32_update(crc, &image[field + 4..])
+}
@@ -0,0 +1,90 @@
+/// Facts about the firmware the bootloader gathered before ExitBootServices,
+/// for the kernel to report and to find the ACPI and SMBIOS tables.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct FirmwareInfo {
+    /// UEFI specification revision, `major << 16 | minor` (2.70 = 0x0002_0046).
+    pub uefi_revision: u32,
+    /// Vendor-defined firmware revision.
+    pub firmware_revision: u32,
+    /// Firmware vendor (ASCII, NUL-padded, truncated if longer).
+    pub vendor: [u8; FirmwareInfo::VENDOR_LEN],
+    /// Physical address of the ACPI RSDP; 0 if the firmware has none.
+    pub acpi_rsdp: u64,
+    /// Physical address of the SMBIOS entry point; 0 if the firmware has none.
+    pub smbios_entry: u64,
+    /// ACPI RSDP revision: 2 for ACPI 2.0+ (XSDT), 1 for ACPI 1.0, 0 if absent.
+    pub acpi_version: u8,
+    /// SMBIOS entry point major version: 3 (64-bit), 2 (32-bit), 0 if absent.
+    pub smbios_version: u8,
+    /// One of the `SECURE_BOOT_*` values.
+    pub secure_boot: u8,
+    pub reserved: [u8; 5],
+    /// Block devices and mountable filesystems the firmware exposed.
+    pub block_devices: u32,
+    pub filesystems: u32,
+}
+
+impl FirmwareInfo {
+    pub const VENDOR_LEN: usize = 32;
+
+    pub const SECURE_BOOT_UNKNOWN: u8 = 0;
+    pub const SECURE_BOOT_OFF: u8 = 1;
+    pub const SECURE_BOOT_ON: u8 = 2;
+    /// Platform is in setup mode: no platform key enrolled.
+    pub const SECURE_BOOT_SETUP: u8 = 3;
+
+    #[inline]
+    pub const fn empty() -> Self {
+        FirmwareInfo {
+            uefi_revision: 0,
+            firmware_revision: 0,
+            vendor: [0; Self::VENDOR_LEN],
+            acpi_rsdp: 0,
+            smbios_entry: 0,
+            acpi_version: 0,
+            smbios_version: 0,
+            secure_boot: Self::SECURE_BOOT_UNKNOWN,
+            reserved: [0; 5],
+            block_devices: 0,
+            filesystems: 0,
+        }
+    }
+
+    /// Stores `name`, truncated to `VENDOR_LEN` bytes.
+    pub fn set_vendor(&mut self, name: &str) {
+        self.vendor = [0; Self::VENDOR_LEN];
+        let n = name.len().min(Self::VENDOR_LEN);
+        self.vendor[..n].copy_from_slice(&name.as_bytes()[..n]);
+    }
+
+    /// The vendor name up to the first NUL ("" if not valid UTF-8).
+    pub fn vendor(&self) -> &str {
+        let end = self.vendor.iter().position(|&b| b == 0).unwrap_or(Self::VENDOR_LEN);
+        core::str::from_utf8(&self.vendor[..end]).unwrap_or("")
+    }
+
+    pub const fn uefi_major(&self) -> u32 {
+        self.uefi_revision >> 16
+    }
+
+    pub const fn uefi_minor(&self) -> u32 {
+        self.uefi_revision & 0xFFFF
+    }
+
+    /// Returns a human-readable Secure Boot state for logging.
+    pub const fn secure_boot_str(&self) -> &'static str {
+        match self.secure_boot {
+            Self::SECURE_BOOT_OFF => "off",
+            Self::SECURE_BOOT_ON => "on",
+            Self::SECURE_BOOT_SETUP => "setup mode",
+            _ => "unknown",
+        }
+    }
+}
+
+impl Default for FirmwareInfo {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
@@ -4,6 +4,8 @@ pub enum FramebufferFormat {
     Bgr = 0,
     Rgb = 1,
     BltOnly = 2,
+    /// Pixels of `FramebufferInfo::bpp` bits laid out by its channel masks.
+    Bitmask = 3,
 }
 
 impl FramebufferFormat {
@@ -11,7 +13,7 @@ impl FramebufferFormat {
     #[inline]
     pub const fn is_memory_accessible(&self) -> bool {
         match self {
-            FramebufferFormat::Bgr | FramebufferFormat::Rgb => true,
+            FramebufferFormat::Bgr | FramebufferFormat::Rgb | FramebufferFormat::Bitmask => true,
             FramebufferFormat::BltOnly => false,
         }
     }
@@ -22,6 +24,7 @@ impl FramebufferFormat {
         match value {
             0 => FramebufferFormat::Bgr,
             1 => FramebufferFormat::Rgb,
+            3 => FramebufferFormat::Bitmask,
             _ => FramebufferFormat::BltOnly,
         }
     }
@@ -39,6 +42,7 @@ impl FramebufferFormat {
             FramebufferFormat::Bgr => "BGR",
             FramebufferFormat::Rgb => "RGB",
             FramebufferFormat::BltOnly => "BLT-only",
+            FramebufferFormat::Bitmask => "bitmask",
         }
     }
 }
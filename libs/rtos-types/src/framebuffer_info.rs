@@ -7,7 +7,12 @@ pub struct FramebufferInfo {
     pub width: u32,     // pixels
     pub height: u32,    // pixels
     pub stride: u32,    // pixels per scanline (NOT bytes)
-    pub format: FramebufferFormat
+    pub format: FramebufferFormat,
+    pub red_mask: u32,      // channel bits within a pixel;
+    pub green_mask: u32,    // also filled in for Rgb/Bgr
+    pub blue_mask: u32,
+    pub reserved_mask: u32,
+    pub bpp: u32,           // bits per pixel: 32 for GOP; 15/16/24 for native drivers
 }
 
 impl FramebufferFormat {
@@ -30,8 +35,26 @@ impl FramebufferInfo {
             height: 0,
             stride: 0,
             format: FramebufferFormat::BltOnly,
+            red_mask: 0,
+            green_mask: 0,
+            blue_mask: 0,
+            reserved_mask: 0,
+            bpp: 0,
         }
     }
+
+    /// Bytes one pixel takes in memory (15-bpp pixels take 2).
+    #[inline]
+    pub const fn bytes_per_pixel(&self) -> usize {
+        (self.bpp as usize).div_ceil(8)
+    }
+
+    /// True when the kernel can draw by writing to `base`. Blt-only modes
+    /// were only reachable through boot services and come through as false.
+    #[inline]
+    pub const fn is_linear(&self) -> bool {
+        self.base != 0 && self.format.is_memory_accessible() && self.bpp != 0 && self.bpp <= 32
+    }
 }
 
 impl Default for FramebufferInfo {
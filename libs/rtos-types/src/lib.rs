@@ -1,8 +1,15 @@
 #![no_std]
 
 pub mod boot_info;
+pub mod boot_log;
+pub mod boot_slot;
+pub mod boot_module;
+pub mod memory_map;
 pub mod rtosk_header;
 pub mod rtosk_segment;
+pub mod rtosk_signature;
 pub mod framebuffer_format;
 pub mod framebuffer_info;
-pub mod constants;
\ No newline at end of file
+pub mod firmware_info;
+pub mod constants;
+pub mod crc32;
\ No newline at end of file
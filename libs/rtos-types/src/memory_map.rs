@@ -0,0 +1,97 @@
+/// What a physical range may be used for, as seen by the kernel.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemoryKind {
+    /// Free RAM, including memory the firmware used during boot.
+    Usable = 0,
+    /// Bootloader image and data, kernel segments, kernel stack, BootInfo
+    /// and this map. Reclaimable only once the kernel no longer needs them.
+    Loader = 1,
+    /// Initrd modules and their table (`RTOS_MODULE_MEMORY_TYPE`).
+    Modules = 2,
+    /// The bootloader's message ring (`RTOS_BOOT_LOG_MEMORY_TYPE`).
+    BootLog = 3,
+    /// UEFI runtime services; must stay mapped for runtime calls.
+    RuntimeCode = 4,
+    RuntimeData = 5,
+    /// ACPI tables; reusable after they have been parsed.
+    AcpiReclaimable = 6,
+    AcpiNvs = 7,
+    Mmio = 8,
+    /// RAM with detected errors.
+    Unusable = 9,
+    Reserved = 10,
+}
+
+impl MemoryKind {
+    /// Returns a human-readable name for logging.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            MemoryKind::Usable => "usable",
+            MemoryKind::Loader => "loader",
+            MemoryKind::Modules => "modules",
+            MemoryKind::BootLog => "boot log",
+            MemoryKind::RuntimeCode => "runtime code",
+            MemoryKind::RuntimeData => "runtime data",
+            MemoryKind::AcpiReclaimable => "ACPI reclaimable",
+            MemoryKind::AcpiNvs => "ACPI NVS",
+            MemoryKind::Mmio => "MMIO",
+            MemoryKind::Unusable => "unusable",
+            MemoryKind::Reserved => "reserved",
+        }
+    }
+}
+
+/// One physically contiguous range of 4 KiB pages. The bootloader hands the
+/// kernel these sorted by `base`, with adjacent ranges of the same kind and
+/// attributes merged.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct MemoryRegion {
+    pub base: u64,
+    pub pages: u64,
+    /// UEFI attribute bits (cacheability, `EFI_MEMORY_RUNTIME`, ...).
+    pub attribute: u64,
+    pub kind: MemoryKind,
+    pub reserved: u32,
+}
+
+impl MemoryRegion {
+    pub const PAGE_SIZE: u64 = 4096;
+
+    pub const fn size(&self) -> u64 {
+        self.pages * Self::PAGE_SIZE
+    }
+
+    pub const fn end(&self) -> u64 {
+        self.base + self.size()
+    }
+}
+
+/// A UEFI runtime range exactly as the firmware reported it, unmerged and
+/// in `EFI_MEMORY_DESCRIPTOR` layout, so the kernel can pass the table to
+/// `SetVirtualAddressMap` after filling in `virt_start`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct RuntimeRegion {
+    /// UEFI memory type.
+    pub ty: u32,
+    pub pad: u32,
+    pub phys_start: u64,
+    /// 0 until the kernel picks a virtual address.
+    pub virt_start: u64,
+    pub page_count: u64,
+    /// UEFI attribute bits; always includes `EFI_MEMORY_RUNTIME`.
+    pub attribute: u64,
+}
+
+impl RuntimeRegion {
+    /// `EFI_MEMORY_RUNTIME`: the range must be mapped for runtime calls.
+    pub const EFI_MEMORY_RUNTIME: u64 = 1 << 63;
+    /// `EFI_MEMORY_DESCRIPTOR_VERSION` of this layout.
+    pub const DESCRIPTOR_VERSION: u32 = 1;
+
+    pub const fn size(&self) -> u64 {
+        self.page_count * MemoryRegion::PAGE_SIZE
+    }
+}
@@ -1,3 +1,6 @@
+use crate::constants::RTOSK_HDR_SIGNED;
+use crate::rtosk_signature::RtoskSignature;
+
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct RtoskHeader {
@@ -57,6 +60,12 @@ impl RtoskHeader {
     pub const fn version(&self) -> (u16, u16) {
         (self.ver_major, self.ver_minor)
     }
+
+    /// Returns true if the image carries a trailing signature block.
+    #[inline]
+    pub const fn is_signed(&self) -> bool {
+        (self.flags & RTOSK_HDR_SIGNED) != 0
+    }
 }
 
 impl Default for RtoskHeader {
@@ -64,3 +73,19 @@ impl Default for RtoskHeader {
         Self::empty()
     }
 }
+
+/// Byte offset of `flags` inside the on-disk header.
+pub const RTOSK_FLAGS_FIELD_OFFSET: usize = core::mem::offset_of!(RtoskHeader, flags);
+
+/// Length of the part of `image` (starting at the magic) that precedes the
+/// signature block: the whole image if the header isn't marked signed.
+pub fn signed_len(image: &[u8]) -> usize {
+    let off = RTOSK_FLAGS_FIELD_OFFSET;
+    if image.len() < off + 4 { return image.len(); }
+    let flags = u32::from_le_bytes([image[off], image[off + 1], image[off + 2], image[off + 3]]);
+    if (flags & RTOSK_HDR_SIGNED) != 0 && image.len() >= RtoskSignature::SIZE {
+        image.len() - RtoskSignature::SIZE
+    } else {
+        image.len()
+    }
+}
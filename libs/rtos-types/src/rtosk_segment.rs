@@ -1,4 +1,4 @@
-use crate::constants::RTOSK_EXEC_FLAG;
+use crate::constants::{RTOSK_COMPRESSED, RTOSK_EXEC_FLAG, RTOSK_NOLOAD, RTOSK_READ_FLAG, RTOSK_TLS, RTOSK_WRITE_FLAG};
 
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -8,6 +8,9 @@ pub struct RtoskSegment {
     pub memory_size: u64,
     pub file_size: u64,
     pub flags: u32,
+    /// Bytes stored in the file when `RTOSK_COMPRESSED` is set; ignored otherwise
+    /// (this occupies what used to be tail padding, so older images keep their layout).
+    pub packed_size: u32,
 }
 
 impl RtoskSegment {
@@ -20,6 +23,7 @@ impl RtoskSegment {
             memory_size: 0,
             file_size: 0,
             flags: 0,
+            packed_size: 0,
         }
     }
 
@@ -32,6 +36,7 @@ impl RtoskSegment {
             memory_size,
             file_size,
             flags,
+            packed_size: 0,
         }
     }
 
@@ -46,6 +51,42 @@ impl RtoskSegment {
     pub const fn is_executable(&self) -> bool {
         (self.flags & RTOSK_EXEC_FLAG) != 0
     }
+
+    /// Returns true if the segment is marked readable.
+    #[inline]
+    pub const fn is_readable(&self) -> bool {
+        (self.flags & RTOSK_READ_FLAG) != 0
+    }
+
+    /// Returns true if the segment is marked writable.
+    #[inline]
+    pub const fn is_writable(&self) -> bool {
+        (self.flags & RTOSK_WRITE_FLAG) != 0
+    }
+
+    /// Returns true if the segment carries no file payload (zero-fill only).
+    #[inline]
+    pub const fn is_noload(&self) -> bool {
+        (self.flags & RTOSK_NOLOAD) != 0
+    }
+
+    /// Returns true if the segment is the TLS template.
+    #[inline]
+    pub const fn is_tls(&self) -> bool {
+        (self.flags & RTOSK_TLS) != 0
+    }
+
+    /// Returns true if the file payload is LZ4-compressed.
+    #[inline]
+    pub const fn is_compressed(&self) -> bool {
+        (self.flags & RTOSK_COMPRESSED) != 0
+    }
+
+    /// Number of payload bytes stored in the file at `file_offset`.
+    #[inline]
+    pub const fn stored_size(&self) -> u64 {
+        if self.is_compressed() { self.packed_size as u64 } else { self.file_size }
+    }
 }
 
 impl Default for RtoskSegment {
@@ -0,0 +1,45 @@
+use crate::constants::RTOSK_SIG_MAGIC;
+
+/// Trailing signature block of a signed RTOSK image.
+///
+/// The Ed25519 signature covers every image byte before this block (header,
+/// segment table and payloads), so the `RTOSK_HDR_SIGNED` header flag is itself
+/// signed and cannot be stripped without invalidating the image.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RtoskSignature {
+    pub magic: [u8; 8],
+    pub public_key: [u8; 32],
+    pub signature: [u8; 64],
+}
+
+impl RtoskSignature {
+    pub const SIZE: usize = core::mem::size_of::<RtoskSignature>();
+
+    /// Constructs a signature block for the given key and signature.
+    #[inline]
+    pub const fn new(public_key: [u8; 32], signature: [u8; 64]) -> Self {
+        RtoskSignature { magic: RTOSK_SIG_MAGIC, public_key, signature }
+    }
+
+    /// Reads the block from the last `SIZE` bytes of `image`.
+    pub fn from_tail(image: &[u8]) -> Option<Self> {
+        if image.len() < Self::SIZE { return None; }
+        let tail = &image[image.len() - Self::SIZE..];
+        let mut sig = RtoskSignature::new([0; 32], [0; 64]);
+        sig.magic.copy_from_slice(&tail[0..8]);
+        sig.public_key.copy_from_slice(&tail[8..40]);
+        sig.signature.copy_from_slice(&tail[40..104]);
+        if sig.magic != RTOSK_SIG_MAGIC { return None; }
+        Some(sig)
+    }
+
+    /// Serializes the block in on-disk order.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut out = [0u8; Self::SIZE];
+        out[0..8].copy_from_slice(&self.magic);
+        out[8..40].copy_from_slice(&self.public_key);
+        out[40..104].copy_from_slice(&self.signature);
+        out
+    }
+}
@@ -0,0 +1,92 @@
+//! Host fuzz harness for the LZ4 codec.
+//!
+//!     cargo run --release -p rtosk-lz4 --example fuzz -- [iterations] [seed]
+//!
+//! Each iteration round-trips a generated buffer through `compress` and
+//! `decompress_exact`, then feeds mutated and purely random blocks to the
+//! decompressor, which must return (`Ok` or `Err`) without panicking.
+//! `cargo test -p rtosk-lz4` runs a short fixed-seed version of the same loop.
+
+use std::env;
+use std::process::ExitCode;
+
+use rtosk_lz4::{compress_into, decompress_exact, decompress_into, max_compressed_len};
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn below(&mut self, n: usize) -> usize { (self.next() % n.max(1) as u64) as usize }
+}
+
+/// Mixture of runs, repeated chunks and noise so both literal and match paths are hit.
+fn gen_input(rng: &mut XorShift) -> Vec<u8> {
+    let len = match rng.below(4) {
+        0 => rng.below(16),
+        1 => rng.below(300),
+        _ => rng.below(70_000),
+    };
+    let mut v = Vec::with_capacity(len);
+    while v.len() < len {
+        match rng.below(3) {
+            0 => { let b = rng.next() as u8; let n = rng.below(600); v.extend(std::iter::repeat_n(b, n)); }
+            1 if !v.is_empty() => {
+                let start = rng.below(v.len());
+                let n = rng.below(v.len() - start).min(500);
+                for k in 0..n { let b = v[start + k]; v.push(b); }
+            }
+            _ => { let n = rng.below(64); for _ in 0..n { v.push(rng.next() as u8); } }
+        }
+    }
+    v.truncate(len);
+    v
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let iters: u64 = args.next().and_then(|s| s.parse().ok()).unwrap_or(10_000);
+    let seed: u64 = args.next().and_then(|s| s.parse().ok()).unwrap_or(0x5eed_1234_abcd_0001);
+    let mut rng = XorShift(seed | 1);
+    let mut scratch = vec![0u8; 1 << 17];
+
+    for it in 0..iters {
+        let input = gen_input(&mut rng);
+        let mut packed = vec![0u8; max_compressed_len(input.len())];
+        let n = compress_into(&input, &mut packed);
+        packed.truncate(n);
+
+        let mut out = vec![0u8; input.len()];
+        if let Err(e) = decompress_exact(&packed, &mut out, input.len()) {
+            eprintln!("iter {}: round-trip failed: {:?} (len {})", it, e, input.len());
+            return ExitCode::FAILURE;
+        }
+        if out != input {
+            eprintln!("iter {}: round-trip mismatch (len {})", it, input.len());
+            return ExitCode::FAILURE;
+        }
+
+        // Mutated valid block: flip, truncate or extend.
+        let mut bad = packed.clone();
+        if !bad.is_empty() {
+            for _ in 0..1 + rng.below(4) {
+                let at = rng.below(bad.len());
+                bad[at] ^= 1 << rng.below(8);
+            }
+        }
+        if rng.below(4) == 0 { let cut = rng.below(bad.len() + 1); bad.truncate(cut); }
+        let cap = rng.below(scratch.len());
+        let _ = decompress_into(&bad, &mut scratch[..cap]);
+
+        // Pure noise.
+        let noise: Vec<u8> = (0..rng.below(512)).map(|_| rng.next() as u8).collect();
+        let _ = decompress_into(&noise, &mut scratch[..rng.below(4096)]);
+    }
+
+    println!("lz4 fuzz: {} iterations OK (seed {:#x})", iters, seed);
+    ExitCode::SUCCESS
+}
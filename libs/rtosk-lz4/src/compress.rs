@@ -0,0 +1,95 @@
+use crate::MIN_MATCH;
+
+const HASH_BITS: u32 = 14;
+const MAX_OFFSET: usize = 0xffff;
+/// LZ4 end-of-block rules: the last match starts at least 12 bytes before the
+/// end and the last 5 bytes are always literals.
+const MF_LIMIT: usize = 12;
+const LAST_LITERALS: usize = 5;
+
+fn hash(v: u32) -> usize {
+    (v.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+fn read_u32(b: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]])
+}
+
+/// Output cursor over the caller's buffer; sized by `max_compressed_len`, so
+/// writes never run past it.
+struct Out<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl Out<'_> {
+    fn push(&mut self, b: u8) {
+        self.buf[self.pos] = b;
+        self.pos += 1;
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        self.buf[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+    }
+
+    fn push_len(&mut self, mut len: usize) {
+        while len >= 255 {
+            self.push(255);
+            len -= 255;
+        }
+        self.push(len as u8);
+    }
+
+    fn emit(&mut self, literals: &[u8], match_len: Option<(usize, usize)>) {
+        let lit = literals.len();
+        let ml = match_len.map(|(_, l)| l - MIN_MATCH).unwrap_or(0);
+        self.push(((lit.min(15) as u8) << 4) | ml.min(15) as u8);
+        if lit >= 15 { self.push_len(lit - 15); }
+        self.extend(literals);
+        if let Some((offset, _)) = match_len {
+            self.extend(&(offset as u16).to_le_bytes());
+            if ml >= 15 { self.push_len(ml - 15); }
+        }
+    }
+}
+
+/// Worst-case block size for `len` input bytes (all literals).
+pub const fn max_compressed_len(len: usize) -> usize {
+    len + len / 255 + 16
+}
+
+/// Compresses `src` into a single LZ4 block in `dst` and returns its length.
+/// Panics if `dst` is shorter than `max_compressed_len(src.len())`.
+pub fn compress_into(src: &[u8], dst: &mut [u8]) -> usize {
+    assert!(dst.len() >= max_compressed_len(src.len()), "lz4: output buffer too small");
+    let mut out = Out { buf: dst, pos: 0 };
+    if src.is_empty() { return 0; }
+
+    let mut table = [usize::MAX; 1 << HASH_BITS];
+    let mut anchor = 0usize;
+    let mut i = 0usize;
+
+    if src.len() > MF_LIMIT {
+        let match_limit = src.len() - MF_LIMIT;
+        let end_limit = src.len() - LAST_LITERALS;
+        while i < match_limit {
+            let h = hash(read_u32(src, i));
+            let cand = table[h];
+            table[h] = i;
+
+            if cand != usize::MAX && i - cand <= MAX_OFFSET && read_u32(src, cand) == read_u32(src, i) {
+                let mut len = MIN_MATCH;
+                while i + len < end_limit && src[cand + len] == src[i + len] { len += 1; }
+                out.emit(&src[anchor..i], Some((i - cand, len)));
+                i += len;
+                anchor = i;
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    out.emit(&src[anchor..], None);
+    out.pos
+}
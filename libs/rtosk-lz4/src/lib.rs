@@ -0,0 +1,274 @@
+#![no_std]
+
+//! LZ4 block format (no frame header) for RTOSK segment payloads.
+//!
+//! The decompressor is bounds-checked on every read and write so a corrupt or
+//! hostile payload can only produce an error, never write outside `dst`.
+//! The compressor (host side, used by `rtosk-gen`) is a simple greedy
+//! single-probe hash matcher; output is standard LZ4 block data.
+
+mod compress;
+pub use compress::{compress_into, max_compressed_len};
+
+const MIN_MATCH: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lz4Error {
+    /// Input ended in the middle of a sequence.
+    Truncated,
+    /// Output would exceed the destination buffer.
+    OutputOverrun,
+    /// Match offset is zero or points before the start of the output.
+    BadOffset,
+    /// Decoded length differs from the expected size.
+    LengthMismatch,
+}
+
+impl Lz4Error {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Lz4Error::Truncated => "compressed payload truncated",
+            Lz4Error::OutputOverrun => "decompressed data overruns segment",
+            Lz4Error::BadOffset => "invalid match offset",
+            Lz4Error::LengthMismatch => "decompressed length does not match file_size",
+        }
+    }
+}
+
+/// Reads an LZ4 extended length (runs of 255 terminated by a smaller byte).
+fn read_len(src: &[u8], pos: &mut usize, base: usize) -> Result<usize, Lz4Error> {
+    let mut len = base;
+    if base == 15 {
+        loop {
+            let b = *src.get(*pos).ok_or(Lz4Error::Truncated)?;
+            *pos += 1;
+            len = len.checked_add(b as usize).ok_or(Lz4Error::OutputOverrun)?;
+            if b != 255 { break; }
+        }
+    }
+    Ok(len)
+}
+
+/// Decompresses one LZ4 block into `dst`, returning the number of bytes written.
+pub fn decompress_into(src: &[u8], dst: &mut [u8]) -> Result<usize, Lz4Error> {
+    let mut ip = 0usize;
+    let mut op = 0usize;
+
+    if src.is_empty() { return Ok(0); }
+
+    loop {
+        let token = *src.get(ip).ok_or(Lz4Error::Truncated)?;
+        ip += 1;
+
+        // Literals
+        let lit_len = read_len(src, &mut ip, (token >> 4) as usize)?;
+        let lit_end = ip.checked_add(lit_len).ok_or(Lz4Error::Truncated)?;
+        let out_end = op.checked_add(lit_len).ok_or(Lz4Error::OutputOverrun)?;
+        if lit_end > src.len() { return Err(Lz4Error::Truncated); }
+        if out_end > dst.len() { return Err(Lz4Error::OutputOverrun); }
+        dst[op..out_end].copy_from_slice(&src[ip..lit_end]);
+        ip = lit_end;
+        op = out_end;
+
+        // The last sequence carries literals only.
+        if ip == src.len() { return Ok(op); }
+
+        // Match
+        if ip + 2 > src.len() { return Err(Lz4Error::Truncated); }
+        let offset = u16::from_le_bytes([src[ip], src[ip + 1]]) as usize;
+        ip += 2;
+        if offset == 0 || offset > op { return Err(Lz4Error::BadOffset); }
+
+        let match_len = read_len(src, &mut ip, (token & 0x0f) as usize)? + MIN_MATCH;
+        let out_end = op.checked_add(match_len).ok_or(Lz4Error::OutputOverrun)?;
+        if out_end > dst.len() { return Err(Lz4Error::OutputOverrun); }
+
+        let from = op - offset;
+        if offset >= match_len {
+            dst.copy_within(from..from + match_len, op);
+        } else {
+            // Overlapping copy repeats the last `offset` bytes.
+            for i in 0..match_len { dst[op + i] = dst[from + i]; }
+        }
+        op = out_end;
+    }
+}
+
+/// Decompresses `src` into `dst` and requires it to fill exactly `expected` bytes.
+pub fn decompress_exact(src: &[u8], dst: &mut [u8], expected: usize) -> Result<(), Lz4Error> {
+    if expected > dst.len() { return Err(Lz4Error::OutputOverrun); }
+    let n = decompress_into(src, &mut dst[..expected])?;
+    if n != expected { return Err(Lz4Error::LengthMismatch); }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+    use super::*;
+
+    fn compress(src: &[u8]) -> Vec<u8> {
+        let mut packed = vec![0u8; max_compressed_len(src.len())];
+        let n = compress_into(src, &mut packed);
+        packed.truncate(n);
+        packed
+    }
+
+    fn round_trip(src: &[u8]) {
+        let packed = compress(src);
+        let mut out = vec![0u8; src.len()];
+        assert_eq!(decompress_exact(&packed, &mut out, src.len()), Ok(()), "len {}", src.len());
+        assert_eq!(out, src);
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(b"");
+        round_trip(b"a");
+        round_trip(b"hello, world");
+        round_trip(&[0u8; 13]);
+        round_trip(&[0x5a; 70_000]);
+        round_trip(&b"abcd".repeat(1000));
+        let noise: Vec<u8> = (0..5000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        round_trip(&noise);
+    }
+
+    #[test]
+    fn compresses_repeats() {
+        assert!(compress(&[0u8; 4096]).len() < 64);
+    }
+
+    #[test]
+    fn long_literal_and_match_lengths() {
+        // Token 0xF0 + 255 + 10: 280 literals, the last sequence.
+        let mut src = vec![0xF0, 255, 10];
+        src.extend((0..280).map(|i| i as u8));
+        let mut out = [0u8; 280];
+        assert_eq!(decompress_into(&src, &mut out), Ok(280));
+        assert_eq!(out[279], 279u32 as u8);
+
+        // One literal, then a match of 4 + 15 + 255 + 1 = 275 at offset 1.
+        let src = [0x1F, b'x', 1, 0, 255, 1, 0x00];
+        let mut out = [0u8; 276];
+        assert_eq!(decompress_into(&src, &mut out), Ok(276));
+        assert!(out.iter().all(|&b| b == b'x'));
+    }
+
+    #[test]
+    fn overlapping_match() {
+        // "ab", then 6 bytes copied from 2 back.
+        let src = [0x22, b'a', b'b', 2, 0, 0x00];
+        let mut out = [0u8; 8];
+        assert_eq!(decompress_into(&src, &mut out), Ok(8));
+        assert_eq!(&out, b"abababab");
+    }
+
+    #[test]
+    fn truncated() {
+        let packed = compress(&b"abcdefgh".repeat(64));
+        let mut out = vec![0u8; 512];
+        for cut in 1..packed.len() {
+            assert!(decompress_exact(&packed[..cut], &mut out, 512).is_err(), "cut {}", cut);
+        }
+        // Literal run past the end of the input.
+        assert_eq!(decompress_into(&[0x50, 1, 2], &mut out), Err(Lz4Error::Truncated));
+        // Extended length with no terminating byte.
+        assert_eq!(decompress_into(&[0xF0, 255], &mut out), Err(Lz4Error::Truncated));
+        // Half a match offset.
+        assert_eq!(decompress_into(&[0x10, b'a', 1], &mut out), Err(Lz4Error::Truncated));
+    }
+
+    #[test]
+    fn bad_offsets() {
+        let mut out = [0u8; 64];
+        // Offset zero.
+        assert_eq!(decompress_into(&[0x10, b'a', 0, 0, 0x00], &mut out), Err(Lz4Error::BadOffset));
+        // Offset past the start of the output.
+        assert_eq!(decompress_into(&[0x10, b'a', 2, 0, 0x00], &mut out), Err(Lz4Error::BadOffset));
+        assert_eq!(decompress_into(&[0x00, 0xFF, 0xFF, 0x00], &mut out), Err(Lz4Error::BadOffset));
+    }
+
+    #[test]
+    fn output_bounds() {
+        let src = b"0123456789abcdef".repeat(8);
+        let packed = compress(&src);
+        let mut out = vec![0u8; src.len() - 1];
+        assert_eq!(decompress_into(&packed, &mut out), Err(Lz4Error::OutputOverrun));
+        // A match longer than what is left of the output.
+        let mut small = [0u8; 8];
+        assert_eq!(decompress_into(&[0x1F, b'x', 1, 0, 10, 0x00], &mut small), Err(Lz4Error::OutputOverrun));
+        // Extended length that overflows usize.
+        let mut huge = vec![0xF0];
+        huge.extend(core::iter::repeat_n(255, 64));
+        huge.push(0);
+        assert!(decompress_into(&huge, &mut small).is_err());
+
+        let mut out = vec![0u8; src.len() + 16];
+        assert_eq!(decompress_exact(&packed, &mut out, src.len() + 1), Err(Lz4Error::LengthMismatch));
+        let too_long = out.len() + 1;
+        assert_eq!(decompress_exact(&packed, &mut out, too_long), Err(Lz4Error::OutputOverrun));
+    }
+
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+        fn below(&mut self, n: usize) -> usize { (self.next() % n.max(1) as u64) as usize }
+    }
+
+    /// A short run of `examples/fuzz.rs` with a fixed seed: round-trips
+    /// generated buffers, then feeds mutated and random blocks to the
+    /// decompressor, which must return an error rather than panic.
+    #[test]
+    fn random_inputs() {
+        let mut rng = XorShift(0x5eed_1234_abcd_0001);
+        let mut scratch = vec![0u8; 1 << 14];
+        for _ in 0..2000 {
+            let len = rng.below(2000);
+            let mut input = Vec::with_capacity(len);
+            while input.len() < len {
+                match rng.below(3) {
+                    0 => input.extend(core::iter::repeat_n(rng.next() as u8, rng.below(100))),
+                    1 if !input.is_empty() => {
+                        let start = rng.below(input.len());
+                        let n = rng.below(input.len() - start).min(100);
+                        input.extend_from_within(start..start + n);
+                    }
+                    _ => input.extend((0..rng.below(32)).map(|_| rng.next() as u8)),
+                }
+            }
+            input.truncate(len);
+            round_trip(&input);
+
+            let mut bad = compress(&input);
+            if !bad.is_empty() {
+                for _ in 0..1 + rng.below(4) {
+                    let at = rng.below(bad.len());
+                    bad[at] ^= 1 << rng.below(8);
+                }
+            }
+            if rng.below(4) == 0 {
+                let cut = rng.below(bad.len() + 1);
+                bad.truncate(cut);
+            }
+            let cap = rng.below(scratch.len());
+            if let Ok(n) = decompress_into(&bad, &mut scratch[..cap]) {
+                assert!(n <= cap);
+            }
+
+            let noise: Vec<u8> = (0..rng.below(512)).map(|_| rng.next() as u8).collect();
+            let cap = rng.below(4096);
+            if let Ok(n) = decompress_into(&noise, &mut scratch[..cap]) {
+                assert!(n <= cap);
+            }
+        }
+    }
+}
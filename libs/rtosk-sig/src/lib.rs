@@ -0,0 +1,164 @@
+#![no_std]
+
+//! Ed25519 signing and verification of RTOSK images.
+//!
+//! A signed image is the ordinary image with `RTOSK_HDR_SIGNED` set in the
+//! header flags (CRC recomputed afterwards) followed by an `RtoskSignature`
+//! block. The signature covers every byte before that block.
+
+use ed25519_compact::{KeyPair, PublicKey, Signature};
+use rtos_types::constants::RTOSK_HDR_SIGNED;
+use rtos_types::crc32::{crc32, RTOSK_CRC_FIELD_OFFSET};
+use rtos_types::rtosk_header::{signed_len, RTOSK_FLAGS_FIELD_OFFSET};
+use rtos_types::rtosk_signature::RtoskSignature;
+
+pub const PUBLIC_KEY_LEN: usize = 32;
+pub const SECRET_KEY_LEN: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SigError {
+    /// Header is not marked signed.
+    Unsigned,
+    /// Header says signed but the trailing block is missing or malformed.
+    MissingBlock,
+    /// The embedded public key is not in the trusted set.
+    UntrustedKey,
+    /// The embedded public key is not a valid Ed25519 point.
+    BadKey,
+    /// Signature does not match the image contents.
+    BadSignature,
+}
+
+impl SigError {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            SigError::Unsigned => "image is not signed",
+            SigError::MissingBlock => "signature block missing or malformed",
+            SigError::UntrustedKey => "image signed by an untrusted key",
+            SigError::BadKey => "embedded public key is invalid",
+            SigError::BadSignature => "signature does not match image",
+        }
+    }
+}
+
+/// Sets `RTOSK_HDR_SIGNED` in an unsigned image and recomputes its CRC, so the
+/// returned bytes are exactly what gets signed.
+pub fn mark_signed(image: &mut [u8]) {
+    let f = RTOSK_FLAGS_FIELD_OFFSET;
+    let c = RTOSK_CRC_FIELD_OFFSET;
+    if image.len() < f.max(c) + 4 { return; }
+    let flags = u32::from_le_bytes([image[f], image[f + 1], image[f + 2], image[f + 3]]);
+    image[f..f + 4].copy_from_slice(&(flags | RTOSK_HDR_SIGNED).to_le_bytes());
+    // Not yet followed by a signature block, so the CRC must cover everything.
+    image[c..c + 4].copy_from_slice(&[0; 4]);
+    let crc = crc32(image);
+    image[c..c + 4].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Signs `image` (already passed through `mark_signed`) with a 64-byte
+/// Ed25519 secret key (seed || public key).
+pub fn sign(image: &[u8], secret_key: &[u8; SECRET_KEY_LEN]) -> Result<RtoskSignature, SigError> {
+    let kp = KeyPair::from_slice(secret_key).map_err(|_| SigError::BadKey)?;
+    let sig = kp.sk.sign(image, None);
+    Ok(RtoskSignature::new(*kp.pk, *sig))
+}
+
+/// Returns the signature block of a signed image without checking it.
+pub fn signature_block(image: &[u8]) -> Result<RtoskSignature, SigError> {
+    if signed_len(image) == image.len() { return Err(SigError::Unsigned); }
+    RtoskSignature::from_tail(image).ok_or(SigError::MissingBlock)
+}
+
+/// Verifies a signed image (starting at the magic) against a set of trusted
+/// public keys. An empty set trusts nothing.
+pub fn verify(image: &[u8], trusted: &[[u8; PUBLIC_KEY_LEN]]) -> Result<RtoskSignature, SigError> {
+    let block = signature_block(image)?;
+    if !trusted.contains(&block.public_key) {
+        return Err(SigError::UntrustedKey);
+    }
+    let pk = PublicKey::from_slice(&block.public_key).map_err(|_| SigError::BadKey)?;
+    let sig = Signature::from_slice(&block.signature).map_err(|_| SigError::BadSignature)?;
+    pk.verify(&image[..signed_len(image)], &sig).map_err(|_| SigError::BadSignature)?;
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+    use super::*;
+
+    // A small kernel packed with `rtosk-gen --sign`; `signed.pub` is the
+    // public half of the key it was signed with.
+    const IMAGE: &[u8] = include_bytes!("../fixtures/signed.rtosk");
+    const KEY: [u8; PUBLIC_KEY_LEN] = *include_bytes!("../fixtures/signed.pub");
+    /// First byte of the `.text` payload.
+    const PAYLOAD_BYTE: usize = 0x1f0;
+
+    fn flipped(at: usize) -> Vec<u8> {
+        let mut image = IMAGE.to_vec();
+        image[at] ^= 0x01;
+        image
+    }
+
+    #[test]
+    fn fixture_verifies() {
+        let block = verify(IMAGE, &[KEY]).unwrap();
+        assert_eq!(block.public_key, KEY);
+        assert_eq!(signature_block(IMAGE).unwrap().public_key, KEY);
+    }
+
+    #[test]
+    fn flipped_payload_byte() {
+        assert!(PAYLOAD_BYTE < signed_len(IMAGE));
+        assert_eq!(verify(&flipped(PAYLOAD_BYTE), &[KEY]).err(), Some(SigError::BadSignature));
+    }
+
+    #[test]
+    fn flipped_signature_byte() {
+        let last = IMAGE.len() - 1;
+        assert_eq!(verify(&flipped(last), &[KEY]).err(), Some(SigError::BadSignature));
+    }
+
+    #[test]
+    fn wrong_key() {
+        let mut other = KEY;
+        other[0] ^= 0x01;
+        assert_eq!(verify(IMAGE, &[other]).err(), Some(SigError::UntrustedKey));
+        assert_eq!(verify(IMAGE, &[]).err(), Some(SigError::UntrustedKey));
+    }
+
+    #[test]
+    fn signed_by_another_key() {
+        let kp = KeyPair::from_seed(ed25519_compact::Seed::new([7; 32]));
+        let mut image = IMAGE[..signed_len(IMAGE)].to_vec();
+        let block = sign(&image, &kp).unwrap();
+        image.extend_from_slice(&block.to_bytes());
+        assert_eq!(verify(&image, &[*kp.pk]).unwrap().public_key, *kp.pk);
+        assert_eq!(verify(&image, &[KEY]).err(), Some(SigError::UntrustedKey));
+    }
+
+    #[test]
+    fn truncated_signature_block() {
+        let image = &IMAGE[..IMAGE.len() - 1];
+        assert_eq!(verify(image, &[KEY]).err(), Some(SigError::MissingBlock));
+        let image = &IMAGE[..signed_len(IMAGE)];
+        assert_eq!(verify(image, &[KEY]).err(), Some(SigError::MissingBlock));
+    }
+
+    #[test]
+    fn cleared_signed_flag() {
+        let mut image = IMAGE.to_vec();
+        image[RTOSK_FLAGS_FIELD_OFFSET] &= !(RTOSK_HDR_SIGNED as u8);
+        assert_eq!(verify(&image, &[KEY]).err(), Some(SigError::Unsigned));
+    }
+
+    #[test]
+    fn mark_signed_sets_the_flag_and_crc() {
+        let mut image = IMAGE[..signed_len(IMAGE)].to_vec();
+        image[RTOSK_FLAGS_FIELD_OFFSET] &= !(RTOSK_HDR_SIGNED as u8);
+        mark_signed(&mut image);
+        assert_eq!(image, IMAGE[..signed_len(IMAGE)]);
+    }
+}
@@ -54,6 +54,13 @@ impl SerialWriter {
         }
     }
 
+    /// A byte received on COM1, if one is waiting.
+    pub fn try_read() -> Option<u8> {
+        unsafe {
+            if Self::inb(Self::COM1 + 5) & 0x01 != 0 { Some(Self::inb(Self::COM1)) } else { None }
+        }
+    }
+
     pub fn write(s: &str) {
         let bytes = s.as_bytes();
         let mut i = 0;
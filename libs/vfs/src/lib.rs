@@ -0,0 +1,82 @@
+#![no_std]
+
+//! Minimal read-only virtual filesystem interface.
+//!
+//! Filesystems hand out `Node`s that borrow straight from their backing
+//! memory, so nothing here allocates. The first implementation is `tar::TarFs`
+//! over a USTAR archive loaded by the bootloader as an initrd.
+
+use core::fmt;
+
+pub mod tar;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+/// One filesystem entry. The path is stored as up to two pieces (USTAR
+/// `prefix` + `name`) joined by `/`; use `matches` or `Display` rather than
+/// comparing the pieces directly.
+#[derive(Clone, Copy, Debug)]
+pub struct Node<'a> {
+    pub prefix: &'a str,
+    pub name: &'a str,
+    pub kind: NodeKind,
+    pub data: &'a [u8],
+}
+
+/// Drops leading `/` and `./` and a trailing `/` so `./etc/`, `/etc` and `etc` compare equal.
+fn normalize(path: &str) -> &str {
+    let mut p = path;
+    loop {
+        if let Some(rest) = p.strip_prefix("./") { p = rest; continue; }
+        if let Some(rest) = p.strip_prefix('/') { p = rest; continue; }
+        break;
+    }
+    p.strip_suffix('/').unwrap_or(p)
+}
+
+impl Node<'_> {
+    /// True if this node's full path equals `path` (after normalization).
+    pub fn matches(&self, path: &str) -> bool {
+        let want = normalize(path);
+        let prefix = normalize(self.prefix);
+        let name = normalize(self.name);
+        if prefix.is_empty() {
+            return name == want;
+        }
+        want.len() == prefix.len() + 1 + name.len()
+            && want.starts_with(prefix)
+            && want.as_bytes()[prefix.len()] == b'/'
+            && want.ends_with(name)
+    }
+}
+
+impl fmt::Display for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = normalize(self.prefix);
+        if prefix.is_empty() {
+            write!(f, "/{}", normalize(self.name))
+        } else {
+            write!(f, "/{}/{}", prefix, normalize(self.name))
+        }
+    }
+}
+
+/// A filesystem whose contents never change.
+pub trait ReadOnlyFs<'a> {
+    /// Finds the node at `path`.
+    fn lookup(&self, path: &str) -> Option<Node<'a>>;
+
+    /// Calls `f` for every node, in storage order.
+    fn visit(&self, f: &mut dyn FnMut(&Node<'a>));
+
+    /// Contents of the regular file at `path`.
+    fn read(&self, path: &str) -> Option<&'a [u8]> {
+        self.lookup(path).filter(|n| n.kind == NodeKind::File).map(|n| n.data)
+    }
+}
@@ -0,0 +1,289 @@
+//! USTAR (POSIX.1-1988 / GNU tar) archive reader. cpio archives are not
+//! supported.
+
+use crate::{Node, NodeKind, ReadOnlyFs};
+
+const BLOCK: usize = 512;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TarError {
+    /// Header checksum doesn't match.
+    BadChecksum,
+    /// Not a USTAR header (missing `ustar` magic).
+    NotUstar,
+    /// Size field isn't a valid octal number.
+    BadSize,
+    /// Entry data runs past the end of the archive.
+    Truncated,
+    /// Name or prefix isn't UTF-8.
+    BadName,
+}
+
+impl TarError {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            TarError::BadChecksum => "tar header checksum mismatch",
+            TarError::NotUstar => "not a USTAR archive",
+            TarError::BadSize => "invalid tar size field",
+            TarError::Truncated => "tar entry runs past end of archive",
+            TarError::BadName => "tar entry name is not UTF-8",
+        }
+    }
+}
+
+/// A USTAR archive in memory.
+#[derive(Clone, Copy)]
+pub struct TarFs<'a> {
+    data: &'a [u8],
+}
+
+/// Iterator over archive entries. Stops at the end-of-archive marker (a zero
+/// block) or after yielding the first error.
+pub struct Entries<'a> {
+    data: &'a [u8],
+    off: usize,
+    done: bool,
+}
+
+fn field_str(b: &[u8]) -> Result<&str, TarError> {
+    let end = b.iter().position(|&c| c == 0).unwrap_or(b.len());
+    core::str::from_utf8(&b[..end]).map_err(|_| TarError::BadName)
+}
+
+fn parse_octal(b: &[u8]) -> Result<u64, TarError> {
+    let mut v: u64 = 0;
+    let mut seen = false;
+    for &c in b {
+        match c {
+            b'0'..=b'7' => {
+                v = v.checked_mul(8).ok_or(TarError::BadSize)? + (c - b'0') as u64;
+                seen = true;
+            }
+            b' ' if !seen => continue,
+            0 | b' ' => break,
+            _ => return Err(TarError::BadSize),
+        }
+    }
+    Ok(v)
+}
+
+fn checksum_ok(h: &[u8]) -> Result<bool, TarError> {
+    let stored = parse_octal(&h[148..156])?;
+    let sum: u64 = h.iter().enumerate()
+        .map(|(i, &b)| if (148..156).contains(&i) { b' ' as u64 } else { b as u64 })
+        .sum();
+    Ok(sum == stored)
+}
+
+fn parse_header(h: &[u8]) -> Result<(Node<'_>, usize), TarError> {
+    if &h[257..262] != b"ustar" { return Err(TarError::NotUstar); }
+    if !checksum_ok(h)? { return Err(TarError::BadChecksum); }
+
+    let size = usize::try_from(parse_octal(&h[124..136])?).map_err(|_| TarError::BadSize)?;
+    let kind = match h[156] {
+        b'0' | 0 | b'7' => NodeKind::File,
+        b'5' => NodeKind::Directory,
+        b'2' => NodeKind::Symlink,
+        _ => NodeKind::Other,
+    };
+    let node = Node { prefix: field_str(&h[345..500])?, name: field_str(&h[0..100])?, kind, data: &[] };
+    Ok((node, size))
+}
+
+impl<'a> TarFs<'a> {
+    /// Wraps `data`, checking that it starts with a valid USTAR header.
+    pub fn new(data: &'a [u8]) -> Result<Self, TarError> {
+        if data.len() < BLOCK { return Err(TarError::Truncated); }
+        parse_header(&data[..BLOCK])?;
+        Ok(TarFs { data })
+    }
+
+    pub fn entries(&self) -> Entries<'a> {
+        Entries { data: self.data, off: 0, done: false }
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<Node<'a>, TarError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done { return None; }
+        let data: &'a [u8] = self.data;
+        let header = match data.get(self.off..self.off + BLOCK) {
+            Some(h) if h.iter().any(|&b| b != 0) => h,
+            _ => { self.done = true; return None; }
+        };
+
+        let res = parse_header(header).and_then(|(mut node, size)| {
+            let start = self.off + BLOCK;
+            let padded = size.checked_next_multiple_of(BLOCK).ok_or(TarError::Truncated)?;
+            // Directories, links and the like carry no data even if size is set.
+            let payload = if node.kind == NodeKind::File { size } else { 0 };
+            node.data = data.get(start..start + payload).ok_or(TarError::Truncated)?;
+            self.off = start + padded;
+            Ok(node)
+        });
+        if res.is_err() { self.done = true; }
+        Some(res)
+    }
+}
+
+impl<'a> ReadOnlyFs<'a> for TarFs<'a> {
+    fn lookup(&self, path: &str) -> Option<Node<'a>> {
+        // Later entries override earlier ones with the same name, as with `tar -x`.
+        self.entries().map_while(Result::ok).filter(|n| n.matches(path)).last()
+    }
+
+    fn visit(&self, f: &mut dyn FnMut(&Node<'a>)) {
+        for node in self.entries().map_while(Result::ok) {
+            f(&node);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+    use super::*;
+
+    fn set_checksum(h: &mut [u8]) {
+        h[148..156].fill(b' ');
+        let sum: u32 = h.iter().map(|&b| b as u32).sum();
+        h[148..156].copy_from_slice(std::format!("{:06o}\0 ", sum).as_bytes());
+    }
+
+    fn header(prefix: &str, name: &str, size: usize, kind: u8) -> Vec<u8> {
+        let mut h = vec![0u8; BLOCK];
+        h[..name.len()].copy_from_slice(name.as_bytes());
+        h[124..136].copy_from_slice(std::format!("{:011o}\0", size).as_bytes());
+        h[156] = kind;
+        h[257..263].copy_from_slice(b"ustar\0");
+        h[263..265].copy_from_slice(b"00");
+        h[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+        set_checksum(&mut h);
+        h
+    }
+
+    /// An archive of `(prefix, name, data)` files with the end marker.
+    fn archive(files: &[(&str, &str, &[u8])]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (prefix, name, data) in files {
+            out.extend(header(prefix, name, data.len(), b'0'));
+            out.extend_from_slice(data);
+            out.resize(out.len().next_multiple_of(BLOCK), 0);
+        }
+        out.resize(out.len() + 2 * BLOCK, 0);
+        out
+    }
+
+    fn first(data: &[u8]) -> Option<Result<Node<'_>, TarError>> {
+        TarFs { data }.entries().next()
+    }
+
+    #[test]
+    fn reads_entries() {
+        let tar = archive(&[("", "motd.txt", b"hello"), ("", "empty", b"")]);
+        let fs = TarFs::new(&tar).unwrap();
+        let names: Vec<_> = fs.entries().map(|n| n.unwrap().name).collect();
+        assert_eq!(names, ["motd.txt", "empty"]);
+        let motd = fs.lookup("motd.txt").unwrap();
+        assert_eq!(motd.kind, NodeKind::File);
+        assert_eq!(motd.data, b"hello");
+        assert!(fs.lookup("missing").is_none());
+    }
+
+    #[test]
+    fn directories_carry_no_data() {
+        let mut tar = header("", "etc/", 0, b'5');
+        tar.extend(archive(&[("", "etc/x", b"x")]));
+        let fs = TarFs::new(&tar).unwrap();
+        let dir = fs.lookup("/etc").unwrap();
+        assert_eq!(dir.kind, NodeKind::Directory);
+        assert!(dir.data.is_empty());
+        assert_eq!(fs.lookup("etc/x").unwrap().data, b"x");
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let mut tar = archive(&[("", "motd.txt", b"hello")]);
+        tar[0] = b'M';
+        assert_eq!(TarFs::new(&tar).err(), Some(TarError::BadChecksum));
+        assert_eq!(first(&tar).unwrap().err(), Some(TarError::BadChecksum));
+    }
+
+    #[test]
+    fn not_ustar() {
+        let mut tar = archive(&[("", "motd.txt", b"hello")]);
+        tar[257] = b'x';
+        assert_eq!(TarFs::new(&tar).err(), Some(TarError::NotUstar));
+        assert_eq!(TarFs::new(&tar[..BLOCK - 1]).err(), Some(TarError::Truncated));
+    }
+
+    #[test]
+    fn non_octal_size() {
+        for size in [b"0000000008\0\0", b"00000x00010\0", b"-0000000010\0"] {
+            let mut tar = archive(&[("", "motd.txt", b"hello")]);
+            tar[124..136].copy_from_slice(size);
+            set_checksum(&mut tar[..BLOCK]);
+            assert_eq!(first(&tar).unwrap().err(), Some(TarError::BadSize));
+        }
+        // Leading spaces and a space terminator are accepted.
+        let mut tar = archive(&[("", "motd.txt", b"hello")]);
+        tar[124..136].copy_from_slice(b"   5 \0\0\0\0\0\0\0");
+        set_checksum(&mut tar[..BLOCK]);
+        assert_eq!(first(&tar).unwrap().unwrap().data, b"hello");
+    }
+
+    #[test]
+    fn truncated_payload() {
+        let mut tar = header("", "big", 1000, b'0');
+        tar.extend_from_slice(&[7; 600]);
+        let fs = TarFs::new(&tar).unwrap();
+        let mut entries = fs.entries();
+        assert_eq!(entries.next().unwrap().err(), Some(TarError::Truncated));
+        assert!(entries.next().is_none());
+        assert!(fs.lookup("big").is_none());
+
+        // The largest size the field holds.
+        let mut tar = header("", "huge", 0, b'0');
+        tar[124..136].copy_from_slice(b"77777777777\0");
+        set_checksum(&mut tar);
+        assert_eq!(first(&tar).unwrap().err(), Some(TarError::Truncated));
+    }
+
+    #[test]
+    fn prefix_and_name_match_joined() {
+        let tar = archive(&[("usr/share", "doc/readme", b"r")]);
+        let fs = TarFs::new(&tar).unwrap();
+        for path in ["usr/share/doc/readme", "/usr/share/doc/readme", "./usr/share/doc/readme"] {
+            assert_eq!(fs.lookup(path).unwrap().data, b"r", "{}", path);
+        }
+        for path in ["doc/readme", "usr/share", "usr/sharedoc/readme", "usr/share/doc/readm"] {
+            assert!(fs.lookup(path).is_none(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn last_entry_wins() {
+        let tar = archive(&[("", "a.txt", b"one"), ("", "b.txt", b"b"), ("", "./a.txt", b"two")]);
+        let fs = TarFs::new(&tar).unwrap();
+        assert_eq!(fs.lookup("a.txt").unwrap().data, b"two");
+        assert_eq!(fs.lookup("b.txt").unwrap().data, b"b");
+    }
+
+    #[test]
+    fn stops_at_bad_entry() {
+        let mut tar = archive(&[("", "a", b"a"), ("", "b", b"b"), ("", "c", b"c")]);
+        tar[2 * BLOCK] = b'B';
+        let fs = TarFs::new(&tar).unwrap();
+        let got: Vec<_> = fs.entries().collect();
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[1].err(), Some(TarError::BadChecksum));
+        // Entries after a bad header are never looked at.
+        assert!(fs.lookup("c").is_none());
+        assert!(fs.lookup("a").is_some());
+    }
+}
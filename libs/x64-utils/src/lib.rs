@@ -3,5 +3,6 @@
 pub mod control;
 pub mod handoff;
 pub mod interrupts;
+pub mod msr;
 pub mod paging;
 pub mod stack;
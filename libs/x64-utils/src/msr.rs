@@ -0,0 +1,77 @@
+use core::arch::asm;
+
+pub const IA32_APIC_BASE: u32 = 0x1B;
+pub const IA32_EFER: u32 = 0xC000_0080;
+pub const IA32_FS_BASE: u32 = 0xC000_0100;
+pub const IA32_GS_BASE: u32 = 0xC000_0101;
+/// Swapped with `IA32_GS_BASE` by `swapgs`.
+pub const IA32_KERNEL_GS_BASE: u32 = 0xC000_0102;
+
+/// Read model-specific register `msr`.
+///
+/// # Safety
+/// `msr` must exist on this CPU; reading an unimplemented MSR raises #GP.
+#[inline(always)]
+pub unsafe fn read(msr: u32) -> u64 {
+    let (lo, hi): (u32, u32);
+    asm!("rdmsr", in("ecx") msr, out("eax") lo, out("edx") hi, options(nomem, nostack, preserves_flags));
+    ((hi as u64) << 32) | lo as u64
+}
+
+/// Write `value` to model-specific register `msr`.
+///
+/// # Safety
+/// `msr` must exist and accept `value`. Many MSRs change paging, segment
+/// bases or interrupt delivery; the caller owns the consequences.
+#[inline(always)]
+pub unsafe fn write(msr: u32, value: u64) {
+    asm!(
+    "wrmsr",
+    in("ecx") msr,
+    in("eax") value as u32,
+    in("edx") (value >> 32) as u32,
+    options(nostack, preserves_flags),
+    );
+}
+
+#[inline(always)] pub fn gs_base() -> u64 { unsafe { read(IA32_GS_BASE) } }
+#[inline(always)] pub fn kernel_gs_base() -> u64 { unsafe { read(IA32_KERNEL_GS_BASE) } }
+
+/// Point GS at `base`.
+///
+/// # Safety
+/// Code that reads through GS (per-CPU data) must find what it expects at `base`.
+#[inline(always)]
+pub unsafe fn set_gs_base(base: u64) {
+    write(IA32_GS_BASE, base)
+}
+
+/// Set the base `swapgs` exchanges with the active GS base.
+///
+/// # Safety
+/// Same as [`set_gs_base`], for the code that runs after the next `swapgs`.
+#[inline(always)]
+pub unsafe fn set_kernel_gs_base(base: u64) {
+    write(IA32_KERNEL_GS_BASE, base)
+}
+
+/// Exchange GS base with `IA32_KERNEL_GS_BASE`.
+///
+/// # Safety
+/// Must be paired on every kernel entry/exit path, or GS ends up pointing at
+/// the wrong data.
+#[inline(always)]
+pub unsafe fn swapgs() {
+    asm!("swapgs", options(nomem, nostack, preserves_flags));
+}
+
+/// Read the `u64` at `offset` bytes into the GS segment.
+///
+/// # Safety
+/// GS base plus `offset` must be mapped and hold a `u64`.
+#[inline(always)]
+pub unsafe fn gs_read_u64(offset: usize) -> u64 {
+    let v: u64;
+    asm!("mov {}, gs:[{}]", out(reg) v, in(reg) offset, options(readonly, nostack, preserves_flags));
+    v
+}
@@ -12,4 +12,62 @@ fn main() {
     let out = std::env::var("OUT_DIR").expect("OUT_DIR not set");
     println!("cargo:rustc-link-search=native={out}");
     println!("cargo:rustc-link-lib=static=jump_asm");
+
+    write_trusted_keys(&out);
+    write_logo(&out);
+}
+
+/// Emits `$OUT_DIR/trusted_keys.rs` from `RTOS_TRUSTED_KEYS`, a comma-separated
+/// list of hex Ed25519 public keys (as printed by `rtosk-sign keygen`).
+fn write_trusted_keys(out: &str) {
+    println!("cargo:rerun-if-env-changed=RTOS_TRUSTED_KEYS");
+    let list = std::env::var("RTOS_TRUSTED_KEYS").unwrap_or_default();
+
+    let mut src = String::from("pub static TRUSTED_KEYS: &[[u8; 32]] = &[\n");
+    for key in list.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+        if key.len() != 64 || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            panic!("RTOS_TRUSTED_KEYS: '{key}' is not a 32-byte hex public key");
+        }
+        src.push_str("    [");
+        for i in (0..64).step_by(2) {
+            src.push_str(&format!("0x{},", &key[i..i + 2]));
+        }
+        src.push_str("],\n");
+    }
+    src.push_str("];\n");
+
+    std::fs::write(std::path::Path::new(out).join("trusted_keys.rs"), src).expect("write trusted_keys.rs");
+}
+
+/// Decodes the splash logo PNG at build time into `$OUT_DIR/logo.rgba`
+/// (straight-alpha RGBA8, row-major) and emits `$OUT_DIR/logo.rs` with its
+/// dimensions and an `include_bytes!` of the pixels.
+fn write_logo(out: &str) {
+    let png_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../images/rtos-logo-transparent.png");
+    println!("cargo:rerun-if-changed={png_path}");
+
+    let file = std::fs::File::open(png_path).expect("open splash logo");
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().expect("decode splash logo header");
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf).expect("decode splash logo");
+    let pixels = &buf[..frame.buffer_size()];
+
+    let rgba: Vec<u8> = match frame.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => pixels.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+        png::ColorType::Indexed => unreachable!("palette is expanded by normalize_to_color8"),
+    };
+
+    let dir = std::path::Path::new(out);
+    std::fs::write(dir.join("logo.rgba"), &rgba).expect("write logo.rgba");
+    let src = format!(
+        "pub const LOGO_WIDTH: u32 = {};\npub const LOGO_HEIGHT: u32 = {};\n\
+         pub static LOGO_RGBA: &[u8] = include_bytes!(concat!(env!(\"OUT_DIR\"), \"/logo.rgba\"));\n",
+        frame.width, frame.height,
+    );
+    std::fs::write(dir.join("logo.rs"), src).expect("write logo.rs");
 }
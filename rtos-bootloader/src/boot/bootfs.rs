@@ -1,20 +1,36 @@
-use uefi::cstr16;
+use uefi::CStr16;
 use uefi::proto::media::file::{Directory, File, FileAttribute, FileInfo, FileMode, FileType, RegularFile};
 
-pub fn open_kernel(root: &mut Directory) -> uefi::Result<RegularFile> {
-    if let Ok(h) = root.open(cstr16!(r"\EFI\BOOT\KERNEL.RTOSK"), FileMode::Read, FileAttribute::empty()) {
-        match h.into_type()? {
-            FileType::Regular(f) => return Ok(f),
-            _ => {}
-        }
-    }
-    let h = root.open(cstr16!("KERNEL.RTOSK"), FileMode::Read, FileAttribute::empty())?;
+pub fn open_file(root: &mut Directory, path: &CStr16) -> uefi::Result<RegularFile> {
+    let h = root.open(path, FileMode::Read, FileAttribute::empty())?;
     match h.into_type()? {
         FileType::Regular(f) => Ok(f),
         _ => Err(uefi::Status::NOT_FOUND.into()),
     }
 }
 
+/// Opens a file by a UTF-8 path from the config; `/` is accepted as a separator.
+pub fn open_path(root: &mut Directory, path: &str) -> uefi::Result<RegularFile> {
+    let mut buf = [0u16; 256];
+    open_file(root, ucs2_path(path, &mut buf)?)
+}
+
+/// Converts a config path to a UEFI path in `buf`, turning `/` into `\`.
+pub fn ucs2_path<'a>(path: &str, buf: &'a mut [u16]) -> uefi::Result<&'a CStr16> {
+    let mut n = 0usize;
+    for ch in path.chars() {
+        if n + 1 >= buf.len() { return Err(uefi::Status::INVALID_PARAMETER.into()); }
+        let c = if ch == '/' { '\\' } else { ch };
+        let mut units = [0u16; 2];
+        let enc = c.encode_utf16(&mut units);
+        if enc.len() != 1 { return Err(uefi::Status::INVALID_PARAMETER.into()); }
+        buf[n] = enc[0];
+        n += 1;
+    }
+    buf[n] = 0;
+    CStr16::from_u16_with_nul(&buf[..=n]).map_err(|_| uefi::Status::INVALID_PARAMETER.into())
+}
+
 pub fn file_size(file: &mut RegularFile) -> Option<usize> {
     let mut info_buf = [0u8; 256];
     file.get_info::<FileInfo>(&mut info_buf).ok().map(|i| i.file_size() as usize)
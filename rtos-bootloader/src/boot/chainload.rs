@@ -0,0 +1,111 @@
+//! `efi` menu entries: other EFI applications (a UEFI shell, memtest) on
+//! the boot volume, started with LoadImage/StartImage. The entry's command
+//! line is passed as the image's load options. When the image exits, the
+//! bootloader goes back to the menu.
+
+use core::fmt::Write;
+use core::mem::MaybeUninit;
+use log::{info, warn};
+use uefi::boot::{self, LoadImageSource};
+use uefi::proto::device_path::build::{media::FilePath, DevicePathBuilder};
+use uefi::proto::loaded_image::LoadedImage;
+use uefi::proto::BootPolicy;
+use uefi::Status;
+use crate::boot::bootfs::ucs2_path;
+use crate::boot::console::{read_key, write_line};
+use crate::boot::error::{BootError, OrFail, Stage};
+use crate::boot::menu::{TextBuf, CMDLINE_MAX};
+use crate::boot::open;
+
+const POLL_US: usize = 50_000;
+
+/// Starts the application at `path` on the bootloader's own volume and waits
+/// for it to exit. Failures, and exits with an error status, are shown on
+/// the console until a key is pressed.
+pub fn run(loaded: &LoadedImage, path: &str, options: &str) {
+    info!("chainloading {}", path);
+    let status = match start(loaded, path, options) {
+        Ok(status) => status,
+        Err(e) => {
+            warn!("{}", e);
+            let mut line: TextBuf<160> = TextBuf::new();
+            let _ = write!(line, "Could not start {}: {}", path, e);
+            wait(line.as_str());
+            return;
+        }
+    };
+    info!("{} exited with {:?}", path, status);
+    if status.is_error() {
+        let mut line: TextBuf<160> = TextBuf::new();
+        let _ = write!(line, "{} exited with {:?}", path, status);
+        wait(line.as_str());
+    }
+}
+
+fn start(loaded: &LoadedImage, path: &str, options: &str) -> Result<Status, BootError> {
+    let device = loaded.device().or_fail(Stage::Chainload, "boot device unknown")?;
+    let device_path = open::open_device_path(device)
+        .or_fail(Stage::Chainload, "device path of boot volume")?;
+
+    // Full path: the boot volume's device path, then the file. LoadImage reads
+    // it through SimpleFileSystem and records device and file in the child's
+    // LoadedImage, so the application can find files next to it.
+    let mut name = [0u16; 256];
+    let name = ucs2_path(path, &mut name).or_fail(Stage::Chainload, "path not representable")?;
+    let mut buf = [MaybeUninit::<u8>::uninit(); 1024];
+    let mut builder = DevicePathBuilder::with_buf(&mut buf);
+    for node in device_path.node_iter() {
+        builder = builder.push(&node).ok().or_fail(Stage::Chainload, "device path too long")?;
+    }
+    let full = builder
+        .push(&FilePath { path_name: name })
+        .and_then(|b| b.finalize())
+        .ok()
+        .or_fail(Stage::Chainload, "device path too long")?;
+
+    let image = boot::load_image(
+        boot::image_handle(),
+        LoadImageSource::FromDevicePath { device_path: full, boot_policy: BootPolicy::ExactMatch },
+    )
+    .or_fail(Stage::Chainload, "LoadImage")?;
+
+    // UCS-2, NUL-terminated; must stay alive until the image exits.
+    let mut opts = [0u16; CMDLINE_MAX + 1];
+    let mut n = 0;
+    for c in options.chars().take(CMDLINE_MAX) {
+        opts[n] = if (c as u32) > 0xFFFF || c == '\0' { b'?' as u16 } else { c as u16 };
+        n += 1;
+    }
+    if n > 0 {
+        match open::open_loaded_image(image) {
+            Ok(mut child) => unsafe {
+                child.set_load_options(opts.as_ptr() as *const u8, ((n + 1) * 2) as u32);
+            },
+            Err(s) => {
+                let _ = boot::unload_image(image);
+                return Err(BootError::new(Stage::Chainload, s, "LoadedImage of child"));
+            }
+        }
+    }
+
+    // StartImage returns once the image calls Exit; applications are
+    // unloaded by the firmware at that point.
+    match boot::start_image(image) {
+        Ok(()) => Ok(Status::SUCCESS),
+        Err(e) => {
+            // If it never ran (e.g. a Secure Boot rejection) it is still
+            // loaded; an already unloaded handle is just refused.
+            let _ = boot::unload_image(image);
+            Ok(e.status())
+        }
+    }
+}
+
+fn wait(message: &str) {
+    write_line("");
+    write_line(message);
+    write_line("Press any key to return to the menu");
+    while read_key().is_none() {
+        boot::stall(POLL_US);
+    }
+}
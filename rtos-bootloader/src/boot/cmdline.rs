@@ -0,0 +1,51 @@
+use uefi::proto::loaded_image::LoadedImage;
+use log::info;
+use crate::boot::menu::{Selection, TextBuf, CMDLINE_MAX};
+
+/// Final kernel command line. Precedence: a line edited in the boot menu,
+/// then the image's LoadOptions (e.g. `bootx64.efi log_level=4` from the UEFI
+/// shell), then boot.cfg.
+pub fn resolve(loaded: &LoadedImage, sel: &Selection) -> TextBuf<CMDLINE_MAX> {
+    if !sel.edited {
+        if let Some(opts) = from_load_options(loaded) {
+            info!("cmdline from LoadOptions");
+            return opts;
+        }
+    }
+    TextBuf::from_text(sel.cmdline.as_str())
+}
+
+/// LoadOptions as ASCII text, minus a leading `*.efi` program path that the
+/// shell includes. Boot-manager entries often carry binary data instead; anything
+/// that isn't a NUL-terminated UCS-2 string is ignored.
+fn from_load_options(loaded: &LoadedImage) -> Option<TextBuf<CMDLINE_MAX>> {
+    let opts = loaded.load_options_as_cstr16().ok()?;
+    let mut raw: TextBuf<CMDLINE_MAX> = TextBuf::new();
+    for ch in opts.iter() {
+        let c = u16::from(*ch);
+        if !(0x20..0x7f).contains(&c) && c != u16::from(b'\t') { return None; }
+        raw.push(c as u8);
+    }
+
+    let text = raw.as_str().trim();
+    let args = match text.split_once(char::is_whitespace) {
+        Some((first, rest)) if is_efi_path(first) => rest.trim_start(),
+        None if is_efi_path(text) => "",
+        _ => text,
+    };
+    if args.is_empty() { return None; }
+    Some(TextBuf::from_text(args))
+}
+
+/// Value of the first `key=value` token in `cmdline`, for the few options the
+/// bootloader itself acts on (e.g. `video=`).
+pub fn option<'a>(cmdline: &'a str, key: &str) -> Option<&'a str> {
+    cmdline.split_whitespace()
+        .filter_map(|tok| tok.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v)
+}
+
+fn is_efi_path(s: &str) -> bool {
+    s.len() >= 4 && s[s.len() - 4..].eq_ignore_ascii_case(".efi")
+}
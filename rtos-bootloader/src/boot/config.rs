@@ -0,0 +1,60 @@
+use core::slice;
+use boot_config::{BootConfig, ConfigError, LogLevel, SplashMode};
+use uefi::boot::{self, AllocateType};
+use uefi::cstr16;
+use uefi::mem::memory_map::MemoryType;
+use uefi::proto::media::file::Directory;
+use crate::boot::bootfs;
+use log::{info, warn};
+
+/// Largest boot.cfg we bother reading.
+const MAX_CONFIG_BYTES: usize = 64 * 1024;
+
+/// Loads and parses `\EFI\RTOS\boot.cfg`. A missing file yields the default
+/// (empty) config; a broken one reports each bad line and keeps the rest.
+/// The file stays in LOADER_DATA pages so the parsed strings can borrow it.
+pub fn load_config(root: &mut Directory) -> BootConfig<'static> {
+    let mut file = match bootfs::open_file(root, cstr16!(r"\EFI\RTOS\boot.cfg")) {
+        Ok(f) => f,
+        Err(_) => { info!("no boot.cfg; using defaults"); return BootConfig::default(); }
+    };
+
+    let size = match bootfs::file_size(&mut file) {
+        Some(sz) if sz <= MAX_CONFIG_BYTES => sz,
+        Some(_) => { warn!("boot.cfg too large; ignored"); return BootConfig::default(); }
+        None => { warn!("boot.cfg size unknown; ignored"); return BootConfig::default(); }
+    };
+    if size == 0 { return BootConfig::default(); }
+
+    let pages = size.div_ceil(4096);
+    let buf = match boot::allocate_pages(AllocateType::AnyPages, MemoryType::LOADER_DATA, pages) {
+        Ok(p) => unsafe { slice::from_raw_parts_mut(p.as_ptr(), size) },
+        Err(_) => { warn!("boot.cfg allocation failed; ignored"); return BootConfig::default(); }
+    };
+    if bootfs::read_exact(&mut file, buf).is_err() {
+        warn!("boot.cfg read failed; ignored");
+        return BootConfig::default();
+    }
+
+    let text = match core::str::from_utf8(buf) {
+        Ok(t) => t,
+        Err(e) => {
+            warn!("boot.cfg is not UTF-8 (byte {}); ignored", e.valid_up_to());
+            return BootConfig::default();
+        }
+    };
+
+    let cfg = BootConfig::parse(text, &mut report_error);
+    info!("loaded boot.cfg");
+    cfg
+}
+
+fn report_error(err: ConfigError) {
+    warn!("boot.cfg line {}: {}", err.line, err.kind.as_str());
+}
+
+/// Log level used when boot.cfg doesn't set one; the full boot trace.
+pub const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Debug;
+
+/// Splash used when boot.cfg doesn't set one; the logo as the old trampoline drew it.
+pub const DEFAULT_SPLASH: SplashMode = SplashMode::Nearest;
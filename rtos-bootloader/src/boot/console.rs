@@ -1,43 +1,72 @@
+use core::fmt;
 use uefi::data_types::CStr16;
+use uefi::proto::console::text::Key;
 use uefi::system;
 
-pub fn write_line(text: &str) {
-    let mut buf = [0u16; 192];
-    let mut i = 0usize;
-    for &b in text.as_bytes() {
-        if i + 3 >= buf.len() { break; }
-        buf[i] = b as u16;
-        i += 1;
+/// `fmt::Write` onto the UEFI text console. Text is converted to UCS-2 and
+/// sent in fixed-size chunks, so lines of any length come out whole; `\n`
+/// becomes `\r\n`. Call `flush` (or drop) to emit the tail.
+pub struct ConsoleWriter {
+    buf: [u16; 129],
+    len: usize,
+}
+
+impl ConsoleWriter {
+    pub const fn new() -> Self {
+        ConsoleWriter { buf: [0; 129], len: 0 }
     }
-    if i + 3 < buf.len() {
-        buf[i] = b'\r' as u16; i += 1;
-        buf[i] = b'\n' as u16; i += 1;
-        buf[i] = 0;
-        // SAFETY: buf is NUL-terminated and bounded.
-        let s16: &CStr16 = unsafe { CStr16::from_u16_with_nul_unchecked(&buf[..=i]) };
+
+    fn put(&mut self, unit: u16) {
+        if self.len == self.buf.len() - 1 { self.flush(); }
+        self.buf[self.len] = unit;
+        self.len += 1;
+    }
+
+    pub fn flush(&mut self) {
+        if self.len == 0 { return; }
+        self.buf[self.len] = 0;
+        // SAFETY: NUL-terminated just above, and no interior NULs are stored.
+        let s16 = unsafe { CStr16::from_u16_with_nul_unchecked(&self.buf[..=self.len]) };
         let _ = system::with_stdout(|out| out.output_string(s16));
+        self.len = 0;
     }
 }
 
-pub fn write_hex(label: &str, value: u64) {
-    let mut tmp = [0u8; 64];
-    let mut n = 0usize;
-    for &b in label.as_bytes() { tmp[n] = b; n += 1; }
-    tmp[n] = b' '; n += 1; tmp[n] = b'0'; n += 1; tmp[n] = b'x'; n += 1;
-
-    let mut started = false;
-    for i in (0..16).rev() {
-        let nyb = ((value >> (i*4)) & 0xF) as u8;
-        let ch = if nyb < 10 { b'0' + nyb } else { b'a' + (nyb - 10) };
-        if !started && ch == b'0' && i != 0 { continue; }
-        started = true; tmp[n] = ch; n += 1;
+impl fmt::Write for ConsoleWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '\n' => { self.put(b'\r' as u16); self.put(b'\n' as u16); }
+                '\0' => {}
+                // UCS-2 only covers the BMP.
+                c if (c as u32) > 0xFFFF => self.put(b'?' as u16),
+                c => self.put(c as u16),
+            }
+        }
+        Ok(())
     }
+}
 
-    if let Ok(s) = core::str::from_utf8(&tmp[..n]) {
-        write_line(s);
+impl Drop for ConsoleWriter {
+    fn drop(&mut self) {
+        self.flush();
     }
 }
 
+/// Writes one line of UI text (menus, error screen). Boot progress goes
+/// through the `log` macros instead.
+pub fn write_line(text: &str) {
+    use fmt::Write;
+    let mut out = ConsoleWriter::new();
+    let _ = out.write_str(text);
+    let _ = out.write_str("\n");
+}
+
 pub fn clear_screen() {
     let _ = system::with_stdout(|out| out.clear());
-}
\ No newline at end of file
+}
+
+/// Next key press, if one is waiting.
+pub fn read_key() -> Option<Key> {
+    system::with_stdin(|input| input.read_key().ok().flatten())
+}
@@ -1,196 +1,310 @@
-use core::{cmp::max, slice};
+use core::cmp::max;
 
-use crate::boot::{bootfs, map, open, prepare, trampoline::trampoline_jump};
-use crate::boot::console::{write_hex, write_line, clear_screen};
-use crate::rtosk::{parse_header_and_segments, find_magic};
-use crate::serial_writer::SerialWriter;
-use crate::serial_logb;
+use crate::boot::{chainload, cmdline, config, exit, initrd, inventory, logger, map, menu, open, prepare, screen, slot, trampoline::trampoline_jump};
+use crate::boot::source::{DeviceSpec, Source};
+use crate::boot::splash::Splash;
+use crate::boot::console::clear_screen;
+use crate::boot::error::{BootError, OrFail, Stage};
+use crate::boot::guard::Pages;
+use crate::rtosk::{parse_header_and_segments, find_magic, verify_signature, signature_required};
+use log::{debug, error, info, warn};
 use rtos_framebuffer::framebuffer::Framebuffer;
 use rtos_framebuffer::framebuffer::mode::{pick, aspect::AspectRatio};
+use rtos_framebuffer::framebuffer::mode::request::ModeRequest;
+use rtos_types::crc32::rtosk_image_crc32;
 use rtos_types::{boot_info::BootInfo, framebuffer_info::FramebufferInfo, framebuffer_format::FramebufferFormat, constants::RTOSK_MAGIC};
+use uefi::Status;
 
-pub fn boot_entry() -> uefi::Status {
+/// Everything the jump to the kernel needs, once no UEFI protocol is open.
+struct Handoff {
+    entry: usize,
+    stack: Pages,
+    boot_info: Pages,
+    exit: exit::ExitBuffers,
+}
+
+pub fn boot_entry() -> Status {
     clear_screen();
-    write_line("BL: boot_entry start");
+    logger::init();
+    info!("boot_entry start");
+
+    match load() {
+        Ok(handoff) => enter_kernel(handoff),
+        Err(e) => {
+            error!("{}", e);
+            screen::report(&e)
+        }
+    }
+}
 
+/// Loads and maps the kernel and fills in BootInfo. Protocols, files and
+/// temporary pages are scoped to this function, so they are all closed or
+/// freed before boot services are exited, and on every error path.
+fn load() -> Result<Handoff, BootError> {
     let image = uefi::boot::image_handle();
 
-    let loaded = match open::open_loaded_image(image) {
-        Ok(x) => { write_line("BL: opened loaded_image"); x }
-        Err(e) => { write_line("BL: ERROR open_loaded_image"); return e; }
-    };
+    let loaded = open::open_loaded_image(image)
+        .or_fail(Stage::LoadedImage, "LoadedImage protocol")?;
+    info!("opened loaded_image");
 
-    let mut sfs = match open::open_simple_fs(&loaded) {
-        Ok(x) => { write_line("BL: opened SimpleFileSystem"); x }
-        Err(e) => { write_line("BL: ERROR open_simple_fs"); return e; }
-    };
+    let mut sfs = open::open_simple_fs(&loaded)
+        .or_fail(Stage::FileSystem, "SimpleFileSystem on boot device")?;
+    info!("opened SimpleFileSystem");
 
-    let mut root = match open::open_root_dir(&mut *sfs) {
-        Ok(x) => { write_line("BL: opened root dir"); x }
-        Err(e) => { write_line("BL: ERROR open_root_dir"); return e; }
-    };
+    let mut root = open::open_root_dir(&mut sfs)
+        .or_fail(Stage::FileSystem, "open volume root")?;
+    info!("opened root dir");
 
-    // Load kernel
-    let mut kfile = match bootfs::open_kernel(&mut root) {
-        Ok(f) => { write_line("BL: opened KERNEL.RTOSK"); f }
-        Err(e) => { write_line("BL: ERROR open_kernel"); return e.status(); }
-    };
+    let cfg = config::load_config(&mut root);
+    logger::set_level(cfg.log_level.unwrap_or(config::DEFAULT_LOG_LEVEL));
+
+    let firmware = inventory::collect();
+    if inventory::requested(&cfg) {
+        inventory::show(&firmware);
+    }
 
-    let kernel_size = match bootfs::file_size(&mut kfile) {
-        Some(sz) => { write_hex("BL: kernel_size", sz as u64); sz }
-        None => { write_line("BL: ERROR get kernel size"); return uefi::Status::LOAD_ERROR; }
+    // `efi` entries run and then return here; the menu comes back without
+    // a countdown so the same entry isn't started again unattended.
+    let mut countdown = true;
+    let mut selection = loop {
+        let selection = menu::choose(&cfg, countdown);
+        let Some(path) = selection.efi else { break selection };
+        chainload::run(&loaded, path, selection.cmdline.as_str());
+        countdown = false;
     };
+    let slot = slot::select(&cfg, &mut selection);
+    let kernel_cmdline = cmdline::resolve(&loaded, &selection);
+    if !kernel_cmdline.is_empty() {
+        info!("cmdline: {}", kernel_cmdline.as_str());
+    }
+
+    // Set the GOP mode before loading so the splash covers the slow part.
+    // Once it is up, log records only go to serial and the boot log ring.
+    let fb = init_framebuffer(&cfg, kernel_cmdline.as_str());
+    let splash = Splash::show(&fb, cfg.splash.unwrap_or(config::DEFAULT_SPLASH));
+    if splash.is_active() {
+        logger::console_off();
+    }
+    splash.status("Loading kernel");
 
-    // Allocate temp buffer for kernel file
-    let pages = (kernel_size + 4095) / 4096;
-    let buf_ptr = match
-        uefi::boot::allocate_pages(
-            uefi::boot::AllocateType::AnyPages,
-            uefi::mem::memory_map::MemoryType::LOADER_DATA,
-            pages,
-        )
-    {
-        Ok(p) => p,
-        Err(e) => { write_line("BL: ERROR allocate buffer"); return e.status(); }
+    // Kernel and initrd come from the boot volume unless boot.cfg `device`
+    // names another one.
+    let spec = match selection.device {
+        Some(text) => DeviceSpec::parse(text).unwrap_or_else(|| {
+            warn!("boot.cfg device '{}' not understood; using the boot volume", text);
+            DeviceSpec::Boot
+        }),
+        None => DeviceSpec::Boot,
     };
+    let mut source = Source::open(spec, loaded.device(), root, selection.kernel)?;
 
-    // Track allocation so we can free it on all paths
-    let mut blob_alloc = Some((buf_ptr, pages));
+    // Load kernel; the temp buffer is freed when `blob` goes out of scope.
+    let (mut blob, kernel_size) = source.read_kernel(selection.kernel)
+        .or_fail(Stage::Kernel, "kernel file not found")?;
+    info!("read kernel {}", selection.kernel.unwrap_or("KERNEL.RTOSK"));
+    debug!("kernel_size {:#x}", kernel_size);
+    let blob_slice = &mut blob.as_mut_slice()[..kernel_size];
+    info!("kernel blob loaded");
+    splash.progress(30);
 
-    let blob_base = buf_ptr.as_ptr() as usize;
-    let blob_slice = unsafe { slice::from_raw_parts_mut(blob_base as *mut u8, pages * 4096) };
-    if let Err(e) = bootfs::read_exact(&mut kfile, &mut blob_slice[..kernel_size]) {
-        write_line("BL: ERROR kernel read");
-        if let Some((addr, cnt)) = blob_alloc.take() {
-            unsafe { let _ = uefi::boot::free_pages(addr, cnt); }
-        }
-        return e.status();
+    // Find RTOSK header
+    let magic_off = find_magic(blob_slice, &RTOSK_MAGIC)
+        .or_fail(Stage::Image, "RTOSK magic not found")?;
+    debug!("RTOSK off {:#x}", magic_off);
+
+    // Parse header and segments
+    let image_bytes = &blob_slice[magic_off..];
+    let (header, segments, header_len, seg_bytes) = parse_header_and_segments(image_bytes)
+        .ok()
+        .or_fail(Stage::Image, "malformed RTOSK header or segment table")?;
+
+    debug!("entry64 {:#x}", header.entry64);
+    debug!("seg_count {}", header.seg_count);
+    debug!("page_size {:#x}", header.page_size);
+    debug!("hdr.len {:#x}", header_len);
+    debug!("segments_bytes {:#x}", seg_bytes);
+
+    for (i, seg) in segments.iter().enumerate() {
+        debug!(
+            "seg[{}] file_offset {:#x} file_size {:#x} memory_addr {:#x} memory_size {:#x} flags {:#x}",
+            i, seg.file_offset, seg.file_size, seg.memory_addr, seg.memory_size, seg.flags,
+        );
     }
-    write_line("BL: kernel blob loaded");
 
-    // Find RTOSK header
-    let magic_off = find_magic(&blob_slice[..kernel_size], &RTOSK_MAGIC).unwrap_or(usize::MAX);
-    if magic_off == usize::MAX {
-        write_line("BL: ERROR RTOSK magic not found");
-        if let Some((addr, cnt)) = blob_alloc.take() {
-            unsafe { let _ = uefi::boot::free_pages(addr, cnt); }
+    // Network transfers can arrive truncated; catch that even in builds
+    // that accept unsigned kernels.
+    if source.is_network() {
+        if rtosk_image_crc32(image_bytes) != header.image_crc32 {
+            return Err(BootError::new(Stage::Image, Status::CRC_ERROR, "image CRC mismatch"));
         }
-        return uefi::Status::LOAD_ERROR;
+        info!("kernel CRC OK");
     }
-    write_hex("BL: RTOSK off", magic_off as u64);
 
-    // Parse header and segments
-    let image_bytes = &blob_slice[magic_off..kernel_size];
-    let (header, segments, header_len, seg_bytes) = match parse_header_and_segments(image_bytes) {
-        Ok(t) => t,
-        Err(_) => {
-            write_line("BL: ERROR parse RTOSK");
-            if let Some((addr, cnt)) = blob_alloc.take() {
-                unsafe { let _ = uefi::boot::free_pages(addr, cnt); }
-            }
-            return uefi::Status::LOAD_ERROR;
+    // Signature check before anything from the image is trusted
+    match verify_signature(image_bytes) {
+        Ok(()) => info!("kernel signature OK"),
+        Err(e) if !signature_required() => {
+            warn!("kernel signature not verified (dev-unsigned build): {}", e.as_str());
         }
-    };
+        Err(e) => return Err(BootError::new(Stage::Signature, Status::SECURITY_VIOLATION, e.as_str())),
+    }
 
-    write_hex("BL: entry64", header.entry64 as u64);
-    write_hex("BL: seg_count", header.seg_count as u64);
-    write_hex("BL: page_size", header.page_size as u64);
-    write_hex("BL: hdr.len", header_len as u64);
-    write_hex("BL: segments_bytes", seg_bytes as u64);
+    splash.progress(50);
 
-    for (i, seg) in segments.iter().enumerate() {
-        write_hex("BL: seg[i]", i as u64);
-        write_hex("  file_offset", seg.file_offset as u64);
-        write_hex("  file_size", seg.file_size as u64);
-        write_hex("  memory_addr", seg.memory_addr as u64);
-        write_hex("  memory_size", seg.memory_size as u64);
-        write_hex("  flags", seg.flags as u64);
+    // Verify entry
+    let entry = header.entry64 as usize;
+    if entry == 0 {
+        return Err(BootError::new(Stage::Image, Status::LOAD_ERROR, "header.entry64 is 0"));
     }
+    debug!("entry (header.entry64) {:#x}", entry);
 
     // Stack + boot info
     let page_size = max(header.page_size as usize, 4096usize);
-    let (stack_top, boot_info) = match prepare::prepare_stack_and_info(page_size) {
-        Ok(t) => t,
-        Err(e) => {
-            write_line("BL: ERROR prepare_stack_and_info");
-            if let Some((addr, cnt)) = blob_alloc.take() {
-                unsafe { let _ = uefi::boot::free_pages(addr, cnt); }
-            }
-            return e;
-        }
-    };
+    let stack_pages = cfg.stack_size
+        .map(|b| (b as usize).div_ceil(page_size))
+        .unwrap_or(prepare::DEFAULT_STACK_PAGES);
+    let (stack, mut boot_info) = prepare::prepare_stack_and_info(page_size, stack_pages)
+        .or_fail(Stage::Memory, "out of memory")?;
 
     // Map kernel segments
-    if let Err(e) = map::map_segments(segments, image_bytes) {
-        write_line("BL: ERROR map_segments");
-        if let Some((addr, cnt)) = blob_alloc.take() {
-            unsafe { let _ = uefi::boot::free_pages(addr, cnt); }
+    splash.status("Mapping kernel");
+    map::map_segments(segments, image_bytes)?;
+    splash.progress(70);
+
+    // We no longer need the temp kernel blob; free it before loading modules.
+    drop(blob);
+    debug!("freed temp kernel blob");
+
+    // Initrd modules are loaded after the kernel so a missing file can't
+    // stop the kernel from booting.
+    let (modules_ptr, module_count) = match selection.initrd {
+        Some(paths) => {
+            splash.status("Loading modules");
+            initrd::load_modules(&mut source, paths)
+                .or_fail(Stage::Initrd, "allocate module table")?
         }
-        return e;
+        None => (0, 0),
+    };
+    splash.progress(90);
+
+    // Last allocation: sized from the map as it is now, plus headroom.
+    let exit = exit::prepare()?;
+
+    // Write framebuffer info and command line into BootInfo for the trampoline
+    let (cmdline_ptr, cmdline_len) = prepare::write_cmdline(&mut boot_info, kernel_cmdline.as_str());
+    let boot_info_addr = boot_info.addr() as *mut BootInfo;
+    let boot_log_ptr = logger::boot_log_addr();
+    unsafe {
+        core::ptr::write(boot_info_addr, BootInfo {
+            framebuffer: fb,
+            cmdline_ptr,
+            cmdline_len,
+            modules_ptr,
+            module_count,
+            boot_log_ptr,
+            // Filled in after ExitBootServices.
+            memory_map_ptr: 0,
+            memory_map_len: 0,
+            firmware,
+            boot_slot: slot.slot,
+            boot_tries_left: slot.tries_left,
+            runtime_services: runtime_services_addr(),
+            // Filled in after ExitBootServices.
+            runtime_map_ptr: 0,
+            runtime_map_len: 0,
+        });
     }
 
-    // We no longer need the temp kernel blob; free it BEFORE ExitBootServices
-    if let Some((addr, cnt)) = blob_alloc.take() {
-        unsafe { let _ = uefi::boot::free_pages(addr, cnt); }
-        write_line("BL: freed temp kernel blob");
+    splash.status("Starting kernel");
+    splash.progress(100);
+
+    Ok(Handoff { entry, stack, boot_info, exit })
+}
+
+/// The runtime services table stays valid after ExitBootServices.
+fn runtime_services_addr() -> u64 {
+    uefi::table::system_table_raw().map_or(0, |st| unsafe { st.as_ref().runtime_services as u64 })
+}
+
+/// GOP mode request. Precedence: `video=` on the command line, then boot.cfg
+/// `video`, then boot.cfg `resolution` (exact, else closest), then the
+/// largest mode for boot.cfg `aspect` (default 16:9).
+fn video_request(cfg: &boot_config::BootConfig<'static>, kernel_cmdline: &str) -> ModeRequest {
+    let sources = [("command line", cmdline::option(kernel_cmdline, "video")), ("boot.cfg", cfg.video)];
+    for (from, text) in sources {
+        if let Some(text) = text {
+            match ModeRequest::parse(text) {
+                Some(req) => return req,
+                None => warn!("{} video '{}' not understood; ignored", from, text),
+            }
+        }
     }
 
-    // --- Framebuffer setup ---
-    write_line("BL: init framebuffer");
+    if let Some((w, h)) = cfg.resolution {
+        return ModeRequest::Exact(w, h);
+    }
+    let aspect = match cfg.aspect {
+        Some((n, d)) => AspectRatio::from_tuple(n, d).unwrap_or_else(|| {
+            warn!("boot.cfg aspect not supported; using 16:9");
+            AspectRatio::Ratio16_9
+        }),
+        None => AspectRatio::Ratio16_9,
+    };
+    ModeRequest::Aspect(aspect)
+}
 
+fn init_framebuffer(cfg: &boot_config::BootConfig<'static>, kernel_cmdline: &str) -> FramebufferInfo {
+    info!("init framebuffer");
 
-    let fb = match Framebuffer::new_from_aspect(AspectRatio::Ratio16_9) {
+    match Framebuffer::new_from_request(video_request(cfg, kernel_cmdline)) {
         Ok(fb) => {
-            write_hex("BL: fb.base", fb.base as u64);
-            write_hex("BL: fb.size", fb.size as u64);
-            write_hex("BL: fb.width", fb.width as u64);
-            write_hex("BL: fb.height", fb.height as u64);
-            write_hex("BL: fb.stride", fb.stride as u64);
-            write_hex("BL: fb.format", fb.format as u32 as u64);
+            info!(
+                "framebuffer {}x{} stride {} {} at {:#x} ({:#x} bytes)",
+                fb.width, fb.height, fb.stride, fb.format.as_str(), fb.base, fb.size,
+            );
+            match fb.format {
+                FramebufferFormat::Bitmask => debug!(
+                    "pixel masks r {:#010x} g {:#010x} b {:#010x} x {:#010x}",
+                    fb.red_mask, fb.green_mask, fb.blue_mask, fb.reserved_mask,
+                ),
+                FramebufferFormat::BltOnly => info!("Blt-only mode; the kernel gets no framebuffer"),
+                _ => {}
+            }
             fb
         }
         Err(_) => {
-            write_line("BL: WARN no framebuffer");
-            FramebufferInfo {
-                base: 0,
-                size: 0,
-                width: 0,
-                height: 0,
-                stride: 0,
-                format: FramebufferFormat::BltOnly,
-            }
+            warn!("no framebuffer");
+            FramebufferInfo::empty()
         }
-    };
-
-    // Verify entry
-    let entry_ptr = header.entry64 as usize;
-    if entry_ptr == 0 {
-        write_line("BL: FATAL header.entry64 = 0");
-        return uefi::Status::LOAD_ERROR;
     }
-    write_hex("BL: entry (header.entry64)", entry_ptr as u64);
+}
 
-    // Write framebuffer info into BootInfo for the trampoline
-    let boot_info_addr = boot_info as *mut BootInfo;
-    unsafe { core::ptr::write(boot_info_addr, BootInfo { framebuffer: fb }); }
+/// Exits boot services and jumps to the kernel. From here on the kernel owns
+/// the stack and BootInfo pages, and failures can only be reported over
+/// serial and the framebuffer.
+fn enter_kernel(handoff: Handoff) -> ! {
+    let stack_top = handoff.stack.end();
+    handoff.stack.leak();
+    let boot_info = handoff.boot_info.leak();
 
     // First instruction in kmain:
-    write_line("BL: disabling interrupts");
+    info!("disabling interrupts");
     x86_64::instructions::interrupts::disable();
 
+    info!("exiting boot services");
+    logger::console_off();
+    screen::mark_exited(boot_info);
+    let map = match unsafe { exit::exit_boot_services(handoff.exit) } {
+        Ok(map) => map,
+        Err(e) => screen::halt_after_exit(&e),
+    };
+    let bi = boot_info as *mut BootInfo;
+    unsafe {
+        (*bi).memory_map_ptr = map.regions.as_ptr() as u64;
+        (*bi).memory_map_len = map.regions.len() as u64;
+        (*bi).runtime_map_ptr = map.runtime.as_ptr() as u64;
+        (*bi).runtime_map_len = map.runtime.len() as u64;
+    }
 
-    write_line("BL: exiting boot services");
-    let _ = unsafe { uefi::boot::exit_boot_services(None) };
-    SerialWriter::init();
-    serial_logb!("exited boot services!!!");
-
-    // jump
-    extern "win64" { fn jump_to_kernel(entry: usize, stack_top: usize, boot_info: usize) -> !; }
-    unsafe { jump_to_kernel(entry_ptr, stack_top, boot_info) }
-
-    //let _ = unsafe { uefi::boot::exit_boot_services(None) };
-    //trampoline_jump(entry_ptr, stack_top, boot_info);
-
-    // Trampoline should not have returned so return device error if it does return
-    uefi::Status::DEVICE_ERROR
+    trampoline_jump(handoff.entry, stack_top, boot_info)
 }
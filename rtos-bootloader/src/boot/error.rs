@@ -0,0 +1,82 @@
+use core::fmt;
+use uefi::Status;
+
+/// Boot step that failed, shown on the error screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    LoadedImage,
+    FileSystem,
+    Kernel,
+    Image,
+    Signature,
+    Memory,
+    Mapping,
+    Initrd,
+    Handoff,
+    /// Loading or starting an `efi` menu entry.
+    Chainload,
+    /// A panic inside the bootloader itself.
+    Internal,
+}
+
+impl Stage {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Stage::LoadedImage => "open loaded image",
+            Stage::FileSystem => "open boot file system",
+            Stage::Kernel => "load kernel file",
+            Stage::Image => "parse kernel image",
+            Stage::Signature => "verify kernel signature",
+            Stage::Memory => "allocate stack and boot info",
+            Stage::Mapping => "map kernel segments",
+            Stage::Initrd => "load initrd",
+            Stage::Handoff => "hand off to kernel",
+            Stage::Chainload => "start EFI application",
+            Stage::Internal => "bootloader internal error",
+        }
+    }
+}
+
+/// A failed boot: where it failed, the UEFI status, and what was being done.
+#[derive(Clone, Copy, Debug)]
+pub struct BootError {
+    pub stage: Stage,
+    pub status: Status,
+    pub context: &'static str,
+}
+
+impl BootError {
+    pub const fn new(stage: Stage, status: Status, context: &'static str) -> Self {
+        BootError { stage, status, context }
+    }
+}
+
+impl fmt::Display for BootError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({:?})", self.stage.as_str(), self.context, self.status)
+    }
+}
+
+/// Attaches a stage and context to a failed UEFI call.
+pub trait OrFail<T> {
+    fn or_fail(self, stage: Stage, context: &'static str) -> Result<T, BootError>;
+}
+
+impl<T> OrFail<T> for Result<T, Status> {
+    fn or_fail(self, stage: Stage, context: &'static str) -> Result<T, BootError> {
+        self.map_err(|s| BootError::new(stage, s, context))
+    }
+}
+
+impl<T, D: fmt::Debug> OrFail<T> for uefi::Result<T, D> {
+    fn or_fail(self, stage: Stage, context: &'static str) -> Result<T, BootError> {
+        self.map_err(|e| BootError::new(stage, e.status(), context))
+    }
+}
+
+impl<T> OrFail<T> for Option<T> {
+    /// `None` is reported as `LOAD_ERROR`.
+    fn or_fail(self, stage: Stage, context: &'static str) -> Result<T, BootError> {
+        self.ok_or(BootError::new(stage, Status::LOAD_ERROR, context))
+    }
+}
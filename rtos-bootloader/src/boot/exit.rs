@@ -0,0 +1,208 @@
+//! The ExitBootServices sequence and the kernel's memory map.
+//!
+//! Both buffers are allocated while boot services are still fully usable;
+//! between the first ExitBootServices attempt and the jump to the kernel
+//! the only firmware calls are GetMemoryMap and ExitBootServices.
+
+use core::{mem, ptr, slice};
+use log::{debug, info, warn};
+use uefi::mem::memory_map::{MemoryAttribute, MemoryDescriptor, MemoryMap, MemoryType};
+use uefi::{boot, Status};
+use uefi_raw::table::system::SystemTable;
+use rtos_types::constants::{RTOS_BOOT_LOG_MEMORY_TYPE, RTOS_MODULE_MEMORY_TYPE};
+use rtos_types::crc32::crc32;
+use rtos_types::memory_map::{MemoryKind, MemoryRegion, RuntimeRegion};
+use crate::boot::error::{BootError, OrFail, Stage};
+use crate::boot::guard::Pages;
+
+/// Descriptors of headroom for allocations between sizing the buffers and
+/// exiting (our own two buffers, framebuffer setup, firmware timers).
+const SLACK_DESCRIPTORS: usize = 32;
+/// GetMemoryMap/ExitBootServices rounds before giving up.
+const MAX_ATTEMPTS: usize = 8;
+
+/// Pre-allocated space for the final UEFI map, its converted form and its
+/// runtime ranges.
+pub struct ExitBuffers {
+    raw: Pages,
+    regions: Pages,
+    runtime: Pages,
+}
+
+/// The final map in kernel form, and the runtime descriptors as reported.
+pub struct FinalMap {
+    pub regions: &'static [MemoryRegion],
+    pub runtime: &'static [RuntimeRegion],
+}
+
+/// Sizes and allocates the buffers from the current map.
+pub fn prepare() -> Result<ExitBuffers, BootError> {
+    let meta = boot::memory_map(MemoryType::LOADER_DATA)
+        .or_fail(Stage::Memory, "read memory map")?
+        .meta();
+    let raw_len = meta.map_size + SLACK_DESCRIPTORS * meta.desc_size;
+    let max_entries = raw_len / meta.desc_size;
+
+    let raw = Pages::for_bytes(MemoryType::LOADER_DATA, raw_len)
+        .or_fail(Stage::Memory, "allocate memory map buffer")?;
+    let regions = Pages::for_bytes(MemoryType::LOADER_DATA, max_entries * mem::size_of::<MemoryRegion>())
+        .or_fail(Stage::Memory, "allocate memory map buffer")?;
+    let runtime = Pages::for_bytes(MemoryType::LOADER_DATA, max_entries * mem::size_of::<RuntimeRegion>())
+        .or_fail(Stage::Memory, "allocate memory map buffer")?;
+    debug!("memory map: {} bytes now, buffer {} bytes", meta.map_size, raw.size());
+    Ok(ExitBuffers { raw, regions, runtime })
+}
+
+/// Exits boot services, retrying while the map key goes stale, and returns
+/// the final map in kernel form (sorted, merged) with its runtime ranges.
+///
+/// # Safety
+/// Nothing that calls into boot services may run after this succeeds: open
+/// protocols, files and `Pages` guards must already be gone or leaked, and
+/// console logging must be off. Afterwards the system table's boot service
+/// pointers are null, so a stray call panics instead of entering firmware.
+pub unsafe fn exit_boot_services(bufs: ExitBuffers) -> Result<FinalMap, BootError> {
+    let raw_cap = bufs.raw.size();
+    let raw = bufs.raw.leak() as *mut u8;
+    let regions_cap = bufs.regions.size() / mem::size_of::<MemoryRegion>();
+    let regions = bufs.regions.leak() as *mut MemoryRegion;
+    let runtime_cap = bufs.runtime.size() / mem::size_of::<RuntimeRegion>();
+    let runtime = bufs.runtime.leak() as *mut RuntimeRegion;
+
+    let st = uefi::table::system_table_raw()
+        .or_fail(Stage::Handoff, "no system table")?
+        .as_ptr();
+    let bt = &*(*st).boot_services;
+    let image = boot::image_handle().as_ptr();
+
+    let mut status = Status::ABORTED;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let mut size = raw_cap;
+        let mut key = 0usize;
+        let mut desc_size = 0usize;
+        let mut desc_version = 0u32;
+        let s = (bt.get_memory_map)(&mut size, raw as *mut MemoryDescriptor, &mut key, &mut desc_size, &mut desc_version);
+        if s == Status::BUFFER_TOO_SMALL {
+            // Allocating is not allowed any more once an exit attempt failed.
+            return Err(BootError::new(Stage::Handoff, s, "memory map outgrew its buffer"));
+        }
+        if s != Status::SUCCESS {
+            return Err(BootError::new(Stage::Handoff, s, "GetMemoryMap failed"));
+        }
+
+        status = (bt.exit_boot_services)(image, key);
+        if status == Status::SUCCESS {
+            seal_system_table(st);
+            let runtime = runtime_ranges(raw, size, desc_size, runtime, runtime_cap);
+            let map = convert(raw, size, desc_size, regions, regions_cap);
+            info!(
+                "exited boot services (attempt {}), {} memory regions, {} runtime",
+                attempt, map.len(), runtime.len(),
+            );
+            return Ok(FinalMap { regions: map, runtime });
+        }
+        if status != Status::INVALID_PARAMETER {
+            return Err(BootError::new(Stage::Handoff, status, "ExitBootServices failed"));
+        }
+        warn!("ExitBootServices: stale map key (attempt {}); retrying", attempt);
+    }
+    Err(BootError::new(Stage::Handoff, status, "memory map kept changing during ExitBootServices"))
+}
+
+/// Firmware must clear the boot service and console pointers on exit; make
+/// sure of it so `uefi::boot`/`uefi::system` calls fail loudly from here on.
+unsafe fn seal_system_table(st: *mut SystemTable) {
+    let t = &mut *st;
+    if t.boot_services.is_null() && t.stdout.is_null() && t.stdin.is_null() && t.stderr.is_null() { return; }
+    warn!("firmware left boot services in the system table; clearing them");
+    t.boot_services = ptr::null_mut();
+    t.stdin_handle = ptr::null_mut();
+    t.stdin = ptr::null_mut();
+    t.stdout_handle = ptr::null_mut();
+    t.stdout = ptr::null_mut();
+    t.stderr_handle = ptr::null_mut();
+    t.stderr = ptr::null_mut();
+    t.header.crc = 0;
+    let len = t.header.size as usize;
+    t.header.crc = crc32(slice::from_raw_parts(st as *const u8, len));
+}
+
+pub(crate) fn kind_of(ty: MemoryType) -> MemoryKind {
+    match ty {
+        MemoryType::CONVENTIONAL | MemoryType::BOOT_SERVICES_CODE | MemoryType::BOOT_SERVICES_DATA => MemoryKind::Usable,
+        MemoryType::LOADER_CODE | MemoryType::LOADER_DATA => MemoryKind::Loader,
+        MemoryType::RUNTIME_SERVICES_CODE => MemoryKind::RuntimeCode,
+        MemoryType::RUNTIME_SERVICES_DATA => MemoryKind::RuntimeData,
+        MemoryType::ACPI_RECLAIM => MemoryKind::AcpiReclaimable,
+        MemoryType::ACPI_NON_VOLATILE => MemoryKind::AcpiNvs,
+        MemoryType::MMIO | MemoryType::MMIO_PORT_SPACE => MemoryKind::Mmio,
+        MemoryType::UNUSABLE => MemoryKind::Unusable,
+        t if t.0 == RTOS_MODULE_MEMORY_TYPE => MemoryKind::Modules,
+        t if t.0 == RTOS_BOOT_LOG_MEMORY_TYPE => MemoryKind::BootLog,
+        _ => MemoryKind::Reserved,
+    }
+}
+
+/// Copies the descriptors with `EFI_MEMORY_RUNTIME` into `out` unchanged;
+/// `SetVirtualAddressMap` expects the firmware's own ranges.
+unsafe fn runtime_ranges(raw: *const u8, size: usize, desc_size: usize, out: *mut RuntimeRegion, cap: usize) -> &'static [RuntimeRegion] {
+    let out = slice::from_raw_parts_mut(out, cap);
+    let mut n = 0;
+    for i in 0..size / desc_size {
+        let d = ptr::read_unaligned(raw.add(i * desc_size) as *const MemoryDescriptor);
+        if !d.att.contains(MemoryAttribute::RUNTIME) || d.page_count == 0 || n == cap { continue; }
+        out[n] = RuntimeRegion {
+            ty: d.ty.0,
+            pad: 0,
+            phys_start: d.phys_start,
+            virt_start: 0,
+            page_count: d.page_count,
+            attribute: d.att.bits(),
+        };
+        n += 1;
+    }
+    &out[..n]
+}
+
+/// Converts `size` bytes of UEFI descriptors into `out`, sorts by base and
+/// merges neighbours with the same kind and attributes.
+unsafe fn convert(raw: *const u8, size: usize, desc_size: usize, out: *mut MemoryRegion, cap: usize) -> &'static [MemoryRegion] {
+    let out = slice::from_raw_parts_mut(out, cap);
+    let mut n = 0;
+    for i in 0..(size / desc_size).min(cap) {
+        let d = ptr::read_unaligned(raw.add(i * desc_size) as *const MemoryDescriptor);
+        if d.page_count == 0 { continue; }
+        out[n] = MemoryRegion {
+            base: d.phys_start,
+            pages: d.page_count,
+            attribute: d.att.bits(),
+            kind: kind_of(d.ty),
+            reserved: 0,
+        };
+        n += 1;
+    }
+
+    // Insertion sort: the map is small and usually nearly sorted already.
+    for i in 1..n {
+        let mut j = i;
+        while j > 0 && out[j - 1].base > out[j].base {
+            out.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+
+    let mut merged = 0;
+    for i in 0..n {
+        let r = out[i];
+        if merged > 0 {
+            let prev = &mut out[merged - 1];
+            if prev.kind == r.kind && prev.attribute == r.attribute && prev.end() == r.base {
+                prev.pages += r.pages;
+                continue;
+            }
+        }
+        out[merged] = r;
+        merged += 1;
+    }
+    &out[..merged]
+}
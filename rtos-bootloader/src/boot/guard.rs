@@ -0,0 +1,56 @@
+use core::{mem, slice};
+use core::ptr::NonNull;
+use uefi::boot::{self, AllocateType};
+use uefi::mem::memory_map::MemoryType;
+
+pub const UEFI_PAGE_SIZE: usize = 4096;
+
+/// Pages from `allocate_pages` that go back to the firmware when dropped,
+/// so early returns can't leak them. Memory the kernel keeps is `leak`ed.
+pub struct Pages {
+    ptr: NonNull<u8>,
+    count: usize,
+}
+
+impl Pages {
+    pub fn allocate(ty: MemoryType, count: usize) -> uefi::Result<Self> {
+        let ptr = boot::allocate_pages(AllocateType::AnyPages, ty, count)?;
+        Ok(Pages { ptr, count })
+    }
+
+    /// Enough pages for `bytes` (at least one).
+    pub fn for_bytes(ty: MemoryType, bytes: usize) -> uefi::Result<Self> {
+        Self::allocate(ty, bytes.div_ceil(UEFI_PAGE_SIZE).max(1))
+    }
+
+    pub fn addr(&self) -> usize {
+        self.ptr.as_ptr() as usize
+    }
+
+    pub fn size(&self) -> usize {
+        self.count * UEFI_PAGE_SIZE
+    }
+
+    pub fn end(&self) -> usize {
+        self.addr() + self.size()
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: we own `count` freshly allocated pages at `ptr`.
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.size()) }
+    }
+
+    /// Keeps the pages allocated for good and returns their address.
+    pub fn leak(self) -> usize {
+        let addr = self.addr();
+        mem::forget(self);
+        addr
+    }
+}
+
+impl Drop for Pages {
+    fn drop(&mut self) {
+        // SAFETY: allocated by `allocate` and not handed out past our lifetime.
+        unsafe { let _ = boot::free_pages(self.ptr, self.count); }
+    }
+}
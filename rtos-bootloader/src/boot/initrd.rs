@@ -0,0 +1,80 @@
+use core::fmt::Write;
+use core::{mem, slice};
+use uefi::mem::memory_map::MemoryType;
+use rtos_types::boot_module::BootModule;
+use rtos_types::constants::RTOS_MODULE_MEMORY_TYPE;
+use rtos_types::crc32::crc32;
+use crate::boot::source::Source;
+use log::{debug, info, warn};
+use crate::boot::guard::{Pages, UEFI_PAGE_SIZE};
+use crate::boot::menu::TextBuf;
+use crate::boot::net::MAX_NAME;
+
+const MODULE_MEMORY: MemoryType = MemoryType::custom(RTOS_MODULE_MEMORY_TYPE);
+/// One page of descriptors.
+const MAX_MODULES: usize = UEFI_PAGE_SIZE / mem::size_of::<BootModule>();
+
+/// Loads every file in the comma-separated `paths` into module memory and
+/// builds the `BootModule` table. Returns (table address, count); a file that
+/// can't be loaded, or that came over the network and fails its CRC check,
+/// is reported and skipped rather than stopping the boot.
+pub fn load_modules(source: &mut Source, paths: &str) -> uefi::Result<(u64, u64)> {
+    let table_page = Pages::allocate(MODULE_MEMORY, 1)?;
+    let table = unsafe { slice::from_raw_parts_mut(table_page.addr() as *mut BootModule, MAX_MODULES) };
+    let mut count = 0usize;
+
+    for path in paths.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        if count == MAX_MODULES {
+            warn!("too many initrd files; rest ignored");
+            break;
+        }
+        match load_one(source, path) {
+            Some((base, size)) => {
+                table[count] = BootModule::new(base, size, path);
+                count += 1;
+                info!("loaded initrd {} at {:#x} ({} bytes)", path, base, size);
+            }
+            None => {
+                warn!("initrd not loaded: {}", path);
+            }
+        }
+    }
+
+    if count == 0 { return Ok((0, 0)); }
+    Ok((table_page.leak() as u64, count as u64))
+}
+
+fn load_one(source: &mut Source, path: &str) -> Option<(u64, u64)> {
+    let (mut pages, size) = source.read(path, MODULE_MEMORY).ok()?;
+    if source.is_network() {
+        let want = expected_crc(source, path)?;
+        let got = crc32(&pages.as_mut_slice()[..size]);
+        if got != want {
+            warn!("initrd {}: CRC {:08x}, expected {:08x}", path, got, want);
+            return None;
+        }
+        debug!("initrd {}: CRC OK", path);
+    }
+    Some((pages.leak() as u64, size as u64))
+}
+
+/// CRC-32 of a module fetched over the network, from `<path>.crc32` next to
+/// it on the server: 8 hex digits, optionally followed by anything else
+/// (`crc32 FILE > FILE.crc32`).
+fn expected_crc(source: &mut Source, path: &str) -> Option<u32> {
+    let mut name: TextBuf<MAX_NAME> = TextBuf::new();
+    let _ = write!(name, "{}.crc32", path);
+    let Ok((mut pages, size)) = source.read(name.as_str(), MemoryType::LOADER_DATA) else {
+        warn!("initrd {}: no {} to check it against", path, name.as_str());
+        return None;
+    };
+    let text = core::str::from_utf8(&pages.as_mut_slice()[..size]).ok();
+    let crc = text
+        .and_then(|t| t.split_whitespace().next())
+        .filter(|hex| hex.len() == 8)
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+    if crc.is_none() {
+        warn!("initrd {}: {} is not a CRC-32", path, name.as_str());
+    }
+    crc
+}
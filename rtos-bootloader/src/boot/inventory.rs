@@ -0,0 +1,251 @@
+//! Hardware inventory. Firmware facts (vendor, revisions, Secure Boot, ACPI
+//! and SMBIOS entry points, storage counts) are collected on every boot and
+//! handed to the kernel in `BootInfo::firmware`. The inventory screen lists
+//! them together with the memory map, GOP modes and block devices; it is
+//! shown when boot.cfg sets `inventory = true` or `i` is held at start-up.
+
+use core::fmt::{self, Write};
+use boot_config::BootConfig;
+use log::info;
+use uefi::boot::{self, OpenProtocolAttributes, SearchType};
+use uefi::mem::memory_map::{MemoryMap, MemoryType};
+use uefi::proto::console::gop::GraphicsOutput;
+use uefi::proto::console::text::Key;
+use uefi::proto::media::block::BlockIO;
+use uefi::proto::media::fs::SimpleFileSystem;
+use uefi::runtime::{self, VariableVendor};
+use uefi::table::cfg::{ACPI2_GUID, ACPI_GUID, SMBIOS3_GUID, SMBIOS_GUID};
+use uefi::{cstr16, system, CStr16, Identify};
+use rtos_framebuffer::framebuffer::mode::edid;
+use rtos_types::firmware_info::FirmwareInfo;
+use rtos_types::memory_map::MemoryKind;
+use crate::boot::console::{clear_screen, read_key, write_line};
+use crate::boot::exit::kind_of;
+use crate::boot::menu::TextBuf;
+use crate::boot::open::{first_handle, params};
+
+/// How long a held key is looked for at start-up.
+const HOLD_POLLS: usize = 5;
+const POLL_US: usize = 10_000;
+
+/// Screen width the mode list is wrapped to.
+const LINE_WIDTH: usize = 78;
+/// Block devices listed before the rest are summarised.
+const MAX_DEVICES: usize = 6;
+
+/// Gathers what the kernel gets in `BootInfo::firmware` and logs a summary.
+pub fn collect() -> FirmwareInfo {
+    let mut fw = FirmwareInfo::empty();
+    fw.uefi_revision = system::uefi_revision().0;
+    fw.firmware_revision = system::firmware_revision();
+
+    let mut vendor: TextBuf<{ FirmwareInfo::VENDOR_LEN }> = TextBuf::new();
+    let _ = write!(vendor, "{}", system::firmware_vendor());
+    fw.set_vendor(vendor.as_str());
+
+    fw = system::with_config_table(|tables| {
+        let mut fw = fw;
+        for t in tables {
+            let addr = t.address as u64;
+            // Prefer the newer entry points when both are installed.
+            if t.guid == ACPI2_GUID {
+                (fw.acpi_rsdp, fw.acpi_version) = (addr, 2);
+            } else if t.guid == ACPI_GUID && fw.acpi_version < 2 {
+                (fw.acpi_rsdp, fw.acpi_version) = (addr, 1);
+            } else if t.guid == SMBIOS3_GUID {
+                (fw.smbios_entry, fw.smbios_version) = (addr, 3);
+            } else if t.guid == SMBIOS_GUID && fw.smbios_version < 3 {
+                (fw.smbios_entry, fw.smbios_version) = (addr, 2);
+            }
+        }
+        fw
+    });
+
+    fw.secure_boot = secure_boot_state();
+    fw.block_devices = handles_with(&BlockIO::GUID) as u32;
+    fw.filesystems = handles_with(&SimpleFileSystem::GUID) as u32;
+
+    info!(
+        "firmware {} rev {:#x}, UEFI {}.{:02}, secure boot {}",
+        fw.vendor(), fw.firmware_revision, fw.uefi_major(), fw.uefi_minor(), fw.secure_boot_str(),
+    );
+    info!(
+        "ACPI {} at {:#x}, SMBIOS {} at {:#x}, {} block devices, {} filesystems",
+        fw.acpi_version, fw.acpi_rsdp, fw.smbios_version, fw.smbios_entry, fw.block_devices, fw.filesystems,
+    );
+    fw
+}
+
+/// True when boot.cfg asks for the inventory screen or `i` is held down.
+/// A different key pressed this early is consumed.
+pub fn requested(cfg: &BootConfig<'static>) -> bool {
+    if cfg.inventory == Some(true) {
+        return true;
+    }
+    for _ in 0..HOLD_POLLS {
+        match read_key() {
+            Some(Key::Printable(c)) => return matches!(char::from(c), 'i' | 'I'),
+            Some(_) => return false,
+            None => boot::stall(POLL_US),
+        }
+    }
+    false
+}
+
+/// Draws the inventory screen and waits for a key.
+pub fn show(fw: &FirmwareInfo) {
+    clear_screen();
+    write_line("RTOS hardware inventory");
+    write_line("");
+
+    line(format_args!("Firmware     {} rev {:#x}", fw.vendor(), fw.firmware_revision));
+    line(format_args!("UEFI         {}.{:02}, Secure Boot {}", fw.uefi_major(), fw.uefi_minor(), fw.secure_boot_str()));
+    match fw.acpi_version {
+        0 => write_line("ACPI         not found"),
+        v => line(format_args!("ACPI         {} RSDP at {:#x}", if v == 2 { "2.0+" } else { "1.0" }, fw.acpi_rsdp)),
+    }
+    match fw.smbios_version {
+        0 => write_line("SMBIOS       not found"),
+        v => line(format_args!("SMBIOS       {}.x entry point at {:#x}", v, fw.smbios_entry)),
+    }
+    show_memory();
+    show_video();
+    show_storage(fw);
+
+    write_line("");
+    write_line("Press any key to continue booting");
+    while read_key().is_none() {
+        boot::stall(POLL_US);
+    }
+    clear_screen();
+}
+
+/// Totals of the current memory map, grouped the way the kernel sees it.
+fn show_memory() {
+    let map = match boot::memory_map(MemoryType::LOADER_DATA) {
+        Ok(map) => map,
+        Err(_) => return write_line("Memory       map unavailable"),
+    };
+    let (mut usable, mut loader, mut runtime, mut acpi, mut other, mut top) = (0u64, 0u64, 0u64, 0u64, 0u64, 0u64);
+    for d in map.entries() {
+        let pages = d.page_count;
+        match kind_of(d.ty) {
+            MemoryKind::Usable => {
+                usable += pages;
+                top = top.max(d.phys_start + pages * 4096);
+            }
+            MemoryKind::Loader | MemoryKind::Modules | MemoryKind::BootLog => loader += pages,
+            MemoryKind::RuntimeCode | MemoryKind::RuntimeData => runtime += pages,
+            MemoryKind::AcpiReclaimable | MemoryKind::AcpiNvs => acpi += pages,
+            _ => other += pages,
+        }
+    }
+    let mib = |pages: u64| pages / 256;
+    line(format_args!(
+        "Memory       {} MiB usable, {} MiB loader, {} MiB runtime, {} MiB ACPI",
+        mib(usable), mib(loader), mib(runtime), mib(acpi),
+    ));
+    line(format_args!(
+        "             {} MiB reserved/MMIO, {} descriptors, usable RAM up to {:#x}",
+        mib(other), map.len(), top,
+    ));
+}
+
+/// Current GOP mode, the EDID preferred mode and every mode on offer.
+fn show_video() {
+    let Some(handle) = first_handle(&GraphicsOutput::GUID) else {
+        return write_line("Video        no GOP");
+    };
+    // GetProtocol: the console driver keeps drawing through this GOP.
+    let gop = match unsafe { boot::open_protocol::<GraphicsOutput>(params(handle), OpenProtocolAttributes::GetProtocol) } {
+        Ok(gop) => gop,
+        Err(_) => return write_line("Video        GOP could not be opened"),
+    };
+
+    let cur = gop.current_mode_info();
+    let (w, h) = cur.resolution();
+    let mut head: TextBuf<160> = TextBuf::new();
+    let _ = write!(head, "Video        {}x{} {:?}", w, h, cur.pixel_format());
+    if let Some((ew, eh)) = edid::preferred_resolution(handle) {
+        let _ = write!(head, ", EDID prefers {}x{}", ew, eh);
+    }
+    let _ = write!(head, ", {} modes:", gop.modes().len());
+    write_line(head.as_str());
+
+    let mut row: TextBuf<{ LINE_WIDTH + 16 }> = TextBuf::new();
+    for mode in gop.modes() {
+        let (w, h) = mode.info().resolution();
+        let mut item: TextBuf<16> = TextBuf::new();
+        let _ = write!(item, " {}x{}", w, h);
+        if 13 + row.as_str().len() + item.as_str().len() > LINE_WIDTH {
+            line(format_args!("            {}", row.as_str()));
+            row = TextBuf::new();
+        }
+        let _ = row.write_str(item.as_str());
+    }
+    if !row.is_empty() {
+        line(format_args!("            {}", row.as_str()));
+    }
+}
+
+/// Block devices: size, block size, and whether they are partitions,
+/// removable or carry a filesystem the firmware can read.
+fn show_storage(fw: &FirmwareInfo) {
+    line(format_args!("Storage      {} block devices, {} filesystems", fw.block_devices, fw.filesystems));
+    let Ok(blocks) = boot::locate_handle_buffer(SearchType::ByProtocol(&BlockIO::GUID)) else {
+        return;
+    };
+    let filesystems = boot::locate_handle_buffer(SearchType::ByProtocol(&SimpleFileSystem::GUID)).ok();
+
+    for (i, &handle) in blocks.iter().enumerate().take(MAX_DEVICES) {
+        let Ok(bio) = (unsafe { boot::open_protocol::<BlockIO>(params(handle), OpenProtocolAttributes::GetProtocol) }) else {
+            continue;
+        };
+        let media = bio.media();
+        let mut l: TextBuf<160> = TextBuf::new();
+        let _ = write!(l, "  {}: ", i);
+        if media.is_media_present() {
+            let bytes = (media.last_block() + 1).saturating_mul(media.block_size() as u64);
+            let _ = write!(l, "{} MiB, {}-byte blocks", bytes >> 20, media.block_size());
+        } else {
+            let _ = l.write_str("no media");
+        }
+        let _ = l.write_str(if media.is_logical_partition() { ", partition" } else { ", disk" });
+        if media.is_removable_media() { let _ = l.write_str(", removable"); }
+        if media.is_read_only() { let _ = l.write_str(", read-only"); }
+        if filesystems.as_ref().is_some_and(|f| f.contains(&handle)) {
+            let _ = l.write_str(", filesystem");
+        }
+        write_line(l.as_str());
+    }
+    if blocks.len() > MAX_DEVICES {
+        line(format_args!("  ... and {} more", blocks.len() - MAX_DEVICES));
+    }
+}
+
+/// `SecureBoot` and `SetupMode` from the global variable namespace. Firmware
+/// without Secure Boot support has neither variable.
+fn secure_boot_state() -> u8 {
+    let flag = |name: &CStr16| {
+        let mut buf = [0u8; 1];
+        runtime::get_variable(name, &VariableVendor::GLOBAL_VARIABLE, &mut buf)
+            .ok()
+            .map(|(data, _)| data.first() == Some(&1))
+    };
+    match (flag(cstr16!("SecureBoot")), flag(cstr16!("SetupMode"))) {
+        (_, Some(true)) => FirmwareInfo::SECURE_BOOT_SETUP,
+        (Some(true), _) => FirmwareInfo::SECURE_BOOT_ON,
+        (Some(false), _) => FirmwareInfo::SECURE_BOOT_OFF,
+        (None, _) => FirmwareInfo::SECURE_BOOT_UNKNOWN,
+    }
+}
+
+fn handles_with(guid: &uefi::Guid) -> usize {
+    boot::locate_handle_buffer(SearchType::ByProtocol(guid)).map(|h| h.len()).unwrap_or(0)
+}
+
+fn line(args: fmt::Arguments) {
+    let mut l: TextBuf<160> = TextBuf::new();
+    let _ = l.write_fmt(args);
+    write_line(l.as_str());
+}
@@ -0,0 +1,111 @@
+//! `log` backend for the bootloader. Records go to the UEFI console (until
+//! boot services are exited), to COM1, and into a `BootLog` ring that the
+//! kernel receives through `BootInfo::boot_log_ptr`.
+//!
+//! The console and serial port only show records at or above the boot.cfg
+//! `log_level`; the ring keeps every record so the kernel's `dmesg` has the
+//! full boot history.
+
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use boot_config::LogLevel;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use uefi::mem::memory_map::MemoryType;
+use rtos_types::boot_log::BootLog;
+use rtos_types::constants::RTOS_BOOT_LOG_MEMORY_TYPE;
+use crate::boot::console::ConsoleWriter;
+use crate::boot::guard::Pages;
+use crate::serial_writer::SerialWriter;
+
+/// Ring size handed to the kernel (64 KiB).
+const BOOT_LOG_PAGES: usize = 16;
+
+struct BootLogger {
+    ring: UnsafeCell<Option<BootLog>>,
+    /// Highest `Level` (as usize) shown on the console and serial port.
+    shown: AtomicUsize,
+    console: AtomicBool,
+}
+
+// SAFETY: the bootloader runs on a single CPU and nothing logs from
+// interrupt context, so records are never written concurrently.
+unsafe impl Sync for BootLogger {}
+unsafe impl Send for BootLogger {}
+
+static LOGGER: BootLogger = BootLogger {
+    ring: UnsafeCell::new(None),
+    shown: AtomicUsize::new(Level::Debug as usize),
+    console: AtomicBool::new(true),
+};
+
+/// Installs the logger and allocates the ring. Without the ring (out of
+/// memory) logging still reaches the console and serial port.
+pub fn init() {
+    SerialWriter::init();
+    if let Ok(mut pages) = Pages::allocate(MemoryType::custom(RTOS_BOOT_LOG_MEMORY_TYPE), BOOT_LOG_PAGES) {
+        let len = pages.size();
+        let log = unsafe { BootLog::init(pages.as_mut_slice().as_mut_ptr(), len) };
+        pages.leak();
+        unsafe { *LOGGER.ring.get() = Some(log); }
+    }
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Trace);
+    }
+}
+
+/// Sets the console/serial threshold from boot.cfg.
+pub fn set_level(level: LogLevel) {
+    let level = match level {
+        LogLevel::Error => Level::Error,
+        LogLevel::Warn => Level::Warn,
+        LogLevel::Info => Level::Info,
+        LogLevel::Debug => Level::Debug,
+        LogLevel::Trace => Level::Trace,
+    };
+    LOGGER.shown.store(level as usize, Ordering::Relaxed);
+}
+
+/// Stops console output; call right before `exit_boot_services`.
+pub fn console_off() {
+    LOGGER.console.store(false, Ordering::Release);
+}
+
+/// Address of the ring for `BootInfo::boot_log_ptr` (0 if none).
+pub fn boot_log_addr() -> u64 {
+    unsafe { (*LOGGER.ring.get()).as_ref().map_or(0, |r| r.addr()) }
+}
+
+/// Fans one record out to every enabled sink.
+struct Sinks<'a> {
+    console: Option<ConsoleWriter>,
+    serial: bool,
+    ring: Option<&'a mut BootLog>,
+}
+
+impl Write for Sinks<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if let Some(c) = self.console.as_mut() { let _ = c.write_str(s); }
+        if self.serial { SerialWriter::write(s); }
+        if let Some(r) = self.ring.as_mut() { r.push(s.as_bytes()); }
+        Ok(())
+    }
+}
+
+impl Log for BootLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let shown = record.level() as usize <= self.shown.load(Ordering::Relaxed);
+        let mut out = Sinks {
+            console: (shown && self.console.load(Ordering::Acquire)).then(ConsoleWriter::new),
+            serial: shown,
+            ring: unsafe { (*self.ring.get()).as_mut() },
+        };
+        let _ = writeln!(out, "BL: {:<5} {}", record.level(), record.args());
+    }
+
+    fn flush(&self) {}
+}
@@ -1,22 +1,72 @@
-use core::ptr;
+use core::{ptr, slice};
+use core::ops::Range;
+use core::ptr::NonNull;
 use uefi::boot::{self, AllocateType};
-use uefi::mem::memory_map::MemoryType;
+use uefi::mem::memory_map::{MemoryAttribute, MemoryType};
+use uefi::proto::security::MemoryProtection;
 use rtos_types::{rtosk_segment::RtoskSegment, constants::RTOSK_EXEC_FLAG};
-use crate::boot::console::{write_hex, write_line};
+use log::{debug, info, trace, warn};
+use crate::boot::error::{BootError, OrFail, Stage};
 
-pub fn map_segments(segments: &[RtoskSegment], image_bytes: &[u8]) -> Result<(), uefi::Status> {
-    const MAX_TRACKED: usize = 4096;
+const PAGE: usize = 0x1000;
+/// Usually one run per segment; more only where segments overlap.
+const MAX_RUNS: usize = 256;
 
-    let mut pages: [usize; MAX_TRACKED] = [0; MAX_TRACKED];
-    let mut count: usize = 0;
+/// Page runs `(start, end)` allocated at segment addresses. If mapping
+/// fails they are all returned to the firmware; on success `keep` hands
+/// them to the kernel.
+struct SegmentPages {
+    runs: [(usize, usize); MAX_RUNS],
+    count: usize,
+}
 
-    let seen = |base: usize, c: usize, p: &[usize; MAX_TRACKED]| -> bool {
-        for i in 0..c { if p[i] == base { return true; } }
-        false
-    };
-    let track = |base: usize, c: &mut usize, p: &mut [usize; MAX_TRACKED]| {
-        if *c < MAX_TRACKED { p[*c] = base; *c += 1; } else { write_line("BL: map warn: page tracker full; may re-alloc"); }
-    };
+impl SegmentPages {
+    fn seen(&self, page: usize) -> bool {
+        self.runs[..self.count].iter().any(|&(start, end)| (start..end).contains(&page))
+    }
+
+    /// Allocates the pages in `start..end` that no earlier segment has
+    /// already, one run at a time.
+    fn allocate(&mut self, start: usize, end: usize, mem_ty: MemoryType) -> Result<(), BootError> {
+        let mut page = start;
+        while page < end {
+            if self.seen(page) {
+                trace!("map page already alloc {:#x}", page);
+                page += PAGE;
+                continue;
+            }
+            let run = page;
+            while page < end && !self.seen(page) {
+                page += PAGE;
+            }
+            if self.count == MAX_RUNS {
+                return Err(BootError::new(Stage::Mapping, uefi::Status::OUT_OF_RESOURCES, "too many segment page runs"));
+            }
+            boot::allocate_pages(AllocateType::Address(run as u64), mem_ty, (page - run) / PAGE)
+                .or_fail(Stage::Mapping, "segment address not available")?;
+            self.runs[self.count] = (run, page);
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    fn keep(mut self) {
+        self.count = 0;
+    }
+}
+
+impl Drop for SegmentPages {
+    fn drop(&mut self) {
+        for &(start, end) in &self.runs[..self.count] {
+            if let Some(p) = NonNull::new(start as *mut u8) {
+                unsafe { let _ = boot::free_pages(p, (end - start) / PAGE); }
+            }
+        }
+    }
+}
+
+pub fn map_segments(segments: &[RtoskSegment], image_bytes: &[u8]) -> Result<(), BootError> {
+    let mut tracked = SegmentPages { runs: [(0, 0); MAX_RUNS], count: 0 };
 
     for (i, seg) in segments.iter().enumerate() {
         let tgt = seg.memory_addr as usize;
@@ -24,51 +74,138 @@ pub fn map_segments(segments: &[RtoskSegment], image_bytes: &[u8]) -> Result<(),
         let file_off = seg.file_offset as usize;
         let file_len = seg.file_size as usize;
 
-        write_hex("BL: map seg", i as u64);
+        debug!("map seg {}", i);
+
+        if seg.is_tls() {
+            debug!("map skip (TLS template)");
+            continue;
+        }
 
         if mem_len == 0 {
-            write_line("BL: map skip (zero mem)");
+            debug!("map skip (zero mem)");
             continue;
         }
 
         let start_page = tgt & !0xfffusize;
         let end_page = (tgt + mem_len + 0xfff) & !0xfffusize;
-        write_hex("  start_page", start_page as u64);
-        write_hex("  end_page", end_page as u64);
+        trace!("  pages {:#x}..{:#x}", start_page, end_page);
 
         let mem_ty = if (seg.flags & RTOSK_EXEC_FLAG) != 0 { MemoryType::LOADER_CODE } else { MemoryType::LOADER_DATA };
 
-        let mut page = start_page;
-        while page < end_page {
-            if seen(page, count, &pages) {
-                write_hex("BL: map page already alloc", page as u64);
-            } else {
-                match boot::allocate_pages(AllocateType::Address(page as u64), mem_ty, 1) {
-                    Ok(_) => { track(page, &mut count, &mut pages); }
-                    Err(e) => { write_line("BL: ERROR allocate_pages"); return Err(e.status()); }
-                }
-            }
-            page += 0x1000;
-        }
+        tracked.allocate(start_page, end_page, mem_ty)?;
 
-        if file_len > 0 {
-            let end = file_off.saturating_add(file_len);
-            if end <= image_bytes.len() {
-                unsafe { ptr::copy_nonoverlapping(image_bytes[file_off..end].as_ptr(), tgt as *mut u8, file_len); }
-                write_hex("BL: map copied", file_len as u64);
+        if file_len > 0 && !seg.is_noload() {
+            if file_len > mem_len {
+                return Err(BootError::new(Stage::Mapping, uefi::Status::LOAD_ERROR, "segment file_size > memory_size"));
+            }
+            let stored = seg.stored_size() as usize;
+            let end = file_off.saturating_add(stored);
+            if end > image_bytes.len() {
+                return Err(BootError::new(Stage::Mapping, uefi::Status::LOAD_ERROR, "segment data outside image"));
+            }
+            let src = &image_bytes[file_off..end];
+            if seg.is_compressed() {
+                // Decompress straight into the freshly allocated target pages.
+                let dst = unsafe { slice::from_raw_parts_mut(tgt as *mut u8, file_len) };
+                rtosk_lz4::decompress_exact(src, dst, file_len)
+                    .map_err(|e| BootError::new(Stage::Mapping, uefi::Status::LOAD_ERROR, e.as_str()))?;
+                debug!("map decompressed {:#x}", file_len);
             } else {
-                write_line("BL: map ERROR file range OOB");
-                return Err(uefi::Status::LOAD_ERROR);
+                unsafe { ptr::copy_nonoverlapping(src.as_ptr(), tgt as *mut u8, file_len); }
+                debug!("map copied {:#x}", file_len);
             }
         }
 
-        if mem_len > file_len {
-            let zero_start = tgt + file_len;
-            let zero_len = mem_len - file_len;
+        let loaded = if seg.is_noload() { 0 } else { file_len };
+        if mem_len > loaded {
+            let zero_start = tgt + loaded;
+            let zero_len = mem_len - loaded;
             unsafe { ptr::write_bytes(zero_start as *mut u8, 0, zero_len); }
-            write_hex("BL: map zeroed", zero_len as u64);
+            debug!("map zeroed {:#x}", zero_len);
         }
     }
 
+    protect_segments(segments);
+    tracked.keep();
     Ok(())
 }
+
+/// R/W/X access of one page.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Access {
+    read: bool,
+    write: bool,
+    exec: bool,
+}
+
+impl Access {
+    const NONE: Access = Access { read: false, write: false, exec: false };
+
+    fn union(self, seg: &RtoskSegment) -> Access {
+        Access {
+            read: self.read || seg.is_readable(),
+            write: self.write || seg.is_writable(),
+            exec: self.exec || seg.is_executable(),
+        }
+    }
+}
+
+/// Whole pages covered by a segment that gets mapped.
+fn page_range(seg: &RtoskSegment) -> Option<(u64, u64)> {
+    if seg.is_tls() || seg.memory_size == 0 { return None; }
+    Some((seg.memory_addr & !0xfff, (seg.memory_addr + seg.memory_size + 0xfff) & !0xfff))
+}
+
+/// Access of the page at `page`: the union over every segment on it, so a
+/// segment sharing a boundary page with its neighbour (`.rodata` right after
+/// `.text`) never takes a permission away from it.
+fn page_access(segments: &[RtoskSegment], page: u64) -> Access {
+    segments
+        .iter()
+        .filter(|seg| page_range(seg).is_some_and(|(start, end)| (start..end).contains(&page)))
+        .fold(Access::NONE, Access::union)
+}
+
+/// Apply per-segment R/W/X permissions through `EFI_MEMORY_ATTRIBUTE_PROTOCOL`,
+/// which updates the firmware's page tables (present/writable/NX) in place.
+/// Must run after all payloads are copied, since read-only pages reject the copy.
+/// Firmware without the protocol keeps its default RWX identity mapping.
+fn protect_segments(segments: &[RtoskSegment]) {
+    let handle = match boot::get_handle_for_protocol::<MemoryProtection>() {
+        Ok(h) => h,
+        Err(_) => { info!("map protect skipped (no MemoryAttribute protocol)"); return; }
+    };
+    let proto = match boot::open_protocol_exclusive::<MemoryProtection>(handle) {
+        Ok(p) => p,
+        Err(_) => { warn!("map protect skipped (open MemoryAttribute failed)"); return; }
+    };
+
+    for (start, end) in segments.iter().filter_map(page_range) {
+        // Runs of pages with the same access; only boundary pages can differ.
+        let mut run = start;
+        while run < end {
+            let access = page_access(segments, run);
+            let mut next = run + 0x1000;
+            while next < end && page_access(segments, next) == access { next += 0x1000; }
+            protect(&proto, run..next, access);
+            run = next;
+        }
+    }
+    info!("map protections applied");
+}
+
+fn protect(proto: &MemoryProtection, range: Range<u64>, access: Access) {
+    let mut set = MemoryAttribute::empty();
+    if !access.read  { set |= MemoryAttribute::READ_PROTECT; }
+    if !access.write { set |= MemoryAttribute::READ_ONLY; }
+    if !access.exec  { set |= MemoryAttribute::EXECUTE_PROTECT; }
+    let all = MemoryAttribute::READ_PROTECT | MemoryAttribute::READ_ONLY | MemoryAttribute::EXECUTE_PROTECT;
+    let clear = all.difference(set);
+
+    if !clear.is_empty() && proto.clear_memory_attributes(range.clone(), clear).is_err() {
+        warn!("map clear attrs failed at {:#x}", range.start);
+    }
+    if !set.is_empty() && proto.set_memory_attributes(range.clone(), set).is_err() {
+        warn!("map set attrs failed at {:#x}", range.start);
+    }
+}
@@ -0,0 +1,267 @@
+use core::fmt::Write;
+use boot_config::{BootConfig, BootEntry};
+use uefi::proto::console::text::{Key, ScanCode};
+use uefi::runtime::{self, VariableAttributes, VariableVendor};
+use uefi::{boot, cstr16, Guid};
+use rtos_types::boot_slot::RTOS_VARIABLE_VENDOR;
+use rtos_types::crc32::crc32;
+use log::{info, warn};
+use crate::boot::console::{clear_screen, read_key, write_line};
+
+/// Countdown used when boot.cfg has entries but no `timeout`.
+const DEFAULT_TIMEOUT_SECS: u64 = 5;
+/// Key polling interval.
+const TICK_US: usize = 100_000;
+const TICKS_PER_SEC: u64 = 1_000_000 / TICK_US as u64;
+
+pub const CMDLINE_MAX: usize = 256;
+
+/// Vendor GUID for the bootloader's own NV variables.
+pub const RTOS_VENDOR: VariableVendor = VariableVendor(Guid::parse_or_panic(RTOS_VARIABLE_VENDOR));
+
+/// Fixed-size text buffer; writes past the end are truncated.
+pub struct TextBuf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> TextBuf<N> {
+    pub const fn new() -> Self {
+        TextBuf { buf: [0; N], len: 0 }
+    }
+
+    pub fn from_text(s: &str) -> Self {
+        let mut t = Self::new();
+        let _ = t.write_str(s);
+        t
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only ASCII or whole `&str` pieces are ever appended, but truncation
+        // may split a code point; keep the valid prefix.
+        match core::str::from_utf8(&self.buf[..self.len]) {
+            Ok(s) => s,
+            Err(e) => unsafe { core::str::from_utf8_unchecked(&self.buf[..e.valid_up_to()]) },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, b: u8) -> bool {
+        if self.len == N { return false; }
+        self.buf[self.len] = b;
+        self.len += 1;
+        true
+    }
+
+    fn pop(&mut self) {
+        self.len = self.len.saturating_sub(1);
+    }
+}
+
+impl<const N: usize> Write for TextBuf<N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for &b in s.as_bytes() {
+            if !self.push(b) { break; }
+        }
+        Ok(())
+    }
+}
+
+/// What to boot: the chosen entry, or the top-level boot.cfg settings when
+/// there is no menu. The command line is owned so it can be edited.
+pub struct Selection {
+    pub kernel: Option<&'static str>,
+    /// EFI application to chainload instead of a kernel (`efi` entries).
+    pub efi: Option<&'static str>,
+    /// boot.cfg `device`: where the kernel and initrd are read from.
+    pub device: Option<&'static str>,
+    /// Comma-separated initrd paths.
+    pub initrd: Option<&'static str>,
+    pub cmdline: TextBuf<CMDLINE_MAX>,
+    /// Set when the user edited the command line in the menu; it then takes
+    /// precedence over the image's LoadOptions.
+    pub edited: bool,
+}
+
+impl Selection {
+    /// An entry without its own `device` uses the top-level one.
+    fn from_entry(e: &BootEntry<'static>, cfg: &BootConfig<'static>) -> Self {
+        Selection {
+            kernel: Some(e.kernel),
+            efi: e.efi,
+            device: e.device.or(cfg.device),
+            initrd: e.initrd,
+            cmdline: TextBuf::from_text(e.cmdline.unwrap_or("")),
+            edited: false,
+        }
+    }
+}
+
+/// Picks what to boot. Without `[[entry]]` tables this is just the
+/// top-level config; otherwise the menu runs until a choice is made or the
+/// countdown, if `countdown` is set, expires.
+pub fn choose(cfg: &BootConfig<'static>, countdown: bool) -> Selection {
+    let entries = cfg.entries();
+    if entries.is_empty() {
+        return Selection {
+            kernel: cfg.kernel,
+            efi: None,
+            device: cfg.device,
+            initrd: cfg.initrd,
+            cmdline: TextBuf::from_text(cfg.cmdline.unwrap_or("")),
+            edited: false,
+        };
+    }
+
+    let last = load_last_booted();
+    let mut selected = last
+        .and_then(|id| entries.iter().position(|e| entry_id(e) == id))
+        .unwrap_or(0);
+    let mut cmdline_override: Option<TextBuf<CMDLINE_MAX>> = None;
+
+    let mut ticks_left = countdown.then(|| cfg.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS).saturating_mul(TICKS_PER_SEC));
+    draw(entries, selected, ticks_left);
+
+    loop {
+        match read_key() {
+            Some(Key::Special(ScanCode::UP)) => {
+                selected = if selected == 0 { entries.len() - 1 } else { selected - 1 };
+                cmdline_override = None;
+                ticks_left = None;
+                draw(entries, selected, ticks_left);
+            }
+            Some(Key::Special(ScanCode::DOWN)) => {
+                selected = (selected + 1) % entries.len();
+                cmdline_override = None;
+                ticks_left = None;
+                draw(entries, selected, ticks_left);
+            }
+            Some(Key::Printable(c)) if char::from(c) == '\r' => break,
+            Some(Key::Printable(c)) if char::from(c) == 'e' => {
+                ticks_left = None;
+                let start = cmdline_override.as_ref()
+                    .map(|b| b.as_str())
+                    .unwrap_or(entries[selected].cmdline.unwrap_or(""));
+                if let Some(edited) = edit_cmdline(start) {
+                    // Boot straight away with the edited line, like GRUB's Ctrl-X.
+                    cmdline_override = Some(edited);
+                    break;
+                }
+                draw(entries, selected, ticks_left);
+            }
+            Some(_) => {
+                // Any other key just stops the countdown.
+                if ticks_left.is_some() {
+                    ticks_left = None;
+                    draw(entries, selected, ticks_left);
+                }
+            }
+            None => {
+                if let Some(t) = ticks_left {
+                    if t == 0 { break; }
+                    ticks_left = Some(t - 1);
+                    if t % TICKS_PER_SEC == 0 { draw(entries, selected, ticks_left); }
+                }
+                boot::stall(TICK_US);
+            }
+        }
+    }
+
+    let entry = &entries[selected];
+    // Only kernels become the default: an unattended reboot should not land
+    // in a shell someone opened once.
+    if entry.efi.is_none() && last != Some(entry_id(entry)) {
+        save_last_booted(entry_id(entry));
+    }
+    let mut sel = Selection::from_entry(entry, cfg);
+    if let Some(c) = cmdline_override {
+        sel.cmdline = c;
+        sel.edited = true;
+    }
+
+    info!("booting entry '{}'", entry.label());
+    sel
+}
+
+fn draw(entries: &[BootEntry<'static>], selected: usize, ticks_left: Option<u64>) {
+    clear_screen();
+    write_line("RTOS boot menu   Up/Down: select   Enter: boot   e: edit command line");
+    write_line("");
+    for (i, e) in entries.iter().enumerate() {
+        let mut line: TextBuf<160> = TextBuf::new();
+        let mark = if i == selected { '>' } else { ' ' };
+        let _ = write!(line, " {} {}. {}", mark, i + 1, e.label());
+        write_line(line.as_str());
+
+        let mut detail: TextBuf<160> = TextBuf::new();
+        let _ = write!(detail, "        {}  {}", e.path(), e.cmdline.unwrap_or(""));
+        write_line(detail.as_str());
+    }
+    write_line("");
+    if let Some(t) = ticks_left {
+        let mut line: TextBuf<80> = TextBuf::new();
+        let _ = write!(line, "Booting selected entry in {} s (any key to stop)", t.div_ceil(TICKS_PER_SEC));
+        write_line(line.as_str());
+    }
+}
+
+/// Single-line editor: printable ASCII appends, Backspace deletes, Enter
+/// accepts, Esc cancels (returns `None`).
+fn edit_cmdline(initial: &str) -> Option<TextBuf<CMDLINE_MAX>> {
+    let mut buf = TextBuf::<CMDLINE_MAX>::from_text(initial);
+    let redraw = |b: &TextBuf<CMDLINE_MAX>| {
+        clear_screen();
+        write_line("Edit command line   Enter: boot   Esc: cancel");
+        write_line("");
+        let mut line: TextBuf<{ CMDLINE_MAX + 8 }> = TextBuf::new();
+        let _ = write!(line, "> {}_", b.as_str());
+        write_line(line.as_str());
+    };
+    redraw(&buf);
+
+    loop {
+        match read_key() {
+            Some(Key::Special(ScanCode::ESCAPE)) => return None,
+            Some(Key::Printable(c)) => {
+                let ch = char::from(c);
+                match ch {
+                    '\r' => return Some(buf),
+                    '\u{8}' => buf.pop(),
+                    ' '..='~' => { buf.push(ch as u8); }
+                    _ => continue,
+                }
+                redraw(&buf);
+            }
+            Some(_) => {}
+            None => boot::stall(TICK_US),
+        }
+    }
+}
+
+/// What `RtosLastBoot` stores for an entry: the CRC-32 of its label, which
+/// fits any title length and survives entries being reordered.
+fn entry_id(entry: &BootEntry) -> u32 {
+    crc32(entry.label().as_bytes())
+}
+
+/// Id of the entry booted last time, from the `RtosLastBoot` NV variable.
+fn load_last_booted() -> Option<u32> {
+    let mut buf = [0u8; 4];
+    let (data, _) = runtime::get_variable(cstr16!("RtosLastBoot"), &RTOS_VENDOR, &mut buf).ok()?;
+    Some(u32::from_le_bytes(data.try_into().ok()?))
+}
+
+/// Only called when the id changed, so an unchanged default costs no flash
+/// write.
+fn save_last_booted(id: u32) {
+    let bytes = id.to_le_bytes();
+    let attrs = VariableAttributes::NON_VOLATILE
+        | VariableAttributes::BOOTSERVICE_ACCESS
+        | VariableAttributes::RUNTIME_ACCESS;
+    if runtime::set_variable(cstr16!("RtosLastBoot"), &RTOS_VENDOR, attrs, &bytes).is_err() {
+        warn!("could not persist last booted entry");
+    }
+}
@@ -4,4 +4,19 @@ pub mod open;
 pub mod prepare;
 pub mod map;
 pub mod bootfs;
+pub mod chainload;
+pub mod cmdline;
+pub mod config;
+pub mod error;
+pub mod exit;
+pub mod guard;
+pub mod initrd;
+pub mod inventory;
+pub mod logger;
+pub mod menu;
+pub mod net;
+pub mod screen;
+pub mod slot;
+pub mod source;
+pub mod splash;
 pub mod trampoline;
\ No newline at end of file
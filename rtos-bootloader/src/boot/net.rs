@@ -0,0 +1,160 @@
+//! Network sources for boot files: TFTP through the PXE Base Code protocol
+//! (`device = tftp`), and HTTP through the firmware's HTTP Boot driver, which
+//! is asked for each URL with LoadFile and a URI node (`device = http://...`).
+//! The bootloader and boot.cfg still come from a local volume.
+
+use core::fmt::Write;
+use core::mem::MaybeUninit;
+use log::{debug, info};
+use uefi::boot::{self, OpenProtocolAttributes, ScopedProtocol, SearchType};
+use uefi::mem::memory_map::MemoryType;
+use uefi::proto::device_path::build::{messaging::Uri, DevicePathBuilder};
+use uefi::proto::device_path::DevicePathNodeEnum;
+use uefi::proto::media::load_file::LoadFile;
+use uefi::proto::network::pxe::BaseCode;
+use uefi::proto::network::IpAddress;
+use uefi::{CStr8, Handle, Identify, Status};
+use crate::boot::error::{BootError, OrFail, Stage};
+use crate::boot::guard::Pages;
+use crate::boot::menu::TextBuf;
+use crate::boot::open::{self, first_handle, params};
+use crate::boot::source::load_file;
+
+/// Longest URL or TFTP file name sent to the firmware.
+pub const MAX_NAME: usize = 256;
+
+/// A started PXE Base Code instance and the TFTP server to read from.
+pub struct Tftp {
+    pxe: ScopedProtocol<BaseCode>,
+    server: IpAddress,
+}
+
+impl Tftp {
+    /// Uses the NIC the bootloader was loaded from if it has PXE Base Code,
+    /// else the first one. Starts it and runs DHCP if the firmware hasn't;
+    /// without `server` the DHCP server address (`siaddr`) is used.
+    pub fn open(boot_device: Option<Handle>, server: Option<[u8; 4]>) -> Result<Tftp, BootError> {
+        let handle = boot_device
+            .filter(|&h| boot::test_protocol::<BaseCode>(params(h)).unwrap_or(false))
+            .or_else(|| first_handle(&BaseCode::GUID))
+            .or_fail(Stage::FileSystem, "no PXE Base Code protocol")?;
+        // GetProtocol: the PXE driver owns the NIC and may share it with an
+        // earlier network boot of this very image.
+        let mut pxe = unsafe { boot::open_protocol::<BaseCode>(params(handle), OpenProtocolAttributes::GetProtocol) }
+            .or_fail(Stage::FileSystem, "open PXE Base Code")?;
+
+        if !pxe.mode().started() {
+            pxe.start(false).or_fail(Stage::FileSystem, "start PXE Base Code")?;
+        }
+        if !pxe.mode().dhcp_ack_received() {
+            info!("DHCP...");
+            pxe.dhcp(false).or_fail(Stage::FileSystem, "DHCP")?;
+        }
+        let ip = pxe.mode().station_ip().0;
+        let server = match server {
+            Some(ip) => ip,
+            None => {
+                let ack: &uefi::proto::network::pxe::DhcpV4Packet = pxe.mode().dhcp_ack().as_ref();
+                ack.bootp_si_addr
+            }
+        };
+        if server == [0; 4] {
+            return Err(BootError::new(Stage::FileSystem, Status::NOT_FOUND, "no TFTP server (set device = tftp:ADDRESS)"));
+        }
+        info!(
+            "TFTP from {}.{}.{}.{} (this machine {}.{}.{}.{})",
+            server[0], server[1], server[2], server[3], ip[0], ip[1], ip[2], ip[3],
+        );
+        Ok(Tftp { pxe, server: IpAddress::new_v4(server) })
+    }
+
+    /// Reads `path` (config separators, leading `\` or `/` dropped) into
+    /// fresh pages of type `ty`; returns them and the file size. A transfer
+    /// shorter than the size the server announced fails.
+    pub fn read(&mut self, path: &str, ty: MemoryType) -> uefi::Result<(Pages, usize)> {
+        let mut buf = [0u8; MAX_NAME];
+        let n = remote_name(path, &mut buf).ok_or(Status::INVALID_PARAMETER)?;
+        let name = CStr8::from_bytes_with_nul(&buf[..=n]).map_err(|_| Status::INVALID_PARAMETER)?;
+
+        let size = self.pxe.tftp_get_file_size(&self.server, name)? as usize;
+        let mut pages = Pages::for_bytes(ty, size)?;
+        let got = self.pxe.tftp_read_file(&self.server, name, Some(&mut pages.as_mut_slice()[..size]))? as usize;
+        debug!("tftp {} ({} of {} bytes)", path, got, size);
+        if got != size {
+            return Err(Status::END_OF_FILE.into());
+        }
+        Ok((pages, size))
+    }
+}
+
+/// The HTTP Boot LoadFile instance and the base URL paths are appended to.
+pub struct Http {
+    handle: Handle,
+    base: &'static str,
+}
+
+impl Http {
+    /// Finds the HTTP Boot driver: a LoadFile whose device path ends in a
+    /// URI node, preferring the handle the bootloader was loaded from.
+    pub fn open(boot_device: Option<Handle>, base: &'static str) -> Result<Http, BootError> {
+        let handles = boot::locate_handle_buffer(SearchType::ByProtocol(&LoadFile::GUID))
+            .or_fail(Stage::FileSystem, "no Load File protocol")?;
+        let handle = boot_device
+            .into_iter()
+            .chain(handles.iter().copied())
+            .find(|&h| handles.contains(&h) && has_uri_node(h))
+            .or_fail(Stage::FileSystem, "no HTTP Boot driver (enable HTTP boot in the firmware)")?;
+        info!("HTTP from {}", base);
+        Ok(Http { handle, base })
+    }
+
+    /// Fetches `base` + `path` into fresh pages of type `ty`.
+    pub fn read(&self, path: &str, ty: MemoryType) -> uefi::Result<(Pages, usize)> {
+        let mut buf = [0u8; MAX_NAME];
+        let n = remote_name(path, &mut buf).ok_or(Status::INVALID_PARAMETER)?;
+        let mut url: TextBuf<{ 2 * MAX_NAME }> = TextBuf::new();
+        let _ = url.write_str(self.base.trim_end_matches('/'));
+        let _ = url.write_str("/");
+        let _ = url.write_str(core::str::from_utf8(&buf[..n]).map_err(|_| Status::INVALID_PARAMETER)?);
+
+        let mut dp = [MaybeUninit::<u8>::uninit(); 3 * MAX_NAME];
+        let file_path = DevicePathBuilder::with_buf(&mut dp)
+            .push(&Uri { value: url.as_str().as_bytes() })
+            .and_then(|b| b.finalize())
+            .map_err(|_| Status::INVALID_PARAMETER)?;
+        // HTTP Boot only answers with BootPolicy TRUE.
+        let r = load_file(self.handle, file_path, true, ty)?;
+        debug!("http {} ({} bytes)", url.as_str(), r.1);
+        Ok(r)
+    }
+}
+
+/// Parses `a.b.c.d`.
+pub fn parse_ipv4(s: &str) -> Option<[u8; 4]> {
+    let mut ip = [0u8; 4];
+    let mut parts = s.split('.');
+    for b in ip.iter_mut() {
+        *b = parts.next()?.parse().ok()?;
+    }
+    if parts.next().is_some() { return None; }
+    Some(ip)
+}
+
+/// Config path as a server-relative name: `/` separators, no leading
+/// separator, ASCII only, NUL-terminated in `buf`. Returns the length.
+fn remote_name(path: &str, buf: &mut [u8; MAX_NAME]) -> Option<usize> {
+    let mut n = 0;
+    for b in path.trim_start_matches(['\\', '/']).bytes() {
+        if !b.is_ascii() || b == 0 || n + 1 >= MAX_NAME { return None; }
+        buf[n] = if b == b'\\' { b'/' } else { b };
+        n += 1;
+    }
+    buf[n] = 0;
+    Some(n)
+}
+
+fn has_uri_node(handle: Handle) -> bool {
+    let Ok(dp) = open::open_device_path(handle) else { return false };
+    let found = dp.node_iter().any(|node| matches!(node.as_enum(), Ok(DevicePathNodeEnum::MessagingUri(_))));
+    found
+}
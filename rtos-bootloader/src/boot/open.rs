@@ -1,6 +1,5 @@
 use uefi::prelude::*;
 use uefi::boot;
-use crate::boot::console::write_line;
 
 // NOTE: ScopedProtocol lives in `uefi::boot` (not `uefi::scoped_protocol`)
 pub fn open_loaded_image(
@@ -8,7 +7,7 @@ pub fn open_loaded_image(
 ) -> Result<uefi::boot::ScopedProtocol<uefi::proto::loaded_image::LoadedImage>, Status> {
     match boot::open_protocol_exclusive::<uefi::proto::loaded_image::LoadedImage>(image) {
         Ok(p) => Ok(p),
-        Err(e) => { write_line("BL: ERROR LoadedImage"); Err(e.status()) }
+        Err(e) => Err(e.status()),
     }
 }
 
@@ -17,11 +16,11 @@ pub fn open_simple_fs(
 ) -> Result<uefi::boot::ScopedProtocol<uefi::proto::media::fs::SimpleFileSystem>, Status> {
     let device = match loaded.device() {
         Some(h) => h,
-        None => { write_line("BL: ERROR LoadedImage.device() is None"); return Err(Status::LOAD_ERROR); }
+        None => return Err(Status::LOAD_ERROR),
     };
     match boot::open_protocol_exclusive::<uefi::proto::media::fs::SimpleFileSystem>(device) {
         Ok(p) => Ok(p),
-        Err(e) => { write_line("BL: ERROR SimpleFileSystem"); Err(e.status()) }
+        Err(e) => Err(e.status()),
     }
 }
 
@@ -30,3 +29,23 @@ pub fn open_root_dir(
 ) -> Result<uefi::proto::media::file::Directory, Status> {
     sfs.open_volume().map_err(|e| e.status())
 }
+
+/// Device path of `handle`. Opened with GetProtocol: the device's own
+/// drivers keep the protocol open.
+pub fn open_device_path(
+    handle: Handle,
+) -> Result<uefi::boot::ScopedProtocol<uefi::proto::device_path::DevicePath>, Status> {
+    match unsafe { boot::open_protocol::<uefi::proto::device_path::DevicePath>(params(handle), boot::OpenProtocolAttributes::GetProtocol) } {
+        Ok(p) => Ok(p),
+        Err(e) => Err(e.status()),
+    }
+}
+
+pub fn first_handle(guid: &uefi::Guid) -> Option<Handle> {
+    boot::locate_handle_buffer(boot::SearchType::ByProtocol(guid)).ok()?.first().copied()
+}
+
+/// Parameters for opening a protocol on `handle` on behalf of this image.
+pub fn params(handle: Handle) -> boot::OpenProtocolParams {
+    boot::OpenProtocolParams { handle, agent: boot::image_handle(), controller: None }
+}
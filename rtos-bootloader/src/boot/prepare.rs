@@ -1,28 +1,31 @@
-use core::ptr;
-use uefi::boot::{self, AllocateType};
+use core::{mem, ptr};
 use uefi::mem::memory_map::MemoryType;
-use uefi::Status;
-use crate::boot::console::write_hex;
+use rtos_types::boot_info::BootInfo;
+use log::debug;
+use crate::boot::guard::Pages;
 
-pub fn prepare_stack_and_info(page_size: usize) -> Result<(usize, usize), Status> {
-    let stack_pages = 8usize;
-    let stack_bytes = stack_pages * page_size;
-    let stack_ptr = match boot::allocate_pages(AllocateType::AnyPages, MemoryType::LOADER_DATA, stack_pages) {
-        Ok(p) => p,
-        Err(e) => return Err(e.status()),
-    };
-    let stack_top = stack_ptr.as_ptr() as usize + stack_bytes;
+pub const DEFAULT_STACK_PAGES: usize = 8;
 
-    let boot_info_pages = 1usize;
-    let boot_info_ptr = match boot::allocate_pages(AllocateType::AnyPages, MemoryType::LOADER_DATA, boot_info_pages) {
-        Ok(p) => p,
-        Err(e) => return Err(e.status()),
-    };
-    let boot_info_base = boot_info_ptr.as_ptr() as usize;
-    unsafe { ptr::write_bytes(boot_info_base as *mut u8, 0, boot_info_pages * page_size); }
+/// Allocates the kernel stack and a zeroed BootInfo page. Both are freed
+/// again if the boot fails before they are `leak`ed at handoff.
+pub fn prepare_stack_and_info(page_size: usize, stack_pages: usize) -> uefi::Result<(Pages, Pages)> {
+    let stack = Pages::for_bytes(MemoryType::LOADER_DATA, stack_pages * page_size)?;
+    let mut boot_info = Pages::allocate(MemoryType::LOADER_DATA, 1)?;
+    boot_info.as_mut_slice().fill(0);
 
-    write_hex("BL: stack_top", stack_top as u64);
-    write_hex("BL: boot_info", boot_info_base as u64);
+    debug!("stack_top {:#x}", stack.end());
+    debug!("boot_info {:#x}", boot_info.addr());
 
-    Ok((stack_top, boot_info_base))
+    Ok((stack, boot_info))
+}
+
+/// Copies the command line into the BootInfo page, just past the `BootInfo`
+/// struct, and returns its (address, length). Lines that don't fit are cut.
+pub fn write_cmdline(boot_info: &mut Pages, cmdline: &str) -> (u64, u64) {
+    if cmdline.is_empty() { return (0, 0); }
+    let off = (mem::size_of::<BootInfo>() + 7) & !7;
+    let len = cmdline.len().min(boot_info.size() - off);
+    let dst = boot_info.addr() + off;
+    unsafe { ptr::copy_nonoverlapping(cmdline.as_ptr(), dst as *mut u8, len); }
+    (dst as u64, len as u64)
 }
@@ -0,0 +1,115 @@
+use core::fmt::Write;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use log::error;
+use uefi::proto::console::text::Key;
+use uefi::runtime::{self, ResetType};
+use uefi::{boot, Status};
+use rtos_types::boot_info::BootInfo;
+use rtos_framebuffer::pixel::{write_pixel, Channels};
+use crate::boot::console::{clear_screen, read_key, write_line};
+use crate::boot::error::{BootError, Stage};
+use crate::boot::menu::TextBuf;
+
+/// BootInfo address once boot services are gone; 0 while they're still up.
+static EXITED_BOOT_INFO: AtomicUsize = AtomicUsize::new(0);
+
+const POLL_US: usize = 50_000;
+
+/// Records that boot services have been exited; from here on failures can
+/// only be reported over serial and the framebuffer in `boot_info`.
+pub fn mark_exited(boot_info: usize) {
+    EXITED_BOOT_INFO.store(boot_info, Ordering::Release);
+}
+
+/// Shows the error screen, waits for a key, then either reboots or returns
+/// the status to hand back to the firmware.
+pub fn report(err: &BootError) -> Status {
+    draw(err);
+    choose(err.status)
+}
+
+/// Last-resort report for failures after `exit_boot_services`: details go to
+/// the log (COM1 and the boot log ring) and the framebuffer is painted red,
+/// then the CPU halts.
+pub fn halt_after_exit(err: &BootError) -> ! {
+    error!("FATAL after ExitBootServices: {}", err);
+
+    let bi = EXITED_BOOT_INFO.load(Ordering::Acquire);
+    if bi != 0 {
+        // SAFETY: `mark_exited` was given the BootInfo page, which is never freed.
+        let fb = unsafe { (*(bi as *const BootInfo)).framebuffer };
+        // Blt-only screens can't be reached without boot services.
+        let red = Channels::of(&fb).map(|c| c.pack(0xc0, 0, 0));
+        if let (Some(color), true) = (red, fb.is_linear()) {
+            let bpp = fb.bytes_per_pixel();
+            for y in 0..fb.height as usize {
+                let row = (fb.base as usize + y * fb.stride as usize * bpp) as *mut u8;
+                for x in 0..fb.width as usize {
+                    unsafe { write_pixel(row.add(x * bpp), bpp, color) };
+                }
+            }
+        }
+    }
+
+    loop {
+        x86_64::instructions::interrupts::disable();
+        x86_64::instructions::hlt();
+    }
+}
+
+/// Panic path: the error screen while boot services are up, otherwise
+/// `halt_after_exit`. "Return to firmware" exits the image.
+pub fn panic(info: &core::panic::PanicInfo) -> ! {
+    error!("panic: {}", info.message());
+    if EXITED_BOOT_INFO.load(Ordering::Acquire) != 0 {
+        halt_after_exit(&BootError::new(Stage::Handoff, Status::ABORTED, "panic"));
+    }
+
+    let err = BootError::new(Stage::Internal, Status::ABORTED, "panic");
+    draw(&err);
+    let mut line: TextBuf<160> = TextBuf::new();
+    let _ = write!(line, "  message: {}", info.message());
+    write_line(line.as_str());
+    if let Some(loc) = info.location() {
+        let mut line: TextBuf<160> = TextBuf::new();
+        let _ = write!(line, "  at:      {}:{}", loc.file(), loc.line());
+        write_line(line.as_str());
+    }
+    let status = choose(err.status);
+    unsafe { boot::exit(boot::image_handle(), status, 0, core::ptr::null_mut()) }
+}
+
+fn draw(err: &BootError) {
+    clear_screen();
+    write_line("RTOS boot failed");
+    write_line("");
+    let mut line: TextBuf<160> = TextBuf::new();
+    let _ = write!(line, "  stage:   {}", err.stage.as_str());
+    write_line(line.as_str());
+    let mut line: TextBuf<160> = TextBuf::new();
+    let _ = write!(line, "  status:  {:?}", err.status);
+    write_line(line.as_str());
+    let mut line: TextBuf<160> = TextBuf::new();
+    let _ = write!(line, "  detail:  {}", err.context);
+    write_line(line.as_str());
+}
+
+fn choose(status: Status) -> Status {
+    write_line("");
+    write_line("Press any key to continue");
+    wait_key();
+    write_line("R: reboot   any other key: return to firmware");
+    match wait_key() {
+        Key::Printable(c) if matches!(char::from(c), 'r' | 'R') => {
+            runtime::reset(ResetType::COLD, Status::SUCCESS, None)
+        }
+        _ => status,
+    }
+}
+
+fn wait_key() -> Key {
+    loop {
+        if let Some(k) = read_key() { return k; }
+        boot::stall(POLL_US);
+    }
+}
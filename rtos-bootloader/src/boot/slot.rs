@@ -0,0 +1,92 @@
+//! A/B kernel slots (boot.cfg `slot_a` and `slot_b`). The `RtosBootSlot`
+//! variable records the active slot and whether a kernel from it has come
+//! up. Until one has, every boot of the slot uses up one of `boot_tries`
+//! tries, and with none left the bootloader switches to the other slot.
+//! The try is taken before the kernel is read, so an image that is missing
+//! or fails verification counts as a failed boot as well.
+
+use boot_config::BootConfig;
+use log::{info, warn};
+use uefi::runtime::{self, VariableAttributes};
+use uefi::CStr16;
+use rtos_types::boot_slot::{
+    other_slot, slot_name, BootSlotState, BOOT_SLOT_A, BOOT_SLOT_NONE, BOOT_SLOT_VARIABLE,
+};
+use crate::boot::menu::{Selection, RTOS_VENDOR};
+
+/// Tries per slot when boot.cfg has no `boot_tries`.
+pub const DEFAULT_TRIES: u8 = 3;
+
+/// The slot this boot uses, for `BootInfo`.
+pub struct Slot {
+    pub slot: u32,
+    pub tries_left: u32,
+}
+
+impl Slot {
+    pub const NONE: Slot = Slot { slot: BOOT_SLOT_NONE, tries_left: 0 };
+}
+
+/// With both slots configured and no menu, points `selection` at the kernel
+/// of the slot to boot and charges it one try. Otherwise leaves `selection`
+/// alone and returns `Slot::NONE`.
+pub fn select(cfg: &BootConfig<'static>, selection: &mut Selection) -> Slot {
+    let (a, b) = match (cfg.slot_a, cfg.slot_b) {
+        (None, None) => return Slot::NONE,
+        (Some(a), Some(b)) => (a, b),
+        _ => {
+            warn!("boot.cfg sets only one of slot_a and slot_b; slots ignored");
+            return Slot::NONE;
+        }
+    };
+    if !cfg.entries().is_empty() {
+        warn!("slot_a and slot_b are ignored when boot.cfg has [[entry]] tables");
+        return Slot::NONE;
+    }
+
+    let tries = cfg.boot_tries.map_or(DEFAULT_TRIES, |n| n as u8);
+    let mut state = load().unwrap_or(BootSlotState::fresh(BOOT_SLOT_A, BootSlotState::TRIES_UNSET));
+    if !state.is_good() {
+        if state.tries_left == BootSlotState::TRIES_UNSET {
+            state.tries_left = tries;
+        }
+        if state.tries_left == 0 {
+            let failed = state.active as u32;
+            state = BootSlotState::fresh(other_slot(failed), tries);
+            warn!("slot {} never came up; rolling back to slot {}", slot_name(failed), slot_name(state.active as u32));
+        }
+        state.tries_left -= 1;
+        save(&state);
+    }
+
+    let slot = state.active as u32;
+    let kernel = if slot == BOOT_SLOT_A { a } else { b };
+    selection.kernel = Some(kernel);
+    if state.is_good() {
+        info!("slot {}: {}", slot_name(slot), kernel);
+    } else {
+        info!("slot {}: {} (unconfirmed, {} tries left)", slot_name(slot), kernel, state.tries_left);
+    }
+    Slot { slot, tries_left: state.tries_left as u32 }
+}
+
+/// The stored state; `None` if the variable is missing or malformed.
+fn load() -> Option<BootSlotState> {
+    let mut name = [0u16; 16];
+    let name = CStr16::from_str_with_buf(BOOT_SLOT_VARIABLE, &mut name).ok()?;
+    let mut buf = [0u8; BootSlotState::SIZE];
+    let (data, _) = runtime::get_variable(name, &RTOS_VENDOR, &mut buf).ok()?;
+    BootSlotState::from_bytes(data)
+}
+
+/// Runtime access so the kernel can set `good` after ExitBootServices.
+fn save(state: &BootSlotState) {
+    let mut name = [0u16; 16];
+    let Ok(name) = CStr16::from_str_with_buf(BOOT_SLOT_VARIABLE, &mut name) else { return };
+    let attrs = VariableAttributes::NON_VOLATILE
+        | VariableAttributes::BOOTSERVICE_ACCESS
+        | VariableAttributes::RUNTIME_ACCESS;
+    if runtime::set_variable(name, &RTOS_VENDOR, attrs, &state.to_bytes()).is_err() {
+        warn!("could not save the boot slot state; this boot is not counted");
+    }
+}
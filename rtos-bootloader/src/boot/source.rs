@@ -0,0 +1,272 @@
+//! Where the kernel and initrd are read from (boot.cfg `device`): the
+//! bootloader's own volume, the first volume holding the kernel, a GPT
+//! partition, a device path, a Load File protocol instance, or the network
+//! (`net.rs`).
+
+use core::ffi::c_void;
+use core::mem::MaybeUninit;
+use log::{debug, info};
+use uefi::boot::{self, OpenProtocolAttributes, ScopedProtocol, SearchType};
+use uefi::mem::memory_map::MemoryType;
+use uefi::proto::device_path::build::{media::FilePath, DevicePathBuilder};
+use uefi::proto::device_path::text::{AllowShortcuts, DevicePathToText, DisplayOnly};
+use uefi::proto::device_path::{DevicePath, DevicePathNodeEnum};
+use uefi::proto::device_path::media::PartitionSignature;
+use uefi::proto::media::file::Directory;
+use uefi::proto::media::fs::SimpleFileSystem;
+use uefi::proto::media::load_file::{LoadFile, LoadFile2};
+use uefi::proto::Protocol;
+use uefi::{Guid, Handle, Identify, Status, StatusExt};
+use uefi_raw::protocol::device_path::DevicePathProtocol;
+use uefi_raw::protocol::media::{LoadFile2Protocol, LoadFileProtocol};
+use crate::boot::bootfs;
+use crate::boot::error::{BootError, OrFail, Stage};
+use crate::boot::guard::Pages;
+use crate::boot::net::{self, Http, Tftp};
+use crate::boot::open::{self, first_handle, params};
+
+/// Kernel paths tried when boot.cfg names none.
+const DEFAULT_KERNELS: [&str; 2] = [r"\EFI\BOOT\KERNEL.RTOSK", r"\KERNEL.RTOSK"];
+
+/// Parsed boot.cfg `device` value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceSpec<'a> {
+    /// `boot`: the volume the bootloader was loaded from (default).
+    Boot,
+    /// `any`: the boot volume, then every other SimpleFileSystem in handle
+    /// order; the first one holding the kernel wins.
+    Any,
+    /// `partuuid:GUID`: the volume on the GPT partition with that unique GUID.
+    PartUuid(Guid),
+    /// `path:TEXT`: the first volume whose device path, as text, starts with
+    /// TEXT (case-insensitive), e.g. `path:PciRoot(0x0)/Pci(0x1F,0x2)/Sata(0x1,0xFFFF,0x0)`.
+    Path(&'a str),
+    /// `loadfile`: files are fetched through LoadFile2, else LoadFile, with
+    /// the path as a file path node.
+    LoadFile,
+    /// `tftp` or `tftp:ADDRESS`: TFTP through PXE Base Code, from the DHCP
+    /// server unless an IPv4 address is given.
+    Tftp(Option<[u8; 4]>),
+    /// `http://...` or `https://...`: base URL the paths are appended to,
+    /// fetched by the firmware's HTTP Boot driver.
+    Http(&'a str),
+}
+
+impl<'a> DeviceSpec<'a> {
+    pub fn parse(s: &'a str) -> Option<Self> {
+        let s = s.trim();
+        let spec = match s {
+            "boot" => DeviceSpec::Boot,
+            "any" => DeviceSpec::Any,
+            "loadfile" => DeviceSpec::LoadFile,
+            "tftp" => DeviceSpec::Tftp(None),
+            _ if s.starts_with("http://") || s.starts_with("https://") => DeviceSpec::Http(s),
+            _ => {
+                if let Some(ip) = s.strip_prefix("tftp:") {
+                    DeviceSpec::Tftp(Some(net::parse_ipv4(ip.trim())?))
+                } else if let Some(g) = s.strip_prefix("partuuid:") {
+                    DeviceSpec::PartUuid(Guid::try_parse(g.trim()).ok()?)
+                } else {
+                    let p = s.strip_prefix("path:")?.trim();
+                    if p.is_empty() { return None; }
+                    DeviceSpec::Path(p)
+                }
+            }
+        };
+        Some(spec)
+    }
+}
+
+/// An opened source of boot files.
+pub enum Source {
+    Volume {
+        root: Directory,
+        /// Kept open for the life of `root`; `None` for the boot volume,
+        /// whose protocol `load` holds.
+        _fs: Option<ScopedProtocol<SimpleFileSystem>>,
+    },
+    LoadFile(Handle),
+    Tftp(Tftp),
+    Http(Http),
+}
+
+impl Source {
+    /// Picks the source for `spec`. `boot_root` is the bootloader's own
+    /// volume; `kernel` is the configured kernel path, used by `any` to find
+    /// the right volume.
+    pub fn open(
+        spec: DeviceSpec<'static>,
+        boot_device: Option<Handle>,
+        mut boot_root: Directory,
+        kernel: Option<&str>,
+    ) -> Result<Source, BootError> {
+        let found = match spec {
+            DeviceSpec::Boot => return Ok(Source::Volume { _fs: None, root: boot_root }),
+            DeviceSpec::LoadFile => {
+                let handle = first_handle(&LoadFile2::GUID)
+                    .or_else(|| first_handle(&LoadFile::GUID))
+                    .or_fail(Stage::FileSystem, "no Load File protocol")?;
+                info!("reading boot files through Load File");
+                return Ok(Source::LoadFile(handle));
+            }
+            DeviceSpec::Tftp(server) => return Ok(Source::Tftp(Tftp::open(boot_device, server)?)),
+            DeviceSpec::Http(base) => return Ok(Source::Http(Http::open(boot_device, base)?)),
+            DeviceSpec::Any => {
+                if has_kernel(&mut boot_root, kernel) {
+                    return Ok(Source::Volume { _fs: None, root: boot_root });
+                }
+                find_volume(|h, root| Some(h) != boot_device && has_kernel(root, kernel))
+            }
+            DeviceSpec::PartUuid(guid) => find_volume(|h, _| partition_guid(h) == Some(guid)),
+            DeviceSpec::Path(text) => find_volume(|h, _| path_matches(h, text)),
+        };
+        let (handle, fs, root) = found.or_fail(Stage::FileSystem, "no volume matches boot.cfg device")?;
+        info!("boot files from volume {:?}", handle);
+        Ok(Source::Volume { _fs: Some(fs), root })
+    }
+
+    /// Reads the kernel (the configured path, else the default locations)
+    /// into temporary pages; returns them and the file size.
+    pub fn read_kernel(&mut self, path: Option<&str>) -> uefi::Result<(Pages, usize)> {
+        if let Some(p) = path {
+            return self.read(p, MemoryType::LOADER_DATA);
+        }
+        let mut last = Status::NOT_FOUND.into();
+        for p in DEFAULT_KERNELS {
+            match self.read(p, MemoryType::LOADER_DATA) {
+                Ok(r) => return Ok(r),
+                Err(e) => last = e,
+            }
+        }
+        Err(last)
+    }
+
+    /// Reads `path` into fresh pages of type `ty`; returns them and the file size.
+    pub fn read(&mut self, path: &str, ty: MemoryType) -> uefi::Result<(Pages, usize)> {
+        match self {
+            Source::Volume { root, .. } => {
+                let mut file = bootfs::open_path(root, path)?;
+                let size = bootfs::file_size(&mut file).ok_or(Status::DEVICE_ERROR)?;
+                let mut pages = Pages::for_bytes(ty, size)?;
+                bootfs::read_exact(&mut file, &mut pages.as_mut_slice()[..size])?;
+                Ok((pages, size))
+            }
+            Source::LoadFile(handle) => load_file_path(*handle, path, ty),
+            Source::Tftp(tftp) => tftp.read(path, ty),
+            Source::Http(http) => http.read(path, ty),
+        }
+    }
+
+    /// True for TFTP and HTTP, whose images get an extra CRC check.
+    pub fn is_network(&self) -> bool {
+        matches!(self, Source::Tftp(_) | Source::Http(_))
+    }
+}
+
+/// Opens every SimpleFileSystem in handle order until `matches` accepts one.
+fn find_volume(
+    mut matches: impl FnMut(Handle, &mut Directory) -> bool,
+) -> Option<(Handle, ScopedProtocol<SimpleFileSystem>, Directory)> {
+    let handles = boot::locate_handle_buffer(SearchType::ByProtocol(&SimpleFileSystem::GUID)).ok()?;
+    for &h in handles.iter() {
+        // GetProtocol: the boot volume is already held exclusively by `load`.
+        let Ok(mut fs) = (unsafe { boot::open_protocol::<SimpleFileSystem>(params(h), OpenProtocolAttributes::GetProtocol) }) else {
+            continue;
+        };
+        let Ok(mut root) = fs.open_volume() else { continue };
+        if matches(h, &mut root) {
+            return Some((h, fs, root));
+        }
+        debug!("volume {:?} skipped", h);
+    }
+    None
+}
+
+fn has_kernel(root: &mut Directory, kernel: Option<&str>) -> bool {
+    match kernel {
+        Some(p) => bootfs::open_path(root, p).is_ok(),
+        None => DEFAULT_KERNELS.iter().any(|p| bootfs::open_path(root, p).is_ok()),
+    }
+}
+
+/// Unique partition GUID from the HardDrive node of a GPT volume's device path.
+fn partition_guid(handle: Handle) -> Option<Guid> {
+    let dp = open::open_device_path(handle).ok()?;
+    dp.node_iter().find_map(|node| match node.as_enum() {
+        Ok(DevicePathNodeEnum::MediaHardDrive(hd)) => match hd.partition_signature() {
+            PartitionSignature::Guid(g) => Some(g),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// True when `handle`'s device path, as text, starts with `text`.
+fn path_matches(handle: Handle, text: &str) -> bool {
+    let Some(conv) = first_handle(&DevicePathToText::GUID) else { return false };
+    let Ok(conv) = (unsafe { boot::open_protocol::<DevicePathToText>(params(conv), OpenProtocolAttributes::GetProtocol) }) else {
+        return false;
+    };
+    let Ok(dp) = open::open_device_path(handle) else { return false };
+    let Ok(s) = conv.convert_device_path_to_text(&dp, DisplayOnly(false), AllowShortcuts(false)) else {
+        return false;
+    };
+    debug!("volume {:?}: {}", handle, &*s);
+    let mut have = s.iter().map(|&c| char::from(c).to_ascii_lowercase());
+    text.chars().all(|want| have.next() == Some(want.to_ascii_lowercase()))
+}
+
+/// `path` through LoadFile2 / LoadFile, as a file path node.
+fn load_file_path(handle: Handle, path: &str, ty: MemoryType) -> uefi::Result<(Pages, usize)> {
+    let mut name = [0u16; 256];
+    let name = bootfs::ucs2_path(path, &mut name)?;
+    let mut buf = [MaybeUninit::<u8>::uninit(); 600];
+    let file_path: &DevicePath = DevicePathBuilder::with_buf(&mut buf)
+        .push(&FilePath { path_name: name })
+        .and_then(|b| b.finalize())
+        .map_err(|_| Status::INVALID_PARAMETER)?;
+    // BootPolicy FALSE: `path` names a file, not a boot option.
+    let r = load_file(handle, file_path, false, ty)?;
+    debug!("loadfile {} ({} bytes)", path, r.1);
+    Ok(r)
+}
+
+/// LoadFile2 (only without `boot_policy`, which it doesn't support), else
+/// LoadFile: ask for the size with an empty buffer, then read.
+pub fn load_file(handle: Handle, file_path: &DevicePath, boot_policy: bool, ty: MemoryType) -> uefi::Result<(Pages, usize)> {
+    let fp = file_path.as_ffi_ptr() as *const DevicePathProtocol;
+
+    let call = |size: &mut usize, dst: *mut c_void| -> Status {
+        // Both protocols share a layout; LoadFile2 is tried first, as
+        // LoadImage does for non-boot-option paths.
+        if !boot_policy {
+            if let Ok(mut p) = unsafe { boot::open_protocol::<LoadFile2>(params(handle), OpenProtocolAttributes::GetProtocol) } {
+                let raw = raw_protocol::<LoadFile2, LoadFile2Protocol>(&mut p);
+                return unsafe { ((*raw).load_file)(raw, fp, false.into(), size, dst) };
+            }
+        }
+        match unsafe { boot::open_protocol::<LoadFile>(params(handle), OpenProtocolAttributes::GetProtocol) } {
+            Ok(mut p) => {
+                let raw = raw_protocol::<LoadFile, LoadFileProtocol>(&mut p);
+                unsafe { ((*raw).load_file)(raw, fp, boot_policy.into(), size, dst) }
+            }
+            Err(e) => e.status(),
+        }
+    };
+
+    let mut size = 0usize;
+    match call(&mut size, core::ptr::null_mut()) {
+        Status::BUFFER_TOO_SMALL => {}
+        Status::SUCCESS => return Err(Status::END_OF_FILE.into()),
+        s => return Err(s.into()),
+    }
+    let pages = Pages::for_bytes(ty, size)?;
+    let mut got = size;
+    call(&mut got, pages.addr() as *mut c_void).to_result()?;
+    Ok((pages, got.min(size)))
+}
+
+/// The uefi wrappers are `repr(transparent)` over the raw tables.
+fn raw_protocol<P: Protocol, R>(p: &mut ScopedProtocol<P>) -> *mut R {
+    &mut **p as *mut P as *mut R
+}
@@ -0,0 +1,232 @@
+//! Boot splash drawn straight into the GOP framebuffer while the kernel is
+//! loaded: background, the logo scaled to fit, a progress bar and a status
+//! line. The logo is decoded from `images/rtos-logo-transparent.png` by
+//! build.rs and blended with `Channels::blend_over`. Blt-only
+//! modes are drawn into a `ShadowBuffer` and pushed to the screen with Blt.
+
+use boot_config::SplashMode;
+use rtos_framebuffer::font::{glyph_5x7, GLYPH_H, GLYPH_W};
+use rtos_framebuffer::framebuffer::shadow::ShadowBuffer;
+use rtos_framebuffer::pixel::{write_pixel, Channels};
+use rtos_types::framebuffer_info::FramebufferInfo;
+
+include!(concat!(env!("OUT_DIR"), "/logo.rs"));
+
+const BACKGROUND: (u8, u8, u8) = (0x00, 0x2D, 0x61);
+const BAR_TRACK: (u8, u8, u8) = (0x10, 0x45, 0x80);
+const BAR_FILL: (u8, u8, u8) = (0xE0, 0xE8, 0xF0);
+const TEXT: (u8, u8, u8) = (0xC8, 0xD4, 0xE4);
+
+/// Longest status line drawn; longer text is cut off.
+const MAX_STATUS_CHARS: usize = 64;
+
+#[derive(Clone, Copy)]
+struct Rect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+/// A drawn splash screen. Inactive (every call a no-op) when the splash is
+/// turned off or there is no screen to draw on.
+pub struct Splash {
+    /// Where pixels are written: the GOP framebuffer, or `shadow`.
+    fb: FramebufferInfo,
+    channels: Channels,
+    shadow: Option<ShadowBuffer>,
+    active: bool,
+    bar: Rect,
+    text: Rect,
+    text_scale: u32,
+}
+
+impl Splash {
+    /// Clears the screen and draws the logo with an empty progress bar.
+    pub fn show(fb: &FramebufferInfo, mode: SplashMode) -> Self {
+        let mut s = Splash {
+            fb: *fb,
+            channels: Channels::of(fb).unwrap_or(Channels::BGR),
+            shadow: None,
+            active: false,
+            bar: Rect { x: 0, y: 0, w: 0, h: 0 },
+            text: Rect { x: 0, y: 0, w: 0, h: 0 },
+            text_scale: 1,
+        };
+        if mode == SplashMode::Off || fb.width < 64 || fb.height < 64 || LOGO_WIDTH == 0 || LOGO_HEIGHT == 0 {
+            return s;
+        }
+        if !fb.is_linear() {
+            match ShadowBuffer::new(fb.width, fb.height) {
+                Ok(shadow) => {
+                    s.fb = shadow.info();
+                    s.channels = Channels::BGR;
+                    s.shadow = Some(shadow);
+                }
+                Err(_) => return s,
+            }
+        }
+        s.active = true;
+
+        // Logo fits in the screen width and 3/5 of its height, never upscaled.
+        let (w, h) = (fb.width as u64, fb.height as u64);
+        let scale = ((w << 16) / LOGO_WIDTH as u64)
+            .min(((h * 3 / 5) << 16) / LOGO_HEIGHT as u64)
+            .min(1 << 16);
+        let logo_w = ((LOGO_WIDTH as u64 * scale) >> 16).max(1) as u32;
+        let logo_h = ((LOGO_HEIGHT as u64 * scale) >> 16).max(1) as u32;
+
+        // Logo, bar and status line stacked and centered as one block.
+        s.text_scale = (fb.height / 360).max(1);
+        let gap = 4 * s.text_scale;
+        let bar_w = fb.width / 3;
+        let bar_h = (fb.height / 120).max(4);
+        let text_h = GLYPH_H * s.text_scale;
+        let block_h = logo_h + gap * 2 + bar_h + gap * 2 + text_h;
+        let top = fb.height.saturating_sub(block_h) / 2;
+
+        let logo = Rect { x: (fb.width - logo_w) / 2, y: top, w: logo_w, h: logo_h };
+        s.bar = Rect { x: (fb.width - bar_w) / 2, y: logo.y + logo_h + gap * 2, w: bar_w, h: bar_h };
+        s.text = Rect { x: 0, y: s.bar.y + bar_h + gap * 2, w: fb.width, h: text_h };
+
+        let bg = s.pack(BACKGROUND);
+        let screen = Rect { x: 0, y: 0, w: fb.width, h: fb.height };
+        s.fill(screen, bg);
+        match mode {
+            SplashMode::Bilinear => s.draw_logo_bilinear(logo, bg),
+            _ => s.draw_logo_nearest(logo, bg),
+        }
+        s.progress(0);
+        s.present(screen);
+        s
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Fills the progress bar to `percent` (clamped to 100).
+    pub fn progress(&self, percent: u32) {
+        if !self.active {
+            return;
+        }
+        let done = self.bar.w * percent.min(100) / 100;
+        self.fill(Rect { w: done, ..self.bar }, self.pack(BAR_FILL));
+        self.fill(Rect { x: self.bar.x + done, w: self.bar.w - done, ..self.bar }, self.pack(BAR_TRACK));
+        self.present(self.bar);
+    }
+
+    /// Replaces the status line under the progress bar.
+    pub fn status(&self, text: &str) {
+        if !self.active {
+            return;
+        }
+        self.fill(self.text, self.pack(BACKGROUND));
+
+        let scale = self.text_scale;
+        let cell = (GLYPH_W + 1) * scale;
+        let fit = (self.fb.width / cell) as usize;
+        let n = text.chars().count().min(MAX_STATUS_CHARS).min(fit);
+        let mut x = (self.fb.width - n as u32 * cell) / 2;
+        let fg = self.pack(TEXT);
+        for ch in text.chars().take(n) {
+            let glyph = glyph_5x7(ch);
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..GLYPH_W {
+                    if bits & (1 << (GLYPH_W - 1 - col)) != 0 {
+                        let px = Rect { x: x + col * scale, y: self.text.y + row as u32 * scale, w: scale, h: scale };
+                        self.fill(px, fg);
+                    }
+                }
+            }
+            x += cell;
+        }
+        self.present(self.text);
+    }
+
+    fn draw_logo_nearest(&self, r: Rect, bg: u32) {
+        let (lw, lh) = (LOGO_WIDTH as u64, LOGO_HEIGHT as u64);
+        for dy in 0..r.h {
+            let sy = ((2 * dy as u64 + 1) * lh / (2 * r.h as u64)) as usize;
+            for dx in 0..r.w {
+                let sx = ((2 * dx as u64 + 1) * lw / (2 * r.w as u64)) as usize;
+                let p = (sy * LOGO_WIDTH as usize + sx) * 4;
+                let px = &LOGO_RGBA[p..p + 4];
+                if px[3] != 0 {
+                    self.put(r.x + dx, r.y + dy, self.channels.blend_over(bg, px[0], px[1], px[2], px[3]));
+                }
+            }
+        }
+    }
+
+    /// Bilinear filtering on premultiplied samples, so transparent texels
+    /// don't darken the edges of the logo.
+    fn draw_logo_bilinear(&self, r: Rect, bg: u32) {
+        for dy in 0..r.h {
+            let (y0, y1, fy) = sample_axis(dy, r.h, LOGO_HEIGHT);
+            for dx in 0..r.w {
+                let (x0, x1, fx) = sample_axis(dx, r.w, LOGO_WIDTH);
+                let taps = [
+                    (x0, y0, (256 - fx) * (256 - fy)),
+                    (x1, y0, fx * (256 - fy)),
+                    (x0, y1, (256 - fx) * fy),
+                    (x1, y1, fx * fy),
+                ];
+
+                let (mut pr, mut pg, mut pb, mut pa) = (0u64, 0u64, 0u64, 0u64);
+                for (x, y, w) in taps {
+                    let p = (y as usize * LOGO_WIDTH as usize + x as usize) * 4;
+                    let a = LOGO_RGBA[p + 3] as u64 * w as u64;
+                    pr += LOGO_RGBA[p] as u64 * a;
+                    pg += LOGO_RGBA[p + 1] as u64 * a;
+                    pb += LOGO_RGBA[p + 2] as u64 * a;
+                    pa += a;
+                }
+                let alpha = (pa >> 16) as u8;
+                if alpha == 0 {
+                    continue;
+                }
+                let (sr, sg, sb) = ((pr / pa) as u8, (pg / pa) as u8, (pb / pa) as u8);
+                self.put(r.x + dx, r.y + dy, self.channels.blend_over(bg, sr, sg, sb, alpha));
+            }
+        }
+    }
+
+    fn pack(&self, (r, g, b): (u8, u8, u8)) -> u32 {
+        self.channels.pack(r, g, b)
+    }
+
+    /// Pushes a drawn area to a Blt-only screen; direct framebuffers need nothing.
+    fn present(&self, r: Rect) {
+        if let Some(shadow) = &self.shadow {
+            let _ = shadow.present(r.x, r.y, r.w, r.h);
+        }
+    }
+
+    fn fill(&self, r: Rect, px: u32) {
+        let x_end = (r.x + r.w).min(self.fb.width);
+        let y_end = (r.y + r.h).min(self.fb.height);
+        for y in r.y..y_end {
+            for x in r.x..x_end {
+                self.put(x, y, px);
+            }
+        }
+    }
+
+    #[inline]
+    fn put(&self, x: u32, y: u32, px: u32) {
+        let bpp = self.fb.bytes_per_pixel();
+        let off = (y as usize * self.fb.stride as usize + x as usize) * bpp;
+        unsafe { write_pixel((self.fb.base as *mut u8).add(off), bpp, px) }
+    }
+}
+
+/// Maps destination pixel `d` of `dst_len` onto the source axis: the two
+/// neighbouring texels and the 8-bit weight of the second one.
+fn sample_axis(d: u32, dst_len: u32, src_len: u32) -> (u32, u32, u32) {
+    // Pixel centers: s = (d + 0.5) * src / dst - 0.5, in 16.16 fixed point.
+    let s = ((2 * d as u64 + 1) * ((src_len as u64) << 16) / (2 * dst_len as u64)).saturating_sub(1 << 15);
+    let s0 = ((s >> 16) as u32).min(src_len - 1);
+    let s1 = (s0 + 1).min(src_len - 1);
+    (s0, s1, ((s >> 8) & 0xFF) as u32)
+}
@@ -0,0 +1,11 @@
+extern "win64" {
+    /// `src/jump.asm`: switches to the kernel stack and jumps to `entry`
+    /// with RDI = `boot_info`.
+    fn jump_to_kernel(entry: usize, stack_top: usize, boot_info: usize) -> !;
+}
+
+/// Enters the kernel. Must run after ExitBootServices, with `stack_top` and
+/// `boot_info` pointing at pages the kernel now owns.
+pub fn trampoline_jump(entry: usize, stack_top: usize, boot_info: usize) -> ! {
+    unsafe { jump_to_kernel(entry, stack_top, boot_info) }
+}
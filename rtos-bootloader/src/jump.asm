@@ -1,284 +1,15 @@
+; Kernel handoff. Called (win64) after ExitBootServices with
+; RCX = entry, RDX = stack top, R8 = BootInfo*. The splash is drawn in
+; Rust before this point (src/boot/splash.rs); all this does is switch to
+; the kernel stack and enter it with RDI = BootInfo* (SysV first argument).
 BITS 64
 default rel
-section .rodata
-color_rgb:  dd 0x00612D00
-color_bgr:  dd 0x00002D61
-logo_data:
-    incbin "../images/rtos-logo-transparent.raw"
-logo_width:  dd 1024
-logo_height: dd 1024
 section .text
 global jump_to_kernel
 jump_to_kernel:
-    mov     r15, rcx
-    mov     r14, rdx
-    mov     r13, r8
-    mov     rbx, [r13 + 0x00]
-    mov     r12d, [r13 + 0x10]
-    mov     r11d, [r13 + 0x14]
-    mov     r10d, [r13 + 0x18]
-    mov     eax,  [r13 + 0x1C]
-    cmp     eax, 0
-    je      .use_bgr
-    cmp     eax, 1
-    je      .use_rgb
-    jmp     .skip_fill
-.use_bgr:
-    mov     eax, dword [rel color_bgr]
-    mov     r8d, 0
-    jmp     .fill_screen
-.use_rgb:
-    mov     eax, dword [rel color_rgb]
-    mov     r8d, 1
-.fill_screen:
-    xor     ecx, ecx
-.fill_row:
-    cmp     ecx, r11d
-    jae     .draw_logo
-    mov     edx, ecx
-    imul    rdx, r10
-    lea     rsi, [rbx + rdx*4]
-    mov     edi, r12d
-.fill_px:
-    mov     [rsi], eax
-    add     rsi, 4
-    dec     edi
-    jnz     .fill_px
-    inc     ecx
-    jmp     .fill_row
-.draw_logo:
-    mov     eax, [rel logo_width]
-    mov     edx, [rel logo_height]
-    mov     rsi, r12
-    shl     rsi, 16
-    xor     rdx, rdx
-    mov     rax, rsi
-    div     dword [rel logo_width]
-    mov     r9, rax
-    mov     eax, r11d
-    shl     rax, 16
-    xor     rdx, rdx
-    div     dword [rel logo_height]
-    cmp     rax, r9
-    cmovb   r9, rax
-    mov     rax, 0x10000
-    cmp     r9, rax
-    cmova   r9, rax
-    mov     eax, [rel logo_width]
-    imul    rax, r9
-    shr     rax, 16
-    mov     rdi, rax
-    mov     eax, [rel logo_height]
-    imul    rax, r9
-    shr     rax, 16
-    mov     rsi, rax
-    mov     eax, r12d
-    sub     eax, edi
-    shr     eax, 1
-    push    rax
-    mov     eax, r11d
-    sub     eax, esi
-    shr     eax, 1
-    push    rax
-    push    r9
-    push    rdi
-    push    rsi
-    xor     ebp, ebp
-.logo_row_loop:
-    cmp     ebp, r11d
-    jge     .done_logo
-    mov     eax, ebp
-    sub     eax, [rsp + 24]
-    test    eax, eax
-    js      .next_logo_row
-    cmp     eax, [rsp]
-    jge     .next_logo_row
-    shl     rax, 16
-    xor     rdx, rdx
-    div     qword [rsp + 16]
-    mov     eax, eax
-    mov     edx, [rel logo_width]
-    imul    eax, edx
-    lea     rsi, [rel logo_data]
-    lea     rsi, [rsi + rax*4]
-    mov     eax, ebp
-    imul    rax, r10
-    lea     rdi, [rbx + rax*4]
-    xor     ecx, ecx
-.logo_pixel_loop:
-    cmp     ecx, r12d
-    jge     .next_logo_row
-    mov     eax, ecx
-    sub     eax, [rsp + 32]
-    test    eax, eax
-    js      .advance_px
-    cmp     eax, [rsp + 8]
-    jge     .advance_px
-    shl     rax, 16
-    xor     rdx, rdx
-    div     qword [rsp + 16]
-    push    rbx
-    push    rbp
-    mov     edx, eax
-    shl     rdx, 2
-    mov     edx, [rsi + rdx]
-    ; edx now has RGBA pixel: bits 0-7=R, 8-15=G, 16-23=B, 24-31=A
-    mov     eax, edx
-    shr     eax, 24
-    and     eax, 0xFF
-    test    eax, eax
-    jz      .skip_store
-    cmp     eax, 255
-    je      .full_alpha
-    ; Alpha blend
-    push    r9
-    push    r10
-    mov     r9d, edx          ; r9d = source RGBA pixel
-    mov     r10d, eax         ; r10d = alpha
-    mov     ebx, [rdi + rcx*4] ; ebx = dst pixel (BGR or RGB)
-    ; inv_alpha = 255 - alpha
-    mov     eax, 255
-    sub     eax, r10d
-    mov     edx, eax          ; edx = inv_alpha
-
-    cmp     r8d, 0
-    jne     .blend_as_rgb
-
-    ; Destination is BGR format
-    ; Blend B: dst[0] with src B (byte 2 of RGBA)
-    mov     eax, r9d
-    shr     eax, 16
-    and     eax, 0xFF         ; B from RGBA
-    imul    eax, r10d
-    movzx   ebp, bl           ; dst B
-    imul    ebp, edx
-    add     eax, ebp
-    add     eax, 127
-    shr     eax, 8
-    push    rax
-    ; Blend G: dst[1] with src G (byte 1 of RGBA)
-    mov     eax, r9d
-    shr     eax, 8
-    and     eax, 0xFF         ; G from RGBA
-    imul    eax, r10d
-    mov     ebp, ebx
-    shr     ebp, 8
-    and     ebp, 0xFF         ; dst G
-    imul    ebp, edx
-    add     eax, ebp
-    add     eax, 127
-    shr     eax, 8
-    push    rax
-    ; Blend R: dst[2] with src R (byte 0 of RGBA)
-    movzx   eax, r9b          ; R from RGBA
-    imul    eax, r10d
-    mov     ebp, ebx
-    shr     ebp, 16
-    and     ebp, 0xFF         ; dst R
-    imul    ebp, edx
-    add     eax, ebp
-    add     eax, 127
-    shr     eax, 8
-    ; Combine as BGR
-    pop     rbp               ; G
-    pop     rbx               ; B
-    shl     eax, 16           ; R in high position
-    shl     ebp, 8            ; G in middle
-    or      eax, ebp
-    or      eax, ebx          ; B in low position
-    mov     [rdi + rcx*4], eax
-    pop     r10
-    pop     r9
-    jmp     .skip_store
-
-.blend_as_rgb:
-    ; Destination is RGB format
-    ; Blend R: dst[0] with src R (byte 0 of RGBA)
-    movzx   eax, r9b          ; R from RGBA
-    imul    eax, r10d
-    movzx   ebp, bl           ; dst R
-    imul    ebp, edx
-    add     eax, ebp
-    add     eax, 127
-    shr     eax, 8
-    push    rax
-    ; Blend G: dst[1] with src G (byte 1 of RGBA)
-    mov     eax, r9d
-    shr     eax, 8
-    and     eax, 0xFF         ; G from RGBA
-    imul    eax, r10d
-    mov     ebp, ebx
-    shr     ebp, 8
-    and     ebp, 0xFF         ; dst G
-    imul    ebp, edx
-    add     eax, ebp
-    add     eax, 127
-    shr     eax, 8
-    push    rax
-    ; Blend B: dst[2] with src B (byte 2 of RGBA)
-    mov     eax, r9d
-    shr     eax, 16
-    and     eax, 0xFF         ; B from RGBA
-    imul    eax, r10d
-    mov     ebp, ebx
-    shr     ebp, 16
-    and     ebp, 0xFF         ; dst B
-    imul    ebp, edx
-    add     eax, ebp
-    add     eax, 127
-    shr     eax, 8
-    ; Combine as RGB
-    pop     rbp               ; G
-    pop     rbx               ; R
-    shl     ebx, 16           ; R in high position
-    shl     ebp, 8            ; G in middle
-    or      ebx, ebp
-    or      ebx, eax          ; B in low position
-    mov     [rdi + rcx*4], ebx
-    pop     r10
-    pop     r9
-    jmp     .skip_store
-
-.full_alpha:
-    ; Source is RGBA: R=byte0, G=byte1, B=byte2
-    movzx   ebx, dl           ; R
-    mov     eax, edx
-    shr     eax, 8
-    and     eax, 0xFF         ; G
-    mov     ebp, edx
-    shr     ebp, 16
-    and     ebp, 0xFF         ; B
-    cmp     r8d, 0
-    jne     .store_rgb
-    ; Store as BGR: B, G, R
-    shl     ebx, 16           ; R high
-    shl     eax, 8            ; G middle
-    or      ebx, eax
-    or      ebx, ebp          ; B low
-    mov     [rdi + rcx*4], ebx
-    jmp     .skip_store
-.store_rgb:
-    ; Store as RGB: R, G, B
-    shl     ebp, 16           ; B high
-    shl     eax, 8            ; G middle
-    or      ebp, eax
-    or      ebp, ebx          ; R low
-    mov     [rdi + rcx*4], ebp
-.skip_store:
-    pop     rbp
-    pop     rbx
-.advance_px:
-    inc     ecx
-    jmp     .logo_pixel_loop
-.next_logo_row:
-    inc     ebp
-    jmp     .logo_row_loop
-.done_logo:
-    add     rsp, 40
-.skip_fill:
-    mov     rsp, r14
+    cli
+    mov     rsp, rdx
     and     rsp, -16
-    mov     rdi, r13
+    mov     rdi, r8
     xor     rbp, rbp
-    cli
-    jmp     r15
\ No newline at end of file
+    jmp     rcx
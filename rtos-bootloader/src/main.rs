@@ -15,6 +15,6 @@ fn efi_main() -> Status {
 }
 
 #[panic_handler]
-fn panic(_info: &core::panic::PanicInfo) -> ! {
-    loop {}
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    boot::screen::panic(info)
 }
\ No newline at end of file
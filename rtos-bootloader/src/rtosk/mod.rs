@@ -1,2 +1,4 @@
 pub mod parse;
+pub mod verify;
 pub use parse::{parse_header_and_segments, find_magic };
+pub use verify::{verify_signature, signature_required};
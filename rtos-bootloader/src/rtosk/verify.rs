@@ -0,0 +1,15 @@
+use rtosk_sig::SigError;
+
+include!(concat!(env!("OUT_DIR"), "/trusted_keys.rs"));
+
+/// Checks the image signature against the keys baked in at build time
+/// (`RTOS_TRUSTED_KEYS`). Returns the error either way; whether it is fatal is
+/// decided by `signature_required`.
+pub fn verify_signature(image_bytes: &[u8]) -> Result<(), SigError> {
+    rtosk_sig::verify(image_bytes, TRUSTED_KEYS).map(|_| ())
+}
+
+/// False only in `dev-unsigned` builds, which boot anything after a warning.
+pub const fn signature_required() -> bool {
+    !cfg!(feature = "dev-unsigned")
+}
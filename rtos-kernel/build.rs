@@ -6,10 +6,12 @@ fn main() {
     println!("cargo:warning=Using linker script: {}", linker_script);
     println!("cargo:rerun-if-changed=linker.ld");
 
-    // Assemble the kernel entry (ELF64)
+    // Assemble the kernel entry, the AP start-up code and the context switch (ELF64)
     println!("cargo:rerun-if-changed=src/entry.asm");
+    println!("cargo:rerun-if-changed=src/ap_trampoline.asm");
+    println!("cargo:rerun-if-changed=src/switch.asm");
     let mut b = nasm_rs::Build::new();
-    b.file("src/entry.asm").flag("-felf64");
+    b.file("src/entry.asm").file("src/ap_trampoline.asm").file("src/switch.asm").flag("-felf64");
     let _ = b.compile("kernel_entry");
 
     // Tell rustc to link the generated static lib from OUT_DIR
@@ -0,0 +1,99 @@
+//! ACPI tables, found through the RSDP the bootloader passed in
+//! `FirmwareInfo::acpi_rsdp`. The firmware's identity mapping covers them.
+//! Only what SMP bring-up needs is parsed: the MADT's local APICs.
+
+use crate::firmware;
+
+const SDT_HEADER_LEN: usize = 36;
+/// MADT: header, local APIC address (u32), flags (u32), then entries.
+const MADT_ENTRIES: usize = SDT_HEADER_LEN + 8;
+
+const MADT_LOCAL_APIC: u8 = 0;
+const MADT_LOCAL_X2APIC: u8 = 9;
+/// Local APIC flags: the CPU is usable as is.
+const LAPIC_ENABLED: u32 = 1 << 0;
+
+/// The MADT ("APIC" table).
+pub struct Madt {
+    bytes: &'static [u8],
+}
+
+impl Madt {
+    /// APIC IDs of the enabled processors, in table order. Disabled and
+    /// online-capable (hot-plug) entries are left out.
+    pub fn apic_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        let mut off = MADT_ENTRIES;
+        core::iter::from_fn(move || {
+            while off + 2 <= self.bytes.len() {
+                let (ty, len) = (self.bytes[off], self.bytes[off + 1] as usize);
+                if len < 2 || off + len > self.bytes.len() { return None; }
+                let e = &self.bytes[off..off + len];
+                off += len;
+                let id = match ty {
+                    MADT_LOCAL_APIC if len >= 8 && u32_at(e, 4) & LAPIC_ENABLED != 0 => e[3] as u32,
+                    MADT_LOCAL_X2APIC if len >= 16 && u32_at(e, 8) & LAPIC_ENABLED != 0 => u32_at(e, 4),
+                    _ => continue,
+                };
+                return Some(id);
+            }
+            None
+        })
+    }
+}
+
+/// The MADT, if the firmware has ACPI and the table checks out.
+pub fn madt() -> Option<Madt> {
+    let bytes = find_table(b"APIC")?;
+    (bytes.len() >= MADT_ENTRIES).then_some(Madt { bytes })
+}
+
+/// A system description table with a valid checksum, by signature, from the
+/// XSDT (ACPI 2.0+) or the RSDT.
+pub fn find_table(signature: &[u8; 4]) -> Option<&'static [u8]> {
+    let fw = firmware::get()?;
+    if fw.acpi_rsdp == 0 { return None; }
+    // SAFETY: the RSDP and the tables it leads to are in ACPI memory,
+    // identity-mapped and never reused by the kernel.
+    let rsdp = unsafe { core::slice::from_raw_parts(fw.acpi_rsdp as *const u8, 36) };
+    if &rsdp[..8] != b"RSD PTR " || !checksum_ok(&rsdp[..20]) { return None; }
+
+    let (root, entry_size) = if rsdp[15] >= 2 && checksum_ok(rsdp) && u64_at(rsdp, 24) != 0 {
+        (u64_at(rsdp, 24), 8)
+    } else {
+        (u32_at(rsdp, 16) as u64, 4)
+    };
+    // SAFETY: as above.
+    let root = unsafe { table_at(root)? };
+    let entries = &root[SDT_HEADER_LEN..];
+    for i in 0..entries.len() / entry_size {
+        let addr = if entry_size == 8 { u64_at(entries, i * 8) } else { u32_at(entries, i * 4) as u64 };
+        // SAFETY: as above; `table_at` checks length and checksum.
+        match unsafe { table_at(addr) } {
+            Some(t) if &t[..4] == signature => return Some(t),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The whole table at `addr`, if its header is sane and the checksum holds.
+unsafe fn table_at(addr: u64) -> Option<&'static [u8]> {
+    if addr == 0 { return None; }
+    let header = core::slice::from_raw_parts(addr as *const u8, SDT_HEADER_LEN);
+    let len = u32_at(header, 4) as usize;
+    if len < SDT_HEADER_LEN { return None; }
+    let table = core::slice::from_raw_parts(addr as *const u8, len);
+    checksum_ok(table).then_some(table)
+}
+
+fn checksum_ok(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |s, &b| s.wrapping_add(b)) == 0
+}
+
+fn u32_at(b: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes([b[off], b[off + 1], b[off + 2], b[off + 3]])
+}
+
+fn u64_at(b: &[u8], off: usize) -> u64 {
+    u32_at(b, off) as u64 | (u32_at(b, off + 4) as u64) << 32
+}
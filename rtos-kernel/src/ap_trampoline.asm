@@ -0,0 +1,115 @@
+; Application processor start-up code. smp.rs copies the bytes between
+; ap_trampoline_start and ap_trampoline_end to a free page below 1 MiB, fills
+; in the parameter block at the end, and sends the page number as the SIPI
+; vector. The AP goes real mode -> protected mode -> long mode on an
+; identity map below 4 GiB (params.boot_cr3) and calls params.entry with
+; params.arg in RDI on params.stack. The entry switches to the kernel's page
+; tables itself: the firmware may map this page no-execute.
+;
+; Everything is addressed relative to the page, so the copy works at any
+; 4 KiB-aligned address below 1 MiB.
+
+%define OFF(x) ((x) - ap_trampoline_start)
+
+global ap_trampoline_start
+global ap_trampoline_params
+global ap_trampoline_end
+
+section .rodata.ap_trampoline align=16
+
+BITS 16
+ap_trampoline_start:
+    cli
+    cld
+    mov     ax, cs
+    mov     ds, ax
+    xor     ebx, ebx
+    mov     bx, ax
+    shl     ebx, 4                      ; ebx = linear address of the page
+
+    ; The GDT pointer and the far jumps need linear addresses.
+    lea     eax, [ebx + OFF(gdt)]
+    mov     [OFF(gdtr) + 2], eax
+    lea     eax, [ebx + OFF(pm_entry)]
+    mov     [OFF(pm_jump)], eax
+    lea     eax, [ebx + OFF(lm_entry)]
+    mov     [OFF(lm_jump)], eax
+
+    o32 lgdt [OFF(gdtr)]
+    mov     eax, cr0
+    or      eax, 1                      ; PE
+    mov     cr0, eax
+    o32 jmp far [OFF(pm_jump)]
+
+BITS 32
+pm_entry:
+    mov     ax, 0x10
+    mov     ds, ax
+    mov     es, ax
+    mov     ss, ax
+
+    mov     eax, cr4
+    or      eax, 1 << 5                 ; PAE
+    mov     cr4, eax
+    mov     eax, [ebx + OFF(param_boot_cr3)]
+    mov     cr3, eax
+    mov     ecx, 0xC0000080             ; IA32_EFER: the BSP's NXE/SCE, plus LME
+    mov     eax, [ebx + OFF(param_efer)]
+    or      eax, 1 << 8
+    xor     edx, edx
+    wrmsr
+    mov     eax, cr0
+    or      eax, 0x80000001             ; PG | PE
+    mov     cr0, eax
+    jmp     far [ebx + OFF(lm_jump)]
+
+BITS 64
+lm_entry:
+    mov     ebx, ebx                    ; upper half is undefined after the switch
+    xor     eax, eax
+    mov     ds, ax
+    mov     es, ax
+    mov     ss, ax
+    mov     rax, [rbx + OFF(param_cr4)]
+    mov     cr4, rax
+    mov     rax, [rbx + OFF(param_cr0)]
+    mov     cr0, rax
+    mov     rsp, [rbx + OFF(param_stack)]
+    mov     rdi, [rbx + OFF(param_arg)]
+    mov     rax, [rbx + OFF(param_entry)]
+    xor     ebp, ebp
+    call    rax
+.hang:
+    cli
+    hlt
+    jmp     .hang
+
+align 16
+gdt:
+    dq 0
+    dq 0x00CF9A000000FFFF               ; 0x08: 32-bit code
+    dq 0x00CF92000000FFFF               ; 0x10: data
+    dq 0x00AF9A000000FFFF               ; 0x18: 64-bit code
+gdt_end:
+
+gdtr:
+    dw gdt_end - gdt - 1
+    dd 0                                ; linear base, patched
+pm_jump:
+    dd 0                                ; linear pm_entry, patched
+    dw 0x08
+lm_jump:
+    dd 0                                ; linear lm_entry, patched
+    dw 0x18
+
+; Filled in by smp.rs; layout matches `TrampolineParams`.
+align 8
+ap_trampoline_params:
+param_boot_cr3: dd 0                    ; identity map below 4 GiB
+param_efer:     dd 0                    ; low half of the BSP's IA32_EFER
+param_cr0:      dq 0
+param_cr4:      dq 0
+param_stack:    dq 0
+param_entry:    dq 0
+param_arg:      dq 0
+ap_trampoline_end:
@@ -0,0 +1,149 @@
+//! Local APIC: this CPU's ID, inter-processor interrupts, the timer and
+//! end-of-interrupt. In xAPIC mode the registers are the MMIO page named by
+//! `IA32_APIC_BASE` (identity-mapped by the firmware). If the firmware left
+//! the APIC in x2APIC mode, the same registers are MSRs.
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use x64_utils::msr;
+
+const REG_ID: u32 = 0x20;
+const REG_EOI: u32 = 0xB0;
+const REG_SVR: u32 = 0xF0;
+const REG_ICR_LOW: u32 = 0x300;
+const REG_ICR_HIGH: u32 = 0x310;
+const REG_LVT_TIMER: u32 = 0x320;
+const REG_TIMER_INIT: u32 = 0x380;
+const REG_TIMER_CURRENT: u32 = 0x390;
+const REG_TIMER_DIVIDE: u32 = 0x3E0;
+
+/// SVR: APIC software enable.
+const SVR_ENABLE: u32 = 1 << 8;
+/// ICR: level assert; INIT, start-up and fixed delivery modes.
+const ICR_ASSERT: u32 = 1 << 14;
+const ICR_INIT: u32 = 0b101 << 8;
+const ICR_STARTUP: u32 = 0b110 << 8;
+/// ICR: the previous IPI has not been accepted yet (xAPIC only).
+const ICR_PENDING: u32 = 1 << 12;
+/// LVT timer: interrupt masked; periodic instead of one-shot.
+const LVT_MASKED: u32 = 1 << 16;
+const LVT_PERIODIC: u32 = 1 << 17;
+/// Timer divide configuration for "bus clock / 16".
+const TIMER_DIVIDE_16: u32 = 0b0011;
+
+const BASE_X2APIC: u64 = 1 << 10;
+const BASE_ADDR_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+/// x2APIC registers are MSRs `0x800 + (xAPIC offset >> 4)`.
+const X2APIC_MSR: u32 = 0x800;
+
+static BASE: AtomicU64 = AtomicU64::new(0);
+static X2APIC: AtomicBool = AtomicBool::new(false);
+
+/// Reads the APIC mode and address from the BSP. Call before any other
+/// function here.
+pub fn init() {
+    // SAFETY: IA32_APIC_BASE exists on every x86_64 CPU.
+    let base = unsafe { msr::read(msr::IA32_APIC_BASE) };
+    X2APIC.store(base & BASE_X2APIC != 0, Ordering::Relaxed);
+    BASE.store(base & BASE_ADDR_MASK, Ordering::Release);
+}
+
+/// True when the APIC is driven through MSRs.
+pub fn is_x2apic() -> bool {
+    X2APIC.load(Ordering::Relaxed)
+}
+
+unsafe fn read(reg: u32) -> u32 {
+    if is_x2apic() {
+        msr::read(X2APIC_MSR + (reg >> 4)) as u32
+    } else {
+        core::ptr::read_volatile((BASE.load(Ordering::Acquire) + reg as u64) as *const u32)
+    }
+}
+
+unsafe fn write(reg: u32, value: u32) {
+    if is_x2apic() {
+        msr::write(X2APIC_MSR + (reg >> 4), value as u64);
+    } else {
+        core::ptr::write_volatile((BASE.load(Ordering::Acquire) + reg as u64) as *mut u32, value);
+    }
+}
+
+/// This CPU's APIC ID.
+pub fn id() -> u32 {
+    // SAFETY: the ID register is always readable.
+    let v = unsafe { read(REG_ID) };
+    if is_x2apic() { v } else { v >> 24 }
+}
+
+/// Software-enables this CPU's APIC, so it accepts fixed IPIs, with
+/// `spurious` as the spurious-interrupt vector.
+///
+/// # Safety
+/// The IDT must handle `spurious`.
+pub unsafe fn enable(spurious: u8) {
+    write(REG_SVR, SVR_ENABLE | spurious as u32);
+}
+
+/// Signals end of interrupt; call at the end of every APIC-delivered handler.
+pub fn eoi() {
+    // SAFETY: writing 0 to EOI has no other effect.
+    unsafe { write(REG_EOI, 0) }
+}
+
+/// Sends `low` (vector and delivery mode) to APIC `dest` and waits until the
+/// local APIC has accepted it.
+unsafe fn send(dest: u32, low: u32) {
+    if is_x2apic() {
+        msr::write(X2APIC_MSR + (REG_ICR_LOW >> 4), (dest as u64) << 32 | low as u64);
+        return;
+    }
+    write(REG_ICR_HIGH, dest << 24);
+    write(REG_ICR_LOW, low);
+    while read(REG_ICR_LOW) & ICR_PENDING != 0 {
+        core::hint::spin_loop();
+    }
+}
+
+/// INIT IPI: puts CPU `dest` into wait-for-SIPI.
+///
+/// # Safety
+/// Whatever `dest` was running is lost.
+pub unsafe fn send_init(dest: u32) {
+    send(dest, ICR_INIT | ICR_ASSERT);
+}
+
+/// Start-up IPI: CPU `dest` begins in real mode at `page * 4096`.
+///
+/// # Safety
+/// `dest` must be waiting for SIPI and start-up code must be at that page.
+pub unsafe fn send_startup(dest: u32, page: u8) {
+    send(dest, ICR_STARTUP | ICR_ASSERT | page as u32);
+}
+
+/// Fixed IPI with `vector` to CPU `dest`.
+///
+/// # Safety
+/// `dest`'s IDT must handle `vector`.
+pub unsafe fn send_ipi(dest: u32, vector: u8) {
+    send(dest, ICR_ASSERT | vector as u32);
+}
+
+/// Starts the timer counting down from `count` (bus clock / 16). With a
+/// vector it interrupts there, once or every `count`; without one it runs
+/// masked, for calibration against `timer_count`.
+///
+/// # Safety
+/// The IDT must handle `vector`.
+pub unsafe fn start_timer(count: u32, vector: Option<u8>, periodic: bool) {
+    write(REG_LVT_TIMER, LVT_MASKED);
+    write(REG_TIMER_DIVIDE, TIMER_DIVIDE_16);
+    let mode = if periodic { LVT_PERIODIC } else { 0 };
+    write(REG_LVT_TIMER, mode | vector.map_or(LVT_MASKED, |v| v as u32));
+    write(REG_TIMER_INIT, count);
+}
+
+/// What is left of the timer's current count.
+pub fn timer_count() -> u32 {
+    // SAFETY: the current-count register is always readable.
+    unsafe { read(REG_TIMER_CURRENT) }
+}
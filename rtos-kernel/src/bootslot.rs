@@ -0,0 +1,71 @@
+//! The A/B slot this kernel was booted from, and the "good" marker that
+//! stops the bootloader from counting further boots of it as tries (see
+//! `rtos_types::boot_slot`).
+
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU32, Ordering};
+use rtos_types::boot_info::BootInfo;
+use rtos_types::boot_slot::{slot_name, BootSlotState, BOOT_SLOT_NONE, BOOT_SLOT_VARIABLE, RTOS_VARIABLE_VENDOR};
+use uefi_raw::{Guid, Status};
+use crate::runtime;
+use crate::serial_writer::SerialWriter;
+
+const VENDOR: Guid = Guid::parse_or_panic(RTOS_VARIABLE_VENDOR);
+
+static SLOT: AtomicU32 = AtomicU32::new(BOOT_SLOT_NONE);
+static TRIES_LEFT: AtomicU32 = AtomicU32::new(0);
+
+/// Records the slot from `BootInfo`. Call once, early in `kmain`.
+pub fn init(bi: &BootInfo) {
+    TRIES_LEFT.store(bi.boot_tries_left, Ordering::Relaxed);
+    SLOT.store(bi.boot_slot, Ordering::Release);
+}
+
+/// `BOOT_SLOT_A`, `BOOT_SLOT_B`, or `BOOT_SLOT_NONE` without slots.
+pub fn current() -> u32 {
+    SLOT.load(Ordering::Acquire)
+}
+
+/// Writes the slot to the serial port; nothing without slots.
+pub fn report() {
+    let slot = current();
+    if slot == BOOT_SLOT_NONE { return; }
+    let _ = writeln!(
+        SerialWriter,
+        "K: boot slot {}, {} tries left",
+        slot_name(slot), TRIES_LEFT.load(Ordering::Relaxed),
+    );
+}
+
+/// Marks the running slot good, so the bootloader keeps booting it. Returns
+/// whether the variable was written: not without slots, when the slot is
+/// already good, or when an update has made the other slot active since.
+pub fn mark_good() -> Result<bool, Status> {
+    let slot = current();
+    if slot == BOOT_SLOT_NONE { return Ok(false); }
+
+    let mut buf = [0u8; BootSlotState::SIZE];
+    let (len, attrs) = runtime::get_variable(BOOT_SLOT_VARIABLE, &VENDOR, &mut buf)?;
+    let mut state = BootSlotState::from_bytes(&buf[..len]).ok_or(Status::VOLUME_CORRUPTED)?;
+    if state.active as u32 != slot || state.is_good() {
+        return Ok(false);
+    }
+    state.good = 1;
+    runtime::set_variable(BOOT_SLOT_VARIABLE, &VENDOR, attrs, &state.to_bytes())?;
+    Ok(true)
+}
+
+/// `mark_good`, reported on the serial port. Called once the kernel is up.
+pub fn confirm() {
+    let slot = current();
+    match mark_good() {
+        Ok(true) => {
+            let _ = writeln!(SerialWriter, "K: boot slot {} marked good", slot_name(slot));
+        }
+        Ok(false) => {}
+        Err(status) => {
+            let _ = writeln!(SerialWriter, "K: could not mark boot slot {} good: {:?}", slot_name(slot), status);
+        }
+    }
+}
+
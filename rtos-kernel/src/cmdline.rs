@@ -0,0 +1,119 @@
+//! Kernel command line handed over in `BootInfo`.
+//!
+//! Tokens are separated by whitespace and are either `key=value` or bare
+//! flags (`quiet`). Values may be double-quoted to contain spaces
+//! (`title="two words"`). When a key repeats, the last occurrence wins.
+
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use rtos_types::boot_info::BootInfo;
+
+static CMDLINE_PTR: AtomicPtr<u8> = AtomicPtr::new(core::ptr::null_mut());
+static CMDLINE_LEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Records the command line from `BootInfo`. Call once, early in `kmain`.
+///
+/// # Safety
+/// The `BootInfo` command line buffer must stay mapped and unmodified for the
+/// life of the kernel (it lives in the bootloader's LOADER_DATA BootInfo page).
+pub unsafe fn init(bi: &BootInfo) {
+    let s = bi.cmdline();
+    CMDLINE_PTR.store(s.as_ptr() as *mut u8, Ordering::Relaxed);
+    CMDLINE_LEN.store(s.len(), Ordering::Release);
+}
+
+/// The kernel command line (empty before `init` or when none was passed).
+pub fn get() -> Cmdline<'static> {
+    let len = CMDLINE_LEN.load(Ordering::Acquire);
+    let ptr = CMDLINE_PTR.load(Ordering::Relaxed);
+    if ptr.is_null() || len == 0 { return Cmdline::new(""); }
+    // SAFETY: set once by `init` from a validated `&'static str`.
+    let s = unsafe { core::str::from_utf8_unchecked(core::slice::from_raw_parts(ptr, len)) };
+    Cmdline::new(s)
+}
+
+/// Borrowed view of a command line with typed lookups.
+#[derive(Clone, Copy)]
+pub struct Cmdline<'a> {
+    text: &'a str,
+}
+
+/// Iterator over `(key, value)` tokens; flags have no value.
+pub struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = (&'a str, Option<&'a str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let s = self.rest.trim_start();
+        if s.is_empty() { return None; }
+
+        // Token ends at the first whitespace outside quotes.
+        let mut in_quotes = false;
+        let mut end = s.len();
+        for (i, c) in s.char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                c if c.is_whitespace() && !in_quotes => { end = i; break; }
+                _ => {}
+            }
+        }
+        let tok = &s[..end];
+        self.rest = &s[end..];
+
+        Some(match tok.split_once('=') {
+            Some((k, v)) => {
+                let v = v.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(v);
+                (k, Some(v))
+            }
+            None => (tok, None),
+        })
+    }
+}
+
+impl<'a> Cmdline<'a> {
+    pub const fn new(text: &'a str) -> Self {
+        Cmdline { text }
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    pub fn tokens(&self) -> Tokens<'a> {
+        Tokens { rest: self.text }
+    }
+
+    /// Value of the last `key=value`; `Some("")` for a bare flag.
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.tokens()
+            .filter(|(k, _)| *k == key)
+            .last()
+            .map(|(_, v)| v.unwrap_or(""))
+    }
+
+    /// True if `key` appears at all, as a flag or with a value.
+    pub fn has(&self, key: &str) -> bool {
+        self.tokens().any(|(k, _)| k == key)
+    }
+
+    /// Decimal or `0x` hex integer value of `key`.
+    pub fn get_u64(&self, key: &str) -> Option<u64> {
+        let v = self.get(key)?;
+        match v.strip_prefix("0x").or_else(|| v.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => v.parse().ok(),
+        }
+    }
+
+    /// Boolean value of `key`: a bare flag is `true`; values accept
+    /// `1/0`, `true/false`, `on/off`, `yes/no`.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            "" | "1" | "true" | "on" | "yes" => Some(true),
+            "0" | "false" | "off" | "no" => Some(false),
+            _ => None,
+        }
+    }
+}
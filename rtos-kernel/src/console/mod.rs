@@ -1,4 +1,5 @@
 
+#[allow(clippy::module_inception)]
 pub mod console;
 
 pub use console::Console;
\ No newline at end of file
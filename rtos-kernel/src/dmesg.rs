@@ -0,0 +1,43 @@
+//! Boot messages handed over by the bootloader (`BootInfo::boot_log_ptr`).
+
+use core::sync::atomic::{AtomicU64, Ordering};
+use rtos_types::boot_info::BootInfo;
+use rtos_types::boot_log::BootLog;
+use crate::serial_writer::SerialWriter;
+
+static BOOT_LOG: AtomicU64 = AtomicU64::new(0);
+
+/// Records where the bootloader's log lives. Call once, early in `kmain`.
+///
+/// # Safety
+/// The log region must stay mapped and reserved for the life of the kernel
+/// (it lives in `RTOS_BOOT_LOG_MEMORY_TYPE` pages).
+pub unsafe fn init(bi: &BootInfo) {
+    BOOT_LOG.store(bi.boot_log_ptr, Ordering::Release);
+}
+
+/// The bootloader's log, oldest text first, as two pieces (see
+/// `BootLog::contents`). Empty when the bootloader didn't pass one.
+pub fn boot_messages() -> (&'static [u8], &'static [u8]) {
+    let addr = BOOT_LOG.load(Ordering::Acquire);
+    if addr == 0 { return (&[], &[]); }
+    // SAFETY: set by `init` from BootInfo; the region is never freed.
+    let log = unsafe { BootLog::from_addr(addr) };
+    let (a, b) = log.contents();
+    unsafe {
+        (core::slice::from_raw_parts(a.as_ptr(), a.len()), core::slice::from_raw_parts(b.as_ptr(), b.len()))
+    }
+}
+
+/// Writes the full boot history to the serial port.
+pub fn dump() {
+    SerialWriter::write("K: ---- boot log ----\n");
+    let (a, b) = boot_messages();
+    for part in [a, b] {
+        // Wrap-around can split a UTF-8 sequence; print what decodes.
+        for chunk in part.utf8_chunks() {
+            SerialWriter::write(chunk.valid());
+        }
+    }
+    SerialWriter::write("K: ---- end boot log ----\n");
+}
@@ -0,0 +1,46 @@
+//! Firmware facts the bootloader collected before ExitBootServices: vendor,
+//! UEFI revision, Secure Boot state and the ACPI/SMBIOS entry points.
+
+use core::fmt::Write;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use rtos_types::boot_info::BootInfo;
+use rtos_types::firmware_info::FirmwareInfo;
+use crate::serial_writer::SerialWriter;
+
+static INFO: AtomicPtr<FirmwareInfo> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Records the firmware info from `BootInfo`. Call once, early in `kmain`.
+///
+/// # Safety
+/// `bi` must stay mapped and unmodified for the life of the kernel (it
+/// lives in `Loader` memory).
+pub unsafe fn init(bi: &BootInfo) {
+    INFO.store(bi.firmware() as *const FirmwareInfo as *mut FirmwareInfo, Ordering::Release);
+}
+
+/// The firmware info, or `None` before `init` or when the bootloader found
+/// no UEFI revision (an older bootloader).
+pub fn get() -> Option<&'static FirmwareInfo> {
+    let ptr = INFO.load(Ordering::Acquire);
+    // SAFETY: set once by `init` from BootInfo, which lives for the kernel's lifetime.
+    let fw = unsafe { ptr.as_ref()? };
+    (fw.uefi_revision != 0).then_some(fw)
+}
+
+/// Writes the firmware summary to the serial port.
+pub fn report() {
+    let Some(fw) = get() else {
+        SerialWriter::write("K: firmware: unknown\n");
+        return;
+    };
+    let _ = writeln!(
+        SerialWriter,
+        "K: firmware: {} rev {:#x}, UEFI {}.{:02}, secure boot {}",
+        fw.vendor(), fw.firmware_revision, fw.uefi_major(), fw.uefi_minor(), fw.secure_boot_str(),
+    );
+    let _ = writeln!(
+        SerialWriter,
+        "K: firmware tables: ACPI {} at {:#x}, SMBIOS {} at {:#x}; {} block devices, {} filesystems",
+        fw.acpi_version, fw.acpi_rsdp, fw.smbios_version, fw.smbios_entry, fw.block_devices, fw.filesystems,
+    );
+}
@@ -1,49 +1,37 @@
-use core::ptr;
 use crate::framebuffer::Framebuffer;
-use crate::framebuffer::pack_rgb_fmt;
-use crate::serial_log;
-use crate::utils::{SerialWriter, mmio_store32, mmio_load32};
-use core::ptr::NonNull;
+use rtos_framebuffer::pixel::{read_pixel, write_pixel};
 
 
 impl Framebuffer {
     #[inline]
-    fn bytes_per_pixel(&self) -> usize { 4 }
+    pub fn row_ptr(&self, y: u32) -> *mut u8 {
+        // Advance by (y * stride * bytes per pixel) bytes from the base pointer.
+        unsafe { self.ptr.add((y as usize) * self.pitch()) }
+    }
 
     #[inline]
-    pub fn row_ptr(&self, y: u32) -> *mut u32 {
-        // Advance by (y * stride * bpp) bytes from the base pointer.
-        let bpp = self.bytes_per_pixel();
-        let stride_bytes = (self.stride as usize) * bpp;
-        unsafe { (self.ptr as *mut u8).add((y as usize) * stride_bytes) as *mut u32 }
+    fn pixel_ptr(&self, x: u32, y: u32) -> *mut u8 {
+        unsafe { self.row_ptr(y).add((x as usize) * (self.bytes_per_pixel as usize)) }
     }
 
-    /// Solid clear (no alpha). Uses mmio_store32 writes and respects stride.
+    /// Solid clear (no alpha). Volatile writes; respects stride.
     pub fn clear(&mut self, r: u8, g: u8, b: u8) {
-        let fill   = pack_rgb_fmt(self.format, r, g, b);
-        let width  = self.width  as usize;
-        let height = self.height as usize;
-        let stride = self.stride as usize;
-        let draw_w = if width < stride { width } else { stride };
-
-        let mut y = 0;
-        while y < height {
-            let mut p = unsafe { self.row_ptr(y as u32) };
-            let end = p.wrapping_add(draw_w);
-            while p != end {
-                unsafe { mmio_store32(p, fill) };
-                p = unsafe { p.add(1) };
-            }
-            y += 1;
-        }
+        let fill = self.pack_rgb(r, g, b);
+        self.fill_rect(0, 0, self.width, self.height, fill);
     }
 
-    /// Put a single pixel; volatile write.
+    /// Put a single packed pixel; volatile write.
     #[inline]
     pub fn put_pixel(&mut self, x: u32, y: u32, color: u32) {
         if x >= self.width || y >= self.height { return; }
-        let p = unsafe { self.row_ptr(y).add(x as usize) };
-        unsafe { ptr::write_volatile(p, color) };
+        unsafe { write_pixel(self.pixel_ptr(x, y), self.bytes_per_pixel as usize, color) };
+    }
+
+    /// Read back a packed pixel; 0 outside the screen.
+    #[inline]
+    pub fn get_pixel(&self, x: u32, y: u32) -> u32 {
+        if x >= self.width || y >= self.height { return 0; }
+        unsafe { read_pixel(self.pixel_ptr(x, y), self.bytes_per_pixel as usize) }
     }
 
     /// Fill a rectangle (clipped to framebuffer); volatile writes; respects stride.
@@ -55,17 +43,32 @@ impl Framebuffer {
         let y2 = (y.saturating_add(h)).min(self.height);
         if x1 >= x2 || y1 >= y2 { return; }
 
+        let bpp = self.bytes_per_pixel as usize;
         let span = (x2 - x1) as usize;
 
         for yy in y1..y2 {
-            let mut px = unsafe { self.row_ptr(yy).add(x1 as usize) };
+            let mut px = self.pixel_ptr(x1, yy);
             for _ in 0..span {
-                unsafe { ptr::write_volatile(px, color) };
-                unsafe { px = px.add(1) };
+                unsafe {
+                    write_pixel(px, bpp, color);
+                    px = px.add(bpp);
+                }
             }
         }
     }
 
+    /// Move scanlines `src_y..src_y + rows` to start at `dst_y`; the rows
+    /// may overlap (used for scrolling).
+    pub fn copy_rows(&mut self, src_y: u32, dst_y: u32, rows: u32) {
+        let rows = rows
+            .min(self.height.saturating_sub(src_y))
+            .min(self.height.saturating_sub(dst_y));
+        if rows == 0 { return; }
+        unsafe {
+            core::ptr::copy(self.row_ptr(src_y), self.row_ptr(dst_y), rows as usize * self.pitch());
+        }
+    }
+
     /// Blit RGBA centered, no scaling. Alpha path delegated to blend.rs.
     pub fn blit_rgba_centered_noscale(&mut self, rgba: &[u8], w: u32, h: u32, use_alpha: bool) {
         if use_alpha {
@@ -84,31 +87,18 @@ impl Framebuffer {
         let off_x  = (fb_w - draw_w) / 2;
         let off_y  = (fb_h - draw_h) / 2;
 
-        let fmt = self.format;
-
         for y in 0..draw_h {
             // Source row (RGBA: 4 bytes per pixel)
             let src_row = (y * w) as usize;
 
-            // Destination pointer at start of this row, offset by off_x
-            let mut dst = unsafe { self.row_ptr(off_y + y).add(off_x as usize) };
-
             // Walk visible pixels only (draw_w)
             for x in 0..draw_w {
                 let s = (src_row + x as usize) * 4;
                 if s + 3 >= rgba.len() { break; } // guard against short buffers
 
-                // RGBA (bytes R,G,B,A in memory)
-                let r = rgba[s + 0];
-                let g = rgba[s + 1];
-                let b = rgba[s + 2];
-                // let a = rgba[s + 3]; // ignored in non-alpha path
-
-                let packed = pack_rgb_fmt(fmt, r, g, b);
-                unsafe {
-                    ptr::write_volatile(dst, packed);
-                    dst = dst.add(1);
-                }
+                // RGBA (bytes R,G,B,A in memory); alpha ignored in this path
+                let packed = self.pack_rgb(rgba[s], rgba[s + 1], rgba[s + 2]);
+                self.put_pixel(off_x + x, off_y + y, packed);
             }
         }
     }
@@ -1,5 +1,6 @@
-use rtos_types::{BootInfo, FramebufferFormat};
-use core::slice;
+use rtos_types::boot_info::BootInfo;
+use rtos_types::framebuffer_format::FramebufferFormat;
+use rtos_framebuffer::pixel::Channels;
 
 // ===== Submodules =====
 pub mod draw;
@@ -15,33 +16,42 @@ pub use validate::*;
 
 // ===== Core framebuffer struct =====
 pub struct Framebuffer {
-    pub ptr: *mut u32,
+    pub ptr: *mut u8,
     pub width: u32,
     pub height: u32,
     pub stride: u32,               // pixels per scanline
-    pub format: FramebufferFormat, // Bgr or Rgb
+    pub format: FramebufferFormat, // Rgb, Bgr or Bitmask
+    pub channels: Channels,        // from the BootInfo masks
+    pub bytes_per_pixel: u32,      // 2 (15/16 bpp), 3 or 4
 }
 
 impl Framebuffer {
-    pub unsafe fn from_bootinfo(bi: &BootInfo) -> Self {
-        Framebuffer {
-            ptr: bi.framebuffer.base as *mut u32,
-            width: bi.framebuffer.width,
-            height: bi.framebuffer.height,
-            stride: bi.framebuffer.stride,
-            format: bi.framebuffer.format,
+    /// None when the bootloader passed no linear framebuffer (no GOP, or a
+    /// Blt-only mode that was only drawable before ExitBootServices).
+    pub unsafe fn from_bootinfo(bi: &BootInfo) -> Option<Self> {
+        let info = &bi.framebuffer;
+        if !info.is_linear() {
+            return None;
         }
+        Some(Framebuffer {
+            ptr: info.base as *mut u8,
+            width: info.width,
+            height: info.height,
+            stride: info.stride,
+            format: info.format,
+            channels: Channels::of(info)?,
+            bytes_per_pixel: info.bytes_per_pixel() as u32,
+        })
     }
 
+    /// Bytes per scanline.
     #[inline]
-    fn row_mut(&mut self, y: u32) -> &mut [u32] {
-        let off = (y as usize) * (self.stride as usize);
-        unsafe { slice::from_raw_parts_mut(self.ptr.add(off), self.stride as usize) }
+    pub fn pitch(&self) -> usize {
+        (self.stride as usize) * (self.bytes_per_pixel as usize)
     }
 
     #[inline]
     pub fn pack_rgb(&self, r: u8, g: u8, b: u8) -> u32 {
-        // utils::pack_rgb_fmt already handles RGB vs BGR
-        pack_rgb_fmt(self.format, r, g, b)
+        self.channels.pack(r, g, b)
     }
-}
\ No newline at end of file
+}
@@ -1,6 +1,5 @@
 use x86_64::VirtAddr;
 use crate::framebuffer::Framebuffer;
-use crate::serial_log;
 
 #[derive(Clone, Copy, Debug)]
 pub enum FbSoftCheckError {
@@ -14,19 +13,21 @@ pub enum FbSoftCheckError {
 
 #[inline]
 fn fb_total_bytes(fb: &Framebuffer) -> usize {
-    (fb.stride as usize) * (fb.height as usize) * 4
+    fb.pitch() * (fb.height as usize)
 }
 
 pub fn validate_framebuffer_soft(fb: &mut Framebuffer) -> Result<(), FbSoftCheckError> {
     let va = VirtAddr::new(fb.ptr as u64);
     if !is_canonical_u64(va.as_u64())  { return Err(FbSoftCheckError::NonCanonicalVa); }
     if fb.width == 0 || fb.height == 0 { return Err(FbSoftCheckError::ZeroDims); }
-    if (fb.ptr as usize) & 0x3 != 0 { return Err(FbSoftCheckError::MisalignedPtr); }
+    let bpp = fb.bytes_per_pixel as usize;
+    let align = if bpp == 3 { 1 } else { bpp };
+    if !(fb.ptr as usize).is_multiple_of(align) { return Err(FbSoftCheckError::MisalignedPtr); }
 
     let row0 = fb.row_ptr(0) as usize;
     let row1 = if fb.height > 1 { fb.row_ptr(1) as usize } else { row0 };
     let delta = row1.wrapping_sub(row0);
-    let expect = (fb.stride as usize) * 4;
+    let expect = fb.pitch();
     if delta != expect { return Err(FbSoftCheckError::StrideDeltaMismatch { delta_bytes: delta, expect_bytes: expect }); }
 
     let width = fb.width as usize;
@@ -34,28 +35,27 @@ pub fn validate_framebuffer_soft(fb: &mut Framebuffer) -> Result<(), FbSoftCheck
     let draw_w = if width < stride { width } else { stride };
     if draw_w == 0 { return Ok(()); }
 
-    unsafe {
-        let p0 = fb.row_ptr(0);
-        let pE = fb.row_ptr(0).add(draw_w - 1);
+    // Probe patterns are cut to the pixel size so they read back unchanged.
+    let mask = if bpp >= 4 { u32::MAX } else { (1u32 << (8 * bpp)) - 1 };
+    let end_x = (draw_w - 1) as u32;
 
-        let old0 = core::ptr::read_volatile(p0);
-        let oldE = core::ptr::read_volatile(pE);
+    let old0 = fb.get_pixel(0, 0);
+    let old_end = fb.get_pixel(end_x, 0);
 
-        let t0 = old0 ^ 0x0055AA55;
-        let tE = oldE ^ 0x00AA55AA;
+    let t0 = (old0 ^ 0x0055AA55) & mask;
+    let t_end = (old_end ^ 0x00AA55AA) & mask;
 
-        core::ptr::write_volatile(p0, t0);
-        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
-        let r0 = core::ptr::read_volatile(p0);
-        core::ptr::write_volatile(p0, old0);
-        if r0 != t0 { return Err(FbSoftCheckError::ProbeStartMismatch { wrote: t0, read: r0 }); }
+    fb.put_pixel(0, 0, t0);
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    let r0 = fb.get_pixel(0, 0);
+    fb.put_pixel(0, 0, old0);
+    if r0 != t0 { return Err(FbSoftCheckError::ProbeStartMismatch { wrote: t0, read: r0 }); }
 
-        core::ptr::write_volatile(pE, tE);
-        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
-        let rE = core::ptr::read_volatile(pE);
-        core::ptr::write_volatile(pE, oldE);
-        if rE != tE { return Err(FbSoftCheckError::ProbeEndMismatch { wrote: tE, read: rE }); }
-    }
+    fb.put_pixel(end_x, 0, t_end);
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    let r_end = fb.get_pixel(end_x, 0);
+    fb.put_pixel(end_x, 0, old_end);
+    if r_end != t_end { return Err(FbSoftCheckError::ProbeEndMismatch { wrote: t_end, read: r_end }); }
 
     let _ = fb_total_bytes(fb); // keeps the intent obvious for future checks
     Ok(())
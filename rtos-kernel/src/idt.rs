@@ -0,0 +1,89 @@
+//! The interrupt descriptor table shared by all CPUs. CPU exceptions are
+//! reported on the serial port and stop the faulting CPU; the remaining
+//! vectors are the APIC's.
+
+use core::fmt::Write;
+use lazy_static::lazy_static;
+use x86_64::registers::control::Cr2;
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
+use crate::apic;
+use crate::percpu;
+use crate::sched;
+use crate::serial_writer::SerialWriter;
+
+/// TSS interrupt stack used for double faults.
+pub const DOUBLE_FAULT_IST: u16 = 0;
+/// Local APIC timer: the scheduler tick (`timer.rs`).
+pub const TIMER_VECTOR: u8 = 0x20;
+/// IPI that wakes an idle CPU (`smp::run_on`).
+pub const WAKE_VECTOR: u8 = 0xF0;
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+lazy_static! {
+    static ref IDT: InterruptDescriptorTable = {
+        let mut idt = InterruptDescriptorTable::new();
+        idt.divide_error.set_handler_fn(divide_error);
+        idt.invalid_opcode.set_handler_fn(invalid_opcode);
+        idt.general_protection_fault.set_handler_fn(general_protection);
+        idt.page_fault.set_handler_fn(page_fault);
+        // SAFETY: every CPU's TSS has a stack at DOUBLE_FAULT_IST (percpu::init_cpu).
+        unsafe {
+            idt.double_fault.set_handler_fn(double_fault).set_stack_index(DOUBLE_FAULT_IST);
+        }
+        idt[TIMER_VECTOR].set_handler_fn(timer);
+        idt[WAKE_VECTOR].set_handler_fn(wake);
+        idt[SPURIOUS_VECTOR].set_handler_fn(spurious);
+        idt
+    };
+}
+
+/// Loads the IDT on this CPU.
+pub fn load() {
+    IDT.load();
+}
+
+/// Reports an exception and halts this CPU for good.
+fn fatal(what: &str, frame: &InterruptStackFrame, detail: Option<u64>) -> ! {
+    let cpu = percpu::current().map_or(0, |c| c.index.load(core::sync::atomic::Ordering::Relaxed));
+    let _ = write!(SerialWriter, "K: cpu {}: {} at {:#x}", cpu, what, frame.instruction_pointer.as_u64());
+    if let Some(d) = detail {
+        let _ = write!(SerialWriter, " ({:#x})", d);
+    }
+    SerialWriter::write("\n");
+    loop {
+        x64_utils::interrupts::cli();
+        x64_utils::interrupts::hlt();
+    }
+}
+
+extern "x86-interrupt" fn divide_error(frame: InterruptStackFrame) {
+    fatal("divide error", &frame, None);
+}
+
+extern "x86-interrupt" fn invalid_opcode(frame: InterruptStackFrame) {
+    fatal("invalid opcode", &frame, None);
+}
+
+extern "x86-interrupt" fn general_protection(frame: InterruptStackFrame, code: u64) {
+    fatal("general protection fault", &frame, Some(code));
+}
+
+extern "x86-interrupt" fn page_fault(frame: InterruptStackFrame, _code: PageFaultErrorCode) {
+    fatal("page fault", &frame, Some(Cr2::read_raw()));
+}
+
+extern "x86-interrupt" fn double_fault(frame: InterruptStackFrame, _code: u64) -> ! {
+    fatal("double fault", &frame, None);
+}
+
+// EOI first: `sched::tick` may switch threads and only come back here later.
+extern "x86-interrupt" fn timer(_frame: InterruptStackFrame) {
+    apic::eoi();
+    sched::tick();
+}
+
+extern "x86-interrupt" fn wake(_frame: InterruptStackFrame) {
+    apic::eoi();
+}
+
+extern "x86-interrupt" fn spurious(_frame: InterruptStackFrame) {}
@@ -0,0 +1,57 @@
+//! Initrd modules handed over in `BootInfo`.
+//!
+//! Each module is an opaque blob loaded by the bootloader from one of the
+//! comma-separated `initrd` paths in boot.cfg. Blobs that are tar archives
+//! can be browsed through [`archive`].
+
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use rtos_types::boot_info::BootInfo;
+use rtos_types::boot_module::BootModule;
+use vfs::tar::TarFs;
+
+static MODULES_PTR: AtomicPtr<BootModule> = AtomicPtr::new(core::ptr::null_mut());
+static MODULE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Records the module table from `BootInfo`. Call once, early in `kmain`.
+///
+/// # Safety
+/// The module table and every blob it describes must stay mapped and
+/// unmodified for the life of the kernel (they live in RTOS module memory).
+pub unsafe fn init(bi: &BootInfo) {
+    let m = bi.modules();
+    MODULES_PTR.store(m.as_ptr() as *mut BootModule, Ordering::Relaxed);
+    MODULE_COUNT.store(m.len(), Ordering::Release);
+}
+
+/// All modules, in the order they were listed (empty before `init`).
+pub fn modules() -> &'static [BootModule] {
+    let count = MODULE_COUNT.load(Ordering::Acquire);
+    let ptr = MODULES_PTR.load(Ordering::Relaxed);
+    if ptr.is_null() || count == 0 { return &[]; }
+    // SAFETY: set once by `init` from a slice that lives for the kernel's lifetime.
+    unsafe { core::slice::from_raw_parts(ptr, count) }
+}
+
+/// Contents of a module.
+pub fn data(m: &BootModule) -> &'static [u8] {
+    // SAFETY: the bootloader loaded `size` bytes at `base`; see `init`.
+    unsafe { core::slice::from_raw_parts(m.base as *const u8, m.size as usize) }
+}
+
+/// Contents of the module whose path, as written in boot.cfg, is `name` or
+/// ends in the file name `name` (ASCII case-insensitive, like FAT).
+pub fn find(name: &str) -> Option<&'static [u8]> {
+    modules()
+        .iter()
+        .find(|m| {
+            let path = m.name();
+            let file = path.rsplit(['\\', '/']).next().unwrap_or(path);
+            path.eq_ignore_ascii_case(name) || file.eq_ignore_ascii_case(name)
+        })
+        .map(data)
+}
+
+/// The first module that parses as a tar archive.
+pub fn archive() -> Option<TarFs<'static>> {
+    modules().iter().find_map(|m| TarFs::new(data(m)).ok())
+}
@@ -23,7 +23,7 @@ fn is_canonical_u64(va: u64) -> bool {
 
 #[inline]
 fn fb_byte_len(fb: &Framebuffer) -> usize {
-    (fb.stride as usize) * (fb.height as usize) * 4
+    fb.pitch() * (fb.height as usize)
 }
 
 pub unsafe fn check_framebuffer_mapped<T: Translate>(
@@ -44,7 +44,7 @@ pub unsafe fn check_framebuffer_mapped<T: Translate>(
     let row0 = fb.row_ptr(0) as usize;
     let row1 = if fb.height > 1 { fb.row_ptr(1) as usize } else { row0 };
     let delta = row1.wrapping_sub(row0);
-    let expect = (fb.stride as usize) * 4;
+    let expect = fb.pitch();
     if delta != expect {
         return Err(FbCheckError::StrideDeltaMismatch { delta_bytes: delta, expect_bytes: expect });
     }
@@ -59,29 +59,30 @@ pub unsafe fn probe_framebuffer_rw(fb: &mut Framebuffer) -> Result<(), FbCheckEr
     let draw_w = core::cmp::min(width, stride);
     if draw_w == 0 || height == 0 { return Ok(()); }
 
-    let p0 = fb.row_ptr(0);
-    let pE = fb.row_ptr(0).add(draw_w - 1);
+    let bpp = fb.bytes_per_pixel as usize;
+    let mask = if bpp >= 4 { u32::MAX } else { (1u32 << (8 * bpp)) - 1 };
+    let end_x = (draw_w - 1) as u32;
 
-    let old0 = core::ptr::read_volatile(p0);
-    let oldE = core::ptr::read_volatile(pE);
+    let old0 = fb.get_pixel(0, 0);
+    let old_end = fb.get_pixel(end_x, 0);
 
-    let test0 = old0 ^ 0x0055AA55;
-    let testE = oldE ^ 0x00AA55AA;
+    let test0 = (old0 ^ 0x0055AA55) & mask;
+    let test_end = (old_end ^ 0x00AA55AA) & mask;
 
-    core::ptr::write_volatile(p0, test0);
+    fb.put_pixel(0, 0, test0);
     core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
-    let back0 = core::ptr::read_volatile(p0);
-    core::ptr::write_volatile(p0, old0);
+    let back0 = fb.get_pixel(0, 0);
+    fb.put_pixel(0, 0, old0);
     if back0 != test0 {
         return Err(FbCheckError::ProbeWriteMismatchStart { wrote: test0, read_back: back0 });
     }
 
-    core::ptr::write_volatile(pE, testE);
+    fb.put_pixel(end_x, 0, test_end);
     core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
-    let backE = core::ptr::read_volatile(pE);
-    core::ptr::write_volatile(pE, oldE);
-    if backE != testE {
-        return Err(FbCheckError::ProbeWriteMismatchEnd { wrote: testE, read_back: backE });
+    let back_end = fb.get_pixel(end_x, 0);
+    fb.put_pixel(end_x, 0, old_end);
+    if back_end != test_end {
+        return Err(FbCheckError::ProbeWriteMismatchEnd { wrote: test_end, read_back: back_end });
     }
 
     Ok(())
@@ -107,7 +108,7 @@ pub unsafe fn validate_framebuffer<T: Translate>(
     SerialWriter::write("K: row0: "); SerialWriter::write_hex(row0); SerialWriter::write("\n");
     SerialWriter::write("K: row1: "); SerialWriter::write_hex(row1); SerialWriter::write("\n");
     SerialWriter::write("K: delta: "); SerialWriter::write_usize(row1.wrapping_sub(row0)); SerialWriter::write("\n");
-    SerialWriter::write("K: expect: "); SerialWriter::write_usize((fb.stride as usize) * 4); SerialWriter::write("\n");
+    SerialWriter::write("K: expect: "); SerialWriter::write_usize(fb.pitch()); SerialWriter::write("\n");
 
     match probe_framebuffer_rw(fb) {
         Ok(()) => { serial_log!("FB probe writes OK"); true }
@@ -0,0 +1,81 @@
+//! Release latency tracer: for every job of a periodic thread, the time from
+//! the timer interrupt that released it to the thread running again, kept
+//! as a power-of-two histogram per thread. `lat` on the serial monitor
+//! prints them.
+
+use core::cell::UnsafeCell;
+use core::fmt::Write;
+use x86_64::instructions::interrupts::without_interrupts;
+use crate::sched::{self, MAX_THREADS};
+use crate::serial_writer::SerialWriter;
+use crate::timer;
+
+/// Bucket 0 is under 1 us; bucket `b` is `[2^(b-1), 2^b)` us, and the last
+/// one takes everything above.
+const BUCKETS: usize = 16;
+
+#[derive(Clone, Copy)]
+struct Histogram {
+    buckets: [u32; BUCKETS],
+    count: u64,
+    total_us: u64,
+    min_us: u64,
+    max_us: u64,
+}
+
+impl Histogram {
+    const EMPTY: Histogram = Histogram { buckets: [0; BUCKETS], count: 0, total_us: 0, min_us: u64::MAX, max_us: 0 };
+
+    fn add(&mut self, us: u64) {
+        let bucket = (u64::BITS - us.leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)] += 1;
+        self.count += 1;
+        self.total_us += us;
+        self.min_us = self.min_us.min(us);
+        self.max_us = self.max_us.max(us);
+    }
+}
+
+struct Histograms(UnsafeCell<[Histogram; MAX_THREADS]>);
+
+// SAFETY: only touched with interrupts off, on the BSP.
+unsafe impl Sync for Histograms {}
+
+static HISTOGRAMS: Histograms = Histograms(UnsafeCell::new([Histogram::EMPTY; MAX_THREADS]));
+
+/// Starts thread slot `index` over with an empty histogram.
+pub fn clear(index: usize) {
+    // SAFETY: interrupts are off.
+    without_interrupts(|| unsafe { (*HISTOGRAMS.0.get())[index] = Histogram::EMPTY });
+}
+
+/// Adds one release-to-run sample for thread slot `index`.
+pub fn record(index: usize, cycles: u64) {
+    let us = timer::cycles_to_us(cycles);
+    // SAFETY: interrupts are off.
+    without_interrupts(|| unsafe { (*HISTOGRAMS.0.get())[index].add(us) });
+}
+
+/// Prints the histogram of every periodic thread.
+pub fn report() {
+    for info in sched::jobs().into_iter().flatten() {
+        // SAFETY: interrupts are off.
+        let h = without_interrupts(|| unsafe { (*HISTOGRAMS.0.get())[info.index] });
+        if h.count == 0 {
+            let _ = writeln!(SerialWriter, "K: latency {}: no samples", info.name);
+            continue;
+        }
+        let _ = writeln!(
+            SerialWriter,
+            "K: latency {}: {} samples, min {} us, avg {} us, max {} us",
+            info.name, h.count, h.min_us, h.total_us / h.count, h.max_us,
+        );
+        for (bucket, &n) in h.buckets.iter().enumerate().filter(|(_, &n)| n != 0) {
+            if bucket == BUCKETS - 1 {
+                let _ = writeln!(SerialWriter, "K:   >= {:>5} us {:>8}", 1u64 << (bucket - 1), n);
+            } else {
+                let _ = writeln!(SerialWriter, "K:   < {:>6} us {:>8}", 1u64 << bucket, n);
+            }
+        }
+    }
+}
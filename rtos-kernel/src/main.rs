@@ -1,11 +1,37 @@
 #![no_std]
 #![no_main]
+#![feature(abi_x86_interrupt)]
 
 #[path = "../../libs/serial-writer/src/lib.rs"]
 mod serial_writer;
 
 
 mod panic;
+mod acpi;
+mod apic;
+mod bootslot;
+mod cmdline;
+mod dmesg;
+mod firmware;
+mod idt;
+mod initrd;
+mod latency;
+mod memmap;
+mod monitor;
+mod mutex;
+mod percpu;
+mod pit;
+mod rt;
+mod runtime;
+mod sched;
+mod smp;
+mod timer;
+// Drawing API for the framebuffer console; not every helper or re-export
+// has a caller yet.
+#[allow(dead_code, unused_imports)]
+mod framebuffer;
+#[allow(dead_code, unused_imports)]
+mod console;
 /*mod kernel;
 mod types;
 mod console;*/
@@ -14,18 +40,138 @@ mod console;*/
 //use kernel::kernel_init;
 use rtos_types::{boot_info::BootInfo, framebuffer_info::FramebufferInfo, framebuffer_format::FramebufferFormat};
 use serial_writer::SerialWriter;
+use core::fmt::Write;
+use vfs::ReadOnlyFs;
+use rtos_types::memory_map::MemoryKind;
+use console::Console;
+use framebuffer::Framebuffer;
 
+// `bi` comes from the bootloader's jump, not from Rust callers.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn kmain(bi: *const BootInfo) -> ! {
     SerialWriter::init();
     serial_logk!("Kernel initializingk...");
+    smp::init_bsp();
+
+    if !bi.is_null() {
+        unsafe {
+            bootslot::init(&*bi);
+            cmdline::init(&*bi);
+            dmesg::init(&*bi);
+            firmware::init(&*bi);
+            initrd::init(&*bi);
+            memmap::init(&*bi);
+            runtime::init(&*bi);
+        }
+    }
+    let args = cmdline::get();
+    if !args.as_str().is_empty() {
+        SerialWriter::write("K: cmdline: ");
+        SerialWriter::write(args.as_str());
+        SerialWriter::write("\n");
+    }
+    // Stable format; tools/qemu-memtest.sh parses this line.
+    let _ = writeln!(
+        SerialWriter,
+        "K: memory map: {} regions, {} MiB usable",
+        memmap::regions().len(),
+        memmap::total(MemoryKind::Usable) >> 20,
+    );
+    firmware::report();
+    runtime::report();
+    if args.get_bool("efi_virtual") == Some(true) {
+        // Identity placement until the kernel has page tables of its own.
+        match unsafe { runtime::set_virtual_address_map(0) } {
+            Ok(()) => SerialWriter::write("K: runtime services in virtual mode\n"),
+            Err(status) => {
+                let _ = writeln!(SerialWriter, "K: SetVirtualAddressMap failed: {:?}", status);
+            }
+        }
+    }
+    bootslot::report();
+    smp::start_aps(&args);
+    // From here on kmain is a thread like any other.
+    sched::init();
+    // Blt-only screens need boot services, so those boots stay serial-only.
+    let fb = if bi.is_null() { FramebufferInfo::empty() } else { unsafe { (*bi).framebuffer } };
+    if fb.is_linear() {
+        let _ = writeln!(
+            SerialWriter,
+            "K: framebuffer {}x{} {} at {:#x}",
+            fb.width, fb.height, fb.format.as_str(), fb.base,
+        );
+    } else if fb.format == FramebufferFormat::BltOnly && fb.width != 0 {
+        SerialWriter::write("K: framebuffer is Blt-only; serial console only\n");
+    } else {
+        SerialWriter::write("K: no framebuffer; serial console only\n");
+    }
+    if args.has("fbcon") {
+        fb_banner(bi);
+    }
+    if args.has("memmap") {
+        memmap::dump();
+    }
+    if args.has("dmesg") {
+        dmesg::dump();
+    }
+    if let Some(level) = args.get_u64("log_level") {
+        serial_logk!("log_level =", level);
+    }
+    if args.get_bool("test_mode") == Some(true) {
+        serial_logk!("test mode enabled");
+    }
+
+    for m in initrd::modules() {
+        SerialWriter::write("K: module ");
+        SerialWriter::write(m.name());
+        SerialWriter::write("\n");
+        serial_logk!("  size", m.size);
+    }
+    if let Some(fs) = initrd::archive() {
+        fs.visit(&mut |node| {
+            SerialWriter::write("K:   ");
+            SerialWriter::write(node.prefix);
+            if !node.prefix.is_empty() { SerialWriter::write("/"); }
+            SerialWriter::write(node.name);
+            SerialWriter::write("\n");
+        });
+    }
+    if let Some(motd) = initrd::find("motd.txt") {
+        serial_logk!("motd.txt bytes", motd.len());
+    }
+
+    if args.has("schedtest") {
+        sched::self_test();
+    }
+    if args.has("rttest") {
+        rt::self_test(if args.get("rttest") == Some("edf") { rt::Class::Edf } else { rt::Class::RateMonotonic });
+    }
 
     //let bi = unsafe { &*bi };
     //let _state = unsafe { kernel_init(bi) };
-    serial_logk!("Kernel initialized.");
-
+    // Up far enough that the bootloader should stop counting tries.
+    bootslot::confirm();
+    if !args.has("quiet") {
+        serial_logk!("Kernel initialized.");
+    }
 
-    loop {
-        unsafe { core::arch::asm!("hlt", options(nomem, nostack, preserves_flags)); }
+    // The monitor and the idle thread carry on without kmain.
+    if let Err(e) = sched::spawn("monitor", monitor::PRIORITY, monitor::run, 0) {
+        let _ = writeln!(SerialWriter, "K: monitor not started: {:?}", e);
     }
+    sched::exit()
+}
+
+/// `fbcon`: replaces the boot splash with a text console showing the mode,
+/// drawn by the kernel's own renderer.
+fn fb_banner(bi: *const BootInfo) {
+    let Some(mut fb) = (if bi.is_null() { None } else { unsafe { Framebuffer::from_bootinfo(&*bi) } }) else {
+        return;
+    };
+    let (w, h, fmt, bits) = (fb.width, fb.height, fb.format, fb.bytes_per_pixel * 8);
+    let mut con = Console::new(&mut fb, (0xE0, 0xE8, 0xF0), Some((0x00, 0x2D, 0x61)));
+    con.clear();
+    con.write_line("RTOS kernel");
+    writeln!(con, "framebuffer {}x{} {} {} bpp", w, h, fmt.as_str(), bits);
 }
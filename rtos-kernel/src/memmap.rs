@@ -0,0 +1,48 @@
+//! Physical memory map handed over in `BootInfo`, taken by the bootloader
+//! at ExitBootServices.
+
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use rtos_types::boot_info::BootInfo;
+use rtos_types::memory_map::{MemoryKind, MemoryRegion};
+use crate::serial_writer::SerialWriter;
+
+static MAP_PTR: AtomicPtr<MemoryRegion> = AtomicPtr::new(core::ptr::null_mut());
+static MAP_LEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Records the memory map from `BootInfo`. Call once, early in `kmain`.
+///
+/// # Safety
+/// The region table must stay mapped and unmodified for the life of the
+/// kernel (it lives in `Loader` memory).
+pub unsafe fn init(bi: &BootInfo) {
+    let m = bi.memory_map();
+    MAP_PTR.store(m.as_ptr() as *mut MemoryRegion, Ordering::Relaxed);
+    MAP_LEN.store(m.len(), Ordering::Release);
+}
+
+/// All regions, sorted by base address (empty before `init`).
+pub fn regions() -> &'static [MemoryRegion] {
+    let len = MAP_LEN.load(Ordering::Acquire);
+    let ptr = MAP_PTR.load(Ordering::Relaxed);
+    if ptr.is_null() || len == 0 { return &[]; }
+    // SAFETY: set once by `init` from a table that lives for the kernel's lifetime.
+    unsafe { core::slice::from_raw_parts(ptr, len) }
+}
+
+/// Total bytes of the given kind.
+pub fn total(kind: MemoryKind) -> u64 {
+    regions().iter().filter(|r| r.kind == kind).map(|r| r.size()).sum()
+}
+
+/// Writes every region to the serial port.
+pub fn dump() {
+    for r in regions() {
+        SerialWriter::write("K:   ");
+        SerialWriter::write_hex(r.base as usize);
+        SerialWriter::write("..");
+        SerialWriter::write_hex(r.end() as usize);
+        SerialWriter::write(" ");
+        SerialWriter::write(r.kind.as_str());
+        SerialWriter::write("\n");
+    }
+}
@@ -0,0 +1,55 @@
+//! Serial monitor: a thread that reads command lines from COM1 and prints
+//! deadline misses as the scheduler logs them. `ps` lists the threads with
+//! their CPU time, `rt` the periodic tasks, `lat` their release latency.
+
+use core::fmt::Write;
+use crate::{latency, rt, sched};
+use crate::serial_writer::SerialWriter;
+
+pub const PRIORITY: u8 = 12;
+/// COM1 is polled; nothing else needs its interrupt yet.
+const POLL_MS: u64 = 20;
+const LINE_MAX: usize = 64;
+
+pub fn run(_: usize) {
+    SerialWriter::write("K: monitor: type `help` for commands\n");
+    let mut line = [0u8; LINE_MAX];
+    let mut len = 0;
+    loop {
+        while let Some(b) = SerialWriter::try_read() {
+            match b {
+                b'\r' | b'\n' => {
+                    SerialWriter::write("\n");
+                    command(core::str::from_utf8(&line[..len]).unwrap_or("").trim());
+                    len = 0;
+                }
+                0x08 | 0x7F if len > 0 => {
+                    len -= 1;
+                    SerialWriter::write("\x08 \x08");
+                }
+                b' '..=b'~' if len < LINE_MAX => {
+                    line[len] = b;
+                    len += 1;
+                    let echo = [b];
+                    SerialWriter::write(core::str::from_utf8(&echo).unwrap_or(""));
+                }
+                _ => {}
+            }
+        }
+        rt::print_misses();
+        sched::sleep_ms(POLL_MS);
+    }
+}
+
+fn command(cmd: &str) {
+    match cmd {
+        "" => {}
+        "ps" => sched::report(),
+        "rt" => rt::report(),
+        "lat" => latency::report(),
+        "help" => SerialWriter::write("K: commands: ps (threads and CPU time), rt (periodic tasks), lat (release latency), help\n"),
+        other => {
+            let _ = writeln!(SerialWriter, "K: unknown command `{}`", other);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Sleeping mutex for kernel threads, with priority inheritance: while a
+//! thread waits, the owner runs at the waiter's rank if that is higher, so
+//! a middle-priority thread cannot keep a high-priority one waiting on a
+//! low-priority owner. The bookkeeping is in `sched.rs` (`lock`/`unlock`).
+//!
+//! Threads only run on the BSP, and so does this mutex: it does not
+//! exclude other CPUs. Code sent to an AP with `smp::run_on` must not lock
+//! one (debug builds assert this).
+
+use core::cell::{Cell, UnsafeCell};
+use core::ops::{Deref, DerefMut};
+use crate::sched::{self, Tid};
+
+/// The scheduler's view of a mutex: which thread holds it.
+pub struct RawMutex {
+    pub(crate) owner: Cell<Option<Tid>>,
+}
+
+/// A mutex for threads on the BSP; see the module docs for why not on APs.
+pub struct Mutex<T> {
+    raw: RawMutex,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: `raw.owner` is only touched by the scheduler, on the BSP with
+// interrupts off, and `data` only through the guard of the thread that owns
+// the mutex.
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Mutex { raw: RawMutex { owner: Cell::new(None) }, data: UnsafeCell::new(value) }
+    }
+
+    /// Blocks until the calling thread owns the mutex. Locking a mutex the
+    /// thread already holds panics, and so does exiting while holding one.
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        sched::lock(&self.raw);
+        MutexGuard { mutex: self }
+    }
+}
+
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the guard's thread owns the mutex.
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: as above.
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        sched::unlock(&self.mutex.raw);
+    }
+}
@@ -1,7 +1,24 @@
 #![allow(unused)]
 
+use core::fmt::Write;
+use crate::cmdline;
+use crate::runtime::{self, ResetKind};
+use crate::serial_writer::SerialWriter;
+
 #[panic_handler]
-fn panic(_info: &core::panic::PanicInfo) -> ! {
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    let _ = writeln!(SerialWriter, "K: panic: {}", info);
+    // `panic=reboot|warm|poweroff`: unattended machines come back, and QEMU
+    // test runs exit, instead of hanging.
+    let kind = match cmdline::get().get("panic") {
+        Some("reboot") => Some(ResetKind::Cold),
+        Some("warm") => Some(ResetKind::Warm),
+        Some("poweroff") => Some(ResetKind::Shutdown),
+        _ => None,
+    };
+    if let Some(kind) = kind {
+        runtime::reset(kind);
+    }
     loop {
         unsafe { core::arch::asm!("hlt", options(nomem, nostack, preserves_flags)); }
     }
@@ -0,0 +1,108 @@
+//! Per-CPU data. Every processor gets a `PerCpu` block holding its GDT and
+//! TSS; its GS base points at the block, so `current()` is one GS-relative
+//! load. Blocks are indexed 0 (the BSP) to `MAX_CPUS - 1`.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use x86_64::instructions::segmentation::{Segment, CS, DS, ES, SS};
+use x86_64::instructions::tables::load_tss;
+use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable};
+use x86_64::structures::tss::TaskStateSegment;
+use x86_64::VirtAddr;
+use x64_utils::msr;
+use crate::idt;
+
+pub const MAX_CPUS: usize = 16;
+/// Stack for double faults, so a kernel stack overflow is still reported.
+const FAULT_STACK_SIZE: usize = 4096;
+
+#[repr(C, align(64))]
+pub struct PerCpu {
+    /// Address of this block; read through `gs:[0]` by `current`.
+    self_addr: AtomicU64,
+    pub index: AtomicUsize,
+    pub apic_id: AtomicU32,
+    /// Set by the CPU itself once it runs kernel code on its own tables.
+    pub online: AtomicBool,
+    /// `fn(usize)` handed over by `smp::run_on`; 0 when idle.
+    pub work: AtomicUsize,
+    gdt: UnsafeCell<GlobalDescriptorTable>,
+    tss: UnsafeCell<TaskStateSegment>,
+}
+
+// SAFETY: `gdt` and `tss` are only written by their own CPU in `init_cpu`,
+// before it loads them.
+unsafe impl Sync for PerCpu {}
+
+impl PerCpu {
+    const fn new() -> Self {
+        PerCpu {
+            self_addr: AtomicU64::new(0),
+            index: AtomicUsize::new(0),
+            apic_id: AtomicU32::new(0),
+            online: AtomicBool::new(false),
+            work: AtomicUsize::new(0),
+            gdt: UnsafeCell::new(GlobalDescriptorTable::new()),
+            tss: UnsafeCell::new(TaskStateSegment::new()),
+        }
+    }
+}
+
+#[repr(C, align(16))]
+struct FaultStack(UnsafeCell<[u8; FAULT_STACK_SIZE]>);
+
+// SAFETY: each stack is only used by the CPU with the same index.
+unsafe impl Sync for FaultStack {}
+
+static CPUS: [PerCpu; MAX_CPUS] = [const { PerCpu::new() }; MAX_CPUS];
+static FAULT_STACKS: [FaultStack; MAX_CPUS] = [const { FaultStack(UnsafeCell::new([0; FAULT_STACK_SIZE])) }; MAX_CPUS];
+/// Set once the BSP's GS base points at its block.
+static READY: AtomicBool = AtomicBool::new(false);
+
+/// Loads a fresh GDT (kernel code/data and the TSS) and the IDT on this CPU,
+/// and points GS at block `index`. Each index must be used by one CPU only.
+///
+/// # Safety
+/// Interrupts must be off; segment registers are reloaded underneath the
+/// running code.
+pub unsafe fn init_cpu(index: usize, apic_id: u32) {
+    let cpu = &CPUS[index];
+    let tss = &mut *cpu.tss.get();
+    let stack_top = FAULT_STACKS[index].0.get() as u64 + FAULT_STACK_SIZE as u64;
+    tss.interrupt_stack_table[idt::DOUBLE_FAULT_IST as usize] = VirtAddr::new(stack_top);
+
+    let gdt = &mut *cpu.gdt.get();
+    *gdt = GlobalDescriptorTable::new();
+    let code = gdt.append(Descriptor::kernel_code_segment());
+    let data = gdt.append(Descriptor::kernel_data_segment());
+    let tss_sel = gdt.append(Descriptor::tss_segment(&*cpu.tss.get()));
+    (*cpu.gdt.get()).load();
+    CS::set_reg(code);
+    SS::set_reg(data);
+    DS::set_reg(data);
+    ES::set_reg(data);
+    load_tss(tss_sel);
+    idt::load();
+
+    let addr = cpu as *const PerCpu as u64;
+    cpu.self_addr.store(addr, Ordering::Relaxed);
+    cpu.index.store(index, Ordering::Relaxed);
+    cpu.apic_id.store(apic_id, Ordering::Relaxed);
+    msr::set_gs_base(addr);
+    if index == 0 {
+        READY.store(true, Ordering::Release);
+    }
+}
+
+/// This CPU's block, or `None` before the BSP has run `init_cpu`.
+pub fn current() -> Option<&'static PerCpu> {
+    if !READY.load(Ordering::Acquire) { return None; }
+    // SAFETY: every CPU that runs kernel code after READY has set its GS
+    // base to its block, whose first field is the block's own address.
+    Some(unsafe { &*(msr::gs_read_u64(0) as *const PerCpu) })
+}
+
+/// Block `index`, whether or not that CPU is running.
+pub fn cpu(index: usize) -> &'static PerCpu {
+    &CPUS[index]
+}
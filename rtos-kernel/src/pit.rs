@@ -0,0 +1,38 @@
+//! Busy-wait delays from the legacy PIT, for start-up code that runs before
+//! any other timer is calibrated. Channel 2 counts down once in mode 0 and
+//! its output is read back through port 0x61.
+
+use x86_64::instructions::port::Port;
+
+const PIT_HZ: u64 = 1_193_182;
+/// Longest single count, well inside the 16-bit counter (~54.9 ms).
+const MAX_CHUNK_US: u64 = 50_000;
+
+/// Spins for at least `us` microseconds.
+pub fn delay_us(us: u64) {
+    let mut left = us;
+    while left > 0 {
+        let chunk = left.min(MAX_CHUNK_US);
+        count_down(((chunk * PIT_HZ).div_ceil(1_000_000)).max(1) as u16);
+        left -= chunk;
+    }
+}
+
+fn count_down(ticks: u16) {
+    let mut gate: Port<u8> = Port::new(0x61);
+    let mut command: Port<u8> = Port::new(0x43);
+    let mut channel2: Port<u8> = Port::new(0x42);
+    // SAFETY: the PIT and port 0x61 are fixed legacy hardware; only channel
+    // 2 (the speaker timer) is touched, with the speaker kept off.
+    unsafe {
+        let g = gate.read();
+        gate.write((g & !0x02) | 0x01);
+        command.write(0b1011_0000); // channel 2, lo/hi byte, mode 0, binary
+        channel2.write(ticks as u8);
+        channel2.write((ticks >> 8) as u8);
+        // OUT2 goes high when the count reaches zero.
+        while gate.read() & 0x20 == 0 {
+            core::hint::spin_loop();
+        }
+    }
+}
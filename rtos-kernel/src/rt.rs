@@ -0,0 +1,322 @@
+//! Periodic real-time tasks. A task releases a job every `period_ms`; each
+//! job must finish within `deadline_ms` of its release and may use
+//! `budget_us` of CPU time. `sched.rs` runs the jobs above every plain
+//! priority, ordered rate-monotonically (shorter period first) or EDF
+//! (earlier absolute deadline first), throttles jobs that overrun their
+//! budget and flags deadline misses.
+//!
+//! Admission control refuses a task unless every admitted task still meets
+//! its deadline: response-time analysis for RM, a density test
+//! (sum of budget / deadline at most 1) for EDF. All periodic tasks use the
+//! same class at a time. Blocking on mutexes held by lower-ranked threads
+//! is not part of the test, so keep critical sections short.
+
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::instructions::interrupts::without_interrupts;
+use crate::mutex::Mutex;
+use crate::sched::{self, JobInfo, SpawnError, Tid, MAX_THREADS};
+use crate::serial_writer::SerialWriter;
+use crate::{latency, timer};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Class {
+    RateMonotonic,
+    Edf,
+}
+
+impl Class {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Class::RateMonotonic => "rm",
+            Class::Edf => "edf",
+        }
+    }
+}
+
+/// A periodic task's timing. `deadline_ms` is relative to each release and
+/// at most `period_ms`; `budget_us` is the worst-case CPU time of one job.
+#[derive(Clone, Copy, Debug)]
+pub struct Periodic {
+    pub class: Class,
+    pub period_ms: u32,
+    pub deadline_ms: u32,
+    pub budget_us: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum AdmitError {
+    /// Zero period or budget, a deadline above the period, or a budget
+    /// longer than the deadline.
+    BadParams,
+    /// Other periodic tasks of the other class are running.
+    OtherClass,
+    /// Some task, this one or an admitted one, could miss its deadline.
+    Unschedulable,
+    Spawn(SpawnError),
+}
+
+impl fmt::Display for AdmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdmitError::BadParams => f.write_str("bad period, deadline or budget"),
+            AdmitError::OtherClass => f.write_str("periodic tasks of the other class are running"),
+            AdmitError::Unschedulable => f.write_str("deadlines could be missed"),
+            AdmitError::Spawn(e) => write!(f, "no thread: {:?}", e),
+        }
+    }
+}
+
+/// Admits a periodic task and starts it: `job(arg)` runs once per release,
+/// and the task ends when it returns false.
+pub fn spawn_periodic(name: &'static str, params: Periodic, job: fn(usize) -> bool, arg: usize) -> Result<Tid, AdmitError> {
+    let p = params;
+    if p.period_ms == 0 || p.budget_us == 0 || p.deadline_ms > p.period_ms || p.budget_us as u64 > p.deadline_ms as u64 * 1000 {
+        return Err(AdmitError::BadParams);
+    }
+    // Nothing else may spawn between the test and the spawn.
+    without_interrupts(|| {
+        let jobs = sched::jobs();
+        let mut set = [params; MAX_THREADS];
+        let mut len = 1;
+        for info in jobs.iter().flatten() {
+            if info.params.class != params.class {
+                return Err(AdmitError::OtherClass);
+            }
+            set[len] = info.params;
+            len += 1;
+        }
+        let ok = match params.class {
+            Class::RateMonotonic => rm_schedulable(&set[..len]),
+            Class::Edf => edf_schedulable(&set[..len]),
+        };
+        if !ok {
+            return Err(AdmitError::Unschedulable);
+        }
+        sched::spawn_job(name, params, job, arg).map_err(AdmitError::Spawn)
+    })
+}
+
+/// Response-time analysis: each task's worst response (its budget plus the
+/// interference of every task with an equal or shorter period) must fit its
+/// deadline. Counting equal periods both ways keeps ties safe.
+fn rm_schedulable(set: &[Periodic]) -> bool {
+    set.iter().enumerate().all(|(i, task)| {
+        let deadline = task.deadline_ms as u64 * 1000;
+        let mut response = task.budget_us as u64;
+        loop {
+            let next = task.budget_us as u64
+                + set
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, other)| j != i && other.period_ms <= task.period_ms)
+                    .map(|(_, other)| response.div_ceil(other.period_ms as u64 * 1000) * other.budget_us as u64)
+                    .sum::<u64>();
+            if next > deadline { return false; }
+            if next == response { return true; }
+            response = next;
+        }
+    })
+}
+
+/// Density test, in millionths: sum of budget / deadline at most 1. Exact
+/// when deadlines equal periods.
+fn edf_schedulable(set: &[Periodic]) -> bool {
+    let density: u64 = set
+        .iter()
+        .map(|task| (task.budget_us as u64 * 1_000_000).div_ceil(task.deadline_ms as u64 * 1000))
+        .sum();
+    density <= 1_000_000
+}
+
+/// Body of every periodic thread. It starts inside its first job (see
+/// `sched::spawn_job`).
+pub(crate) fn periodic_main(arg: usize) {
+    let Some(job) = sched::job_fn() else { return };
+    while job(arg) {
+        sched::next_release();
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Miss {
+    name: &'static str,
+    job: u64,
+}
+
+/// Deadline misses waiting for the monitor to print them: the scheduler
+/// logs them with interrupts off and never waits on the UART.
+struct MissLog {
+    entries: [Miss; 16],
+    len: usize,
+    dropped: u64,
+}
+
+struct Global<T>(UnsafeCell<T>);
+
+// SAFETY: only touched with interrupts off, on the BSP.
+unsafe impl<T> Sync for Global<T> {}
+
+static MISSES: Global<MissLog> = Global(UnsafeCell::new(MissLog {
+    entries: [Miss { name: "", job: 0 }; 16],
+    len: 0,
+    dropped: 0,
+}));
+
+/// Queues a deadline miss. Interrupts must be off.
+pub(crate) fn log_miss(name: &'static str, job: u64) {
+    // SAFETY: interrupts are off (called by the scheduler).
+    let log = unsafe { &mut *MISSES.0.get() };
+    if log.len == log.entries.len() {
+        log.dropped += 1;
+        return;
+    }
+    log.entries[log.len] = Miss { name, job };
+    log.len += 1;
+}
+
+/// Prints and clears the queued deadline misses.
+pub fn print_misses() {
+    let (entries, len, dropped) = without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let log = unsafe { &mut *MISSES.0.get() };
+        let taken = (log.entries, log.len, log.dropped);
+        log.len = 0;
+        log.dropped = 0;
+        taken
+    });
+    for miss in &entries[..len] {
+        let _ = writeln!(SerialWriter, "K: rt: {} missed the deadline of job {}", miss.name, miss.job);
+    }
+    if dropped != 0 {
+        let _ = writeln!(SerialWriter, "K: rt: {} more deadline misses not shown", dropped);
+    }
+}
+
+/// Prints every periodic task with its timing and counters (`rt` on the
+/// serial monitor).
+pub fn report() {
+    let jobs = sched::jobs();
+    if jobs.iter().all(Option::is_none) {
+        SerialWriter::write("K: rt: no periodic tasks\n");
+        return;
+    }
+    SerialWriter::write("K:   class period deadline budget(us)   jobs misses overruns worst(us) name\n");
+    for info in jobs.iter().flatten() {
+        let (p, st) = (info.params, info.stats);
+        let _ = writeln!(
+            SerialWriter,
+            "K:   {:<5} {:>6} {:>8} {:>10} {:>6} {:>6} {:>8} {:>9} {}",
+            p.class.as_str(), p.period_ms, p.deadline_ms, p.budget_us,
+            st.jobs, st.misses, st.overruns, st.worst_response_us, info.name,
+        );
+    }
+}
+
+/// `rttest` (`rttest=edf` for EDF): a synthetic task set for one second.
+///
+/// Four periodic tasks are admitted (utilisation 0.59). One of them overruns
+/// its budget on purpose. A fifth task that would overload the CPU must be
+/// refused. `rt-10ms` shares a mutex with `low` (priority 2), which holds it
+/// for 2 ms of CPU at a time. `hog` (priority 7) spins 8 ms at a time. Without
+/// priority inheritance it would keep `low`, and so `rt-10ms`, waiting.
+/// Passes if the three well-behaved tasks miss nothing, the overrunning one
+/// is throttled and its misses are detected, and no wait for the mutex
+/// lasts 4 ms or more.
+pub fn self_test(class: Class) {
+    const RUN_MS: u64 = 1000;
+    static END: AtomicU64 = AtomicU64::new(0);
+    static LOCK_WAIT_MAX: AtomicU64 = AtomicU64::new(0);
+    static SHARED: Mutex<u64> = Mutex::new(0);
+
+    fn running() -> bool {
+        timer::ticks() < END.load(Ordering::Relaxed)
+    }
+    /// Spins until this thread has used `us` more microseconds of CPU.
+    fn work(us: u64) {
+        let me = sched::current();
+        let start = sched::cpu_us(me).unwrap_or(0);
+        while sched::cpu_us(me).unwrap_or(0) - start < us {
+            core::hint::spin_loop();
+        }
+    }
+    fn fast(_: usize) -> bool {
+        work(600);
+        running()
+    }
+    fn locker(_: usize) -> bool {
+        work(800);
+        let asked = timer::tsc();
+        let mut shared = SHARED.lock();
+        LOCK_WAIT_MAX.fetch_max(timer::cycles_to_us(timer::tsc() - asked), Ordering::Relaxed);
+        *shared += 1;
+        work(200);
+        drop(shared);
+        running()
+    }
+    fn slow(_: usize) -> bool {
+        work(1500);
+        running()
+    }
+    fn overrun(_: usize) -> bool {
+        work(20_000);
+        running()
+    }
+    fn extra(_: usize) -> bool {
+        false
+    }
+    fn low(_: usize) {
+        while running() {
+            let mut shared = SHARED.lock();
+            *shared += 1;
+            work(2000);
+            drop(shared);
+            sched::sleep_ms(3);
+        }
+    }
+    fn hog(_: usize) {
+        while running() {
+            work(8000);
+            sched::sleep_ms(2);
+        }
+    }
+
+    END.store(timer::ticks() + timer::ms_to_ticks(RUN_MS), Ordering::Relaxed);
+    let task = |period_ms, budget_us| Periodic { class, period_ms, deadline_ms: period_ms, budget_us };
+    let threads = [sched::spawn("low", 2, low, 0), sched::spawn("hog", 7, hog, 0)];
+    let tasks = [
+        spawn_periodic("rt-5ms", task(5, 1000), fast, 0),
+        spawn_periodic("rt-10ms", task(10, 2000), locker, 0),
+        spawn_periodic("rt-20ms", task(20, 3000), slow, 0),
+        spawn_periodic("overrun", task(50, 2000), overrun, 0),
+    ];
+    for (name, task) in ["rt-5ms", "rt-10ms", "rt-20ms", "overrun"].iter().zip(&tasks) {
+        if let Err(e) = task {
+            let _ = writeln!(SerialWriter, "K: rttest: {} not admitted: {}", name, e);
+        }
+    }
+    let refused = matches!(spawn_periodic("extra", task(10, 5000), extra, 0), Err(AdmitError::Unschedulable));
+
+    // Take the numbers just before the tasks start ending.
+    sched::sleep_ms(RUN_MS - 2);
+    let jobs = sched::jobs();
+    report();
+    latency::report();
+    for tid in threads.iter().flatten().chain(tasks.iter().flatten()) {
+        sched::join(*tid);
+    }
+    print_misses();
+
+    let stats = |name: &str| jobs.iter().flatten().find(|i| i.name == name).map(|i: &JobInfo| i.stats);
+    let good_misses: u64 = ["rt-5ms", "rt-10ms", "rt-20ms"].iter().map(|n| stats(n).map_or(1, |s| s.misses)).sum();
+    let bad = stats("overrun").unwrap_or_default();
+    let wait = LOCK_WAIT_MAX.load(Ordering::Relaxed);
+    let ok = tasks.iter().all(|t| t.is_ok()) && refused && good_misses == 0 && bad.overruns > 0 && bad.misses > 0 && wait < 4000;
+    let _ = writeln!(
+        SerialWriter,
+        "K: rttest ({}): extra task {}, {} misses in admitted tasks, overrun task {} overruns/{} misses, lock wait max {} us: {}",
+        class.as_str(), if refused { "refused" } else { "NOT refused" }, good_misses,
+        bad.overruns, bad.misses, wait, if ok { "ok" } else { "FAIL" },
+    );
+}
@@ -0,0 +1,228 @@
+//! UEFI runtime services after ExitBootServices. The bootloader passes the
+//! table's address and the firmware's runtime ranges in `BootInfo`. The
+//! kernel still runs on the firmware's identity-mapped page tables, so the
+//! table and the code it points to are called at their physical addresses
+//! until `set_virtual_address_map` moves them.
+//!
+//! Runtime services are not reentrant, and kernel threads (and, for
+//! `reset` from the panic handler, other CPUs) may call them at any time.
+//! Every call runs with interrupts off while holding `BUSY`, so no two
+//! overlap.
+
+use core::fmt::Write;
+use core::mem::size_of;
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use x86_64::instructions::interrupts::{self, without_interrupts};
+use rtos_types::boot_info::BootInfo;
+use rtos_types::memory_map::RuntimeRegion;
+use uefi_raw::table::boot::MemoryDescriptor;
+use uefi_raw::table::runtime::{ResetType, RuntimeServices, VariableAttributes};
+use uefi_raw::time::Time;
+use uefi_raw::{Guid, Status};
+use crate::serial_writer::SerialWriter;
+
+static RT: AtomicPtr<RuntimeServices> = AtomicPtr::new(core::ptr::null_mut());
+static MAP_PTR: AtomicPtr<RuntimeRegion> = AtomicPtr::new(core::ptr::null_mut());
+static MAP_LEN: AtomicUsize = AtomicUsize::new(0);
+/// Set once SetVirtualAddressMap has been tried; the firmware allows one call.
+static VIRTUAL: AtomicBool = AtomicBool::new(false);
+/// Held for the length of every runtime call.
+static BUSY: AtomicBool = AtomicBool::new(false);
+/// How long `reset` waits for a call in progress before going ahead anyway:
+/// a panic inside a runtime call must still be able to reset.
+const RESET_SPINS: u32 = 10_000_000;
+
+/// The bootloader's table is handed to the firmware as its descriptors.
+const _: () = assert!(size_of::<RuntimeRegion>() == size_of::<MemoryDescriptor>());
+
+/// Longest variable name accepted, in characters.
+const MAX_NAME: usize = 64;
+
+/// How `reset` restarts the machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetKind {
+    /// Power cycle.
+    Cold,
+    /// Re-initialise without cutting power; firmware may do a cold reset.
+    Warm,
+    /// Power off.
+    Shutdown,
+}
+
+/// Records the runtime services table and ranges from `BootInfo`. Call
+/// once, early in `kmain`.
+///
+/// # Safety
+/// `bi.runtime_services` must be 0 or the address of the firmware's table,
+/// with the runtime ranges still mapped as the firmware left them. The
+/// range table must stay mapped for the life of the kernel (it lives in
+/// `Loader` memory) and is written by `set_virtual_address_map`.
+pub unsafe fn init(bi: &BootInfo) {
+    let m = bi.runtime_map();
+    MAP_PTR.store(m.as_ptr() as *mut RuntimeRegion, Ordering::Relaxed);
+    MAP_LEN.store(m.len(), Ordering::Relaxed);
+    RT.store(bi.runtime_services as *mut RuntimeServices, Ordering::Release);
+}
+
+fn table() -> Result<&'static RuntimeServices, Status> {
+    // SAFETY: set by `init` or `set_virtual_address_map`; the table lives
+    // in runtime memory.
+    unsafe { RT.load(Ordering::Acquire).as_ref() }.ok_or(Status::UNSUPPORTED)
+}
+
+/// The firmware's runtime ranges (empty before `init`).
+pub fn regions() -> &'static [RuntimeRegion] {
+    let len = MAP_LEN.load(Ordering::Acquire);
+    let ptr = MAP_PTR.load(Ordering::Relaxed);
+    if ptr.is_null() || len == 0 { return &[]; }
+    // SAFETY: set once by `init` from a table that lives for the kernel's lifetime.
+    unsafe { core::slice::from_raw_parts(ptr, len) }
+}
+
+/// `name` as NUL-terminated UCS-2; names are ASCII.
+fn ucs2(name: &str, buf: &mut [u16; MAX_NAME]) -> Result<(), Status> {
+    if !name.is_ascii() || name.len() >= MAX_NAME || name.contains('\0') {
+        return Err(Status::INVALID_PARAMETER);
+    }
+    for (d, b) in buf.iter_mut().zip(name.bytes()) {
+        *d = b as u16;
+    }
+    buf[name.len()] = 0;
+    Ok(())
+}
+
+fn try_lock() -> bool {
+    BUSY.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok()
+}
+
+/// Runs `f` with interrupts off and no other runtime call in progress on
+/// any CPU.
+fn serialized<R>(f: impl FnOnce() -> R) -> R {
+    without_interrupts(|| {
+        while !try_lock() { spin_loop(); }
+        let r = f();
+        BUSY.store(false, Ordering::Release);
+        r
+    })
+}
+
+fn check(status: Status) -> Result<(), Status> {
+    if status.is_error() { Err(status) } else { Ok(()) }
+}
+
+/// Reads the real-time clock.
+pub fn get_time() -> Result<Time, Status> {
+    let mut time = Time::invalid();
+    serialized(|| {
+        let rt = table()?;
+        // SAFETY: `time` is valid for the call; capabilities are optional.
+        check(unsafe { (rt.get_time)(&mut time, core::ptr::null_mut()) })
+    })?;
+    Ok(time)
+}
+
+/// Resets or powers off the machine through ResetSystem. Returns only when
+/// there are no runtime services, with `Status::UNSUPPORTED`. Interrupts
+/// stay off.
+pub fn reset(kind: ResetKind) -> Status {
+    interrupts::disable();
+    let Ok(rt) = table() else { return Status::UNSUPPORTED };
+    let ty = match kind {
+        ResetKind::Cold => ResetType::COLD,
+        ResetKind::Warm => ResetType::WARM,
+        ResetKind::Shutdown => ResetType::SHUTDOWN,
+    };
+    // The lock is never given back: nothing runs after a reset.
+    for _ in 0..RESET_SPINS {
+        if try_lock() { break; }
+        spin_loop();
+    }
+    // SAFETY: no reset data is passed.
+    unsafe { (rt.reset_system)(ty, Status::SUCCESS, 0, core::ptr::null()) }
+}
+
+/// Reads variable `name` of `vendor` into `buf`; returns the data size and
+/// attributes. A short buffer gives `Status::BUFFER_TOO_SMALL`.
+pub fn get_variable(name: &str, vendor: &Guid, buf: &mut [u8]) -> Result<(usize, VariableAttributes), Status> {
+    let mut n = [0u16; MAX_NAME];
+    ucs2(name, &mut n)?;
+    let mut attrs = VariableAttributes::empty();
+    let mut size = buf.len();
+    serialized(|| {
+        let rt = table()?;
+        // SAFETY: every pointer is valid for the call; `size` bounds the write.
+        check(unsafe { (rt.get_variable)(n.as_ptr(), vendor, &mut attrs, &mut size, buf.as_mut_ptr()) })
+    })?;
+    Ok((size, attrs))
+}
+
+/// Writes variable `name` of `vendor`. Only variables with
+/// `RUNTIME_ACCESS` can be written after ExitBootServices; empty `data`
+/// deletes the variable.
+pub fn set_variable(name: &str, vendor: &Guid, attrs: VariableAttributes, data: &[u8]) -> Result<(), Status> {
+    let mut n = [0u16; MAX_NAME];
+    ucs2(name, &mut n)?;
+    serialized(|| {
+        let rt = table()?;
+        // SAFETY: every pointer is valid for the call.
+        check(unsafe { (rt.set_variable)(n.as_ptr(), vendor, attrs, data.len(), data.as_ptr()) })
+    })
+}
+
+/// Moves the firmware to virtual addressing: every runtime range is placed
+/// at `phys + offset`, and later calls go through the moved table. An
+/// offset of 0 keeps the identity mapping the kernel runs on today. The
+/// firmware accepts this once per boot; a second call fails with
+/// `Status::UNSUPPORTED`.
+///
+/// # Safety
+/// Every range in `regions()` must be mapped at `phys + offset` in the
+/// page tables used for all later runtime calls, and the identity mapping
+/// must still be present during this call.
+pub unsafe fn set_virtual_address_map(offset: u64) -> Result<(), Status> {
+    serialized(|| {
+        let rt = table()?;
+        let ptr = MAP_PTR.load(Ordering::Acquire);
+        let len = MAP_LEN.load(Ordering::Acquire);
+        if ptr.is_null() || len == 0 { return Err(Status::NOT_FOUND); }
+        if VIRTUAL.swap(true, Ordering::AcqRel) { return Err(Status::UNSUPPORTED); }
+
+        let map = core::slice::from_raw_parts_mut(ptr, len);
+        for r in map.iter_mut() {
+            r.virt_start = r.phys_start + offset;
+        }
+        check((rt.set_virtual_address_map)(
+            len * size_of::<RuntimeRegion>(),
+            size_of::<RuntimeRegion>(),
+            RuntimeRegion::DESCRIPTOR_VERSION,
+            ptr as *mut MemoryDescriptor,
+        ))?;
+        // The table itself sits in a runtime range; later calls wait on
+        // `BUSY` until it is stored.
+        RT.store((rt as *const RuntimeServices as u64 + offset) as *mut RuntimeServices, Ordering::Release);
+        Ok(())
+    })
+}
+
+/// Writes the runtime services summary and the clock to the serial port.
+pub fn report() {
+    if table().is_err() {
+        SerialWriter::write("K: runtime services: none\n");
+        return;
+    }
+    let pages: u64 = regions().iter().map(|r| r.page_count).sum();
+    let _ = write!(SerialWriter, "K: runtime services: {} ranges, {} KiB", regions().len(), pages * 4);
+    match get_time() {
+        Ok(t) => {
+            let _ = writeln!(
+                SerialWriter,
+                ", RTC {:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                t.year, t.month, t.day, t.hour, t.minute, t.second,
+            );
+        }
+        Err(status) => {
+            let _ = writeln!(SerialWriter, ", RTC unavailable ({:?})", status);
+        }
+    }
+}
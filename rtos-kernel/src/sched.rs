@@ -0,0 +1,819 @@
+//! Kernel threads and the scheduler, on the BSP (the APs stay in
+//! `smp::run_on`'s work loop). Each thread has its own stack; `switch.asm`
+//! saves and restores its registers there.
+//!
+//! Scheduling is fixed-priority and preemptive: the highest-priority ready
+//! thread runs, and threads of equal priority take turns every
+//! `SLICE_TICKS` timer ticks. A thread that becomes ready with a higher
+//! priority than the running one takes over at once. The idle thread runs
+//! `sti; hlt` when nothing else is ready.
+//!
+//! Periodic jobs (`rt.rs`) rank above every plain priority: by period under
+//! rate-monotonic scheduling, by absolute deadline under EDF. A job that
+//! uses up its budget drops to `JOB_IDLE_PRIORITY` until its next release,
+//! so it cannot take time promised to the other admitted jobs. The owner of
+//! a mutex (`mutex.rs`) runs at the rank of its most urgent waiter
+//! (priority inheritance), along chains of owners blocked on other mutexes
+//! too.
+//!
+//! Scheduler state is only touched with interrupts off, which on a single
+//! CPU is all the locking it needs.
+
+use core::cell::UnsafeCell;
+use core::cmp::Reverse;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::instructions::interrupts::{self, without_interrupts};
+use crate::mutex::RawMutex;
+use crate::rt::{self, Class, Periodic};
+use crate::serial_writer::SerialWriter;
+use crate::{idt, latency, percpu, pit, timer};
+
+pub const MAX_THREADS: usize = 32;
+/// Higher numbers run first. `IDLE_PRIORITY` belongs to the idle thread.
+pub const IDLE_PRIORITY: u8 = 0;
+pub const MAX_PRIORITY: u8 = 31;
+/// `kmain`'s priority once it becomes a thread.
+pub const MAIN_PRIORITY: u8 = 8;
+/// A periodic thread's priority between jobs and while over budget.
+pub const JOB_IDLE_PRIORITY: u8 = 1;
+/// Ticks a thread runs before an equal-priority thread gets a turn.
+const SLICE_TICKS: u32 = 10;
+const STACK_SIZE: usize = 16 * 1024;
+
+extern "C" {
+    fn context_switch(save: *mut u64, load: u64);
+    fn thread_start();
+}
+
+/// A thread's slot and that slot's generation, so a stale `Tid` never names
+/// a later thread in the same slot.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Tid {
+    index: u16,
+    gen: u16,
+}
+
+impl fmt::Display for Tid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.index)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SpawnError {
+    /// All `MAX_THREADS` slots are in use.
+    NoSlot,
+    /// `IDLE_PRIORITY` or above `MAX_PRIORITY`.
+    BadPriority,
+    /// `init` found no working timer tick, so threads never run.
+    NoTick,
+}
+
+/// What ready threads are ordered by; the greatest runs. Any job outranks
+/// every plain priority.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Rank {
+    Priority(u8),
+    /// Period (RM) or absolute deadline (EDF) in ticks: smaller is more urgent.
+    Job(Reverse<u64>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Free,
+    Ready,
+    Running,
+    /// Until this tick.
+    Sleeping(u64),
+    /// Until this thread exits.
+    Joining(Tid),
+    /// A periodic thread, until its next release at this tick.
+    Released(u64),
+    /// Until the owner hands this mutex over.
+    Locking(*const RawMutex),
+}
+
+impl State {
+    fn as_str(self) -> &'static str {
+        match self {
+            State::Free => "free",
+            State::Ready => "ready",
+            State::Running => "running",
+            State::Sleeping(_) => "sleeping",
+            State::Joining(_) => "joining",
+            State::Released(_) => "waiting",
+            State::Locking(_) => "locking",
+        }
+    }
+}
+
+/// Per-task counters for `rt::report`.
+#[derive(Clone, Copy, Default)]
+pub struct JobStats {
+    pub jobs: u64,
+    pub misses: u64,
+    pub overruns: u64,
+    pub worst_response_us: u64,
+}
+
+/// A periodic thread's job state.
+#[derive(Clone, Copy)]
+struct Job {
+    params: Periodic,
+    run: fn(usize) -> bool,
+    /// Release tick of the current job, or of the next while none is active.
+    release: u64,
+    deadline: u64,
+    /// TSC at the tick that released the current job.
+    release_tsc: u64,
+    /// The thread's CPU cycles when the current job was released.
+    cpu_start: u64,
+    active: bool,
+    /// Over budget: runs at `JOB_IDLE_PRIORITY` until the next release.
+    throttled: bool,
+    missed: bool,
+    stats: JobStats,
+}
+
+impl Job {
+    fn new(params: Periodic, run: fn(usize) -> bool, release: u64) -> Job {
+        Job {
+            params,
+            run,
+            release,
+            deadline: release + timer::ms_to_ticks(params.deadline_ms as u64),
+            release_tsc: 0,
+            cpu_start: 0,
+            active: false,
+            throttled: false,
+            missed: false,
+            stats: JobStats::default(),
+        }
+    }
+
+    fn start(&mut self, tsc: u64, cpu: u64) {
+        self.active = true;
+        self.throttled = false;
+        self.missed = false;
+        self.release_tsc = tsc;
+        self.cpu_start = cpu;
+        self.stats.jobs += 1;
+    }
+
+    /// Ends the current job and moves on to the next release. Returns true
+    /// if the job turns out to have missed its deadline just now.
+    fn finish(&mut self) -> bool {
+        let response = timer::cycles_to_us(timer::tsc() - self.release_tsc);
+        self.stats.worst_response_us = self.stats.worst_response_us.max(response);
+        let missed = response > self.params.deadline_ms as u64 * 1000 && self.miss();
+        self.active = false;
+        self.release += timer::ms_to_ticks(self.params.period_ms as u64);
+        self.deadline = self.release + timer::ms_to_ticks(self.params.deadline_ms as u64);
+        missed
+    }
+
+    /// Counts a deadline miss, once per job. Returns true the first time.
+    fn miss(&mut self) -> bool {
+        if self.missed { return false; }
+        self.missed = true;
+        self.stats.misses += 1;
+        true
+    }
+}
+
+/// A periodic thread as seen by `rt` and `latency`.
+#[derive(Clone, Copy)]
+pub struct JobInfo {
+    pub index: usize,
+    pub name: &'static str,
+    pub params: Periodic,
+    pub stats: JobStats,
+}
+
+#[derive(Clone, Copy)]
+struct Thread {
+    name: &'static str,
+    state: State,
+    priority: u8,
+    gen: u16,
+    entry: Option<fn(usize)>,
+    arg: usize,
+    /// Saved stack pointer while the thread is not running.
+    rsp: u64,
+    /// Ready threads of equal priority run in this order.
+    ready_seq: u64,
+    cpu_cycles: u64,
+    switches: u64,
+    /// Rank lent by the waiters of mutexes this thread holds.
+    inherited: Option<Rank>,
+    /// Mutexes this thread holds.
+    held: u32,
+    job: Option<Job>,
+}
+
+impl Thread {
+    const FREE: Thread = Thread {
+        name: "",
+        state: State::Free,
+        priority: 0,
+        gen: 0,
+        entry: None,
+        arg: 0,
+        rsp: 0,
+        ready_seq: 0,
+        cpu_cycles: 0,
+        switches: 0,
+        inherited: None,
+        held: 0,
+        job: None,
+    };
+
+    fn base_rank(&self) -> Rank {
+        match &self.job {
+            Some(job) if job.active && !job.throttled => Rank::Job(Reverse(match job.params.class {
+                Class::RateMonotonic => job.params.period_ms as u64,
+                Class::Edf => job.deadline,
+            })),
+            _ => Rank::Priority(self.priority),
+        }
+    }
+
+    fn rank(&self) -> Rank {
+        let base = self.base_rank();
+        self.inherited.map_or(base, |r| r.max(base))
+    }
+}
+
+struct Sched {
+    threads: [Thread; MAX_THREADS],
+    current: usize,
+    started: bool,
+    seq: u64,
+    slice_left: u32,
+    /// TSC when `current` was switched in.
+    since: u64,
+}
+
+struct Global<T>(UnsafeCell<T>);
+
+// SAFETY: only touched on the BSP with interrupts off.
+unsafe impl<T> Sync for Global<T> {}
+
+#[repr(C, align(16))]
+struct Stack([u8; STACK_SIZE]);
+
+static SCHED: Global<Sched> = Global(UnsafeCell::new(Sched {
+    threads: [Thread::FREE; MAX_THREADS],
+    current: 0,
+    started: false,
+    seq: 0,
+    slice_left: SLICE_TICKS,
+    since: 0,
+}));
+// Slot 0 is `kmain`, which keeps the bootloader's stack until it exits.
+static STACKS: Global<[Stack; MAX_THREADS]> = Global(UnsafeCell::new([const { Stack([0; STACK_SIZE]) }; MAX_THREADS]));
+
+/// The scheduler state. Interrupts must be off, and the reference must not
+/// be used after a context switch.
+unsafe fn sched() -> &'static mut Sched {
+    &mut *SCHED.0.get()
+}
+
+impl Sched {
+    fn tid(&self, index: usize) -> Tid {
+        Tid { index: index as u16, gen: self.threads[index].gen }
+    }
+
+    fn alive(&self, tid: Tid) -> bool {
+        let t = &self.threads[tid.index as usize];
+        t.gen == tid.gen && t.state != State::Free
+    }
+
+    fn make_ready(&mut self, index: usize) {
+        self.seq += 1;
+        let t = &mut self.threads[index];
+        t.state = State::Ready;
+        t.ready_seq = self.seq;
+    }
+
+    /// Readies every thread whose state matches.
+    fn wake(&mut self, blocked: impl Fn(State) -> bool) {
+        for index in 0..MAX_THREADS {
+            if blocked(self.threads[index].state) {
+                self.make_ready(index);
+            }
+        }
+    }
+
+    fn spawn(&mut self, name: &'static str, priority: u8, entry: fn(usize), arg: usize) -> Result<Tid, SpawnError> {
+        if !self.started { return Err(SpawnError::NoTick); }
+        let index = self.threads.iter().position(|t| t.state == State::Free).ok_or(SpawnError::NoSlot)?;
+        // SAFETY: the slot is free, so nothing runs on its stack.
+        let top = unsafe { (*STACKS.0.get())[index].0.as_mut_ptr() } as u64 + STACK_SIZE as u64;
+        // Popped by `context_switch`: r15, r14, r13, r12, rbx, rbp, then RET.
+        let frame = [0, 0, index as u64, thread_main as usize as u64, 0, 0, thread_start as usize as u64];
+        let rsp = top - 8 * frame.len() as u64;
+        // SAFETY: `rsp..top` is the top of this slot's stack.
+        unsafe { core::ptr::copy_nonoverlapping(frame.as_ptr(), rsp as *mut u64, frame.len()) };
+        self.threads[index] = Thread {
+            name,
+            priority,
+            gen: self.threads[index].gen.wrapping_add(1),
+            entry: Some(entry),
+            arg,
+            rsp,
+            ..Thread::FREE
+        };
+        self.make_ready(index);
+        Ok(self.tid(index))
+    }
+
+    /// The ready thread to run instead of the current one, if any should.
+    /// A blocked current thread always gives way; a running one only to a
+    /// higher rank, or with `rotate` to an equal one.
+    fn pick(&self, rotate: bool) -> Option<usize> {
+        let (best, t) = self
+            .threads
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.state == State::Ready)
+            .max_by(|(_, a), (_, b)| a.rank().cmp(&b.rank()).then(b.ready_seq.cmp(&a.ready_seq)))?;
+        let cur = &self.threads[self.current];
+        if cur.state == State::Running && (t.rank() < cur.rank() || (t.rank() == cur.rank() && !rotate)) {
+            return None;
+        }
+        Some(best)
+    }
+
+    /// Recomputes what `owner` inherits from the waiters of the mutexes it
+    /// holds.
+    fn refresh(&mut self, owner: usize) {
+        let tid = self.tid(owner);
+        let inherited = self
+            .threads
+            .iter()
+            .filter(|t| match t.state {
+                // SAFETY: a waiter's mutex outlives its `lock` call.
+                State::Locking(m) => (unsafe { (*m).owner.get() }) == Some(tid),
+                _ => false,
+            })
+            .map(Thread::rank)
+            .max();
+        self.threads[owner].inherited = inherited;
+    }
+
+    /// After a thread starts waiting on `m`: lends its rank to the owner,
+    /// and on along the chain while owners wait on mutexes themselves.
+    fn propagate(&mut self, mut m: *const RawMutex) {
+        // A longer chain would be a deadlock cycle.
+        for _ in 0..MAX_THREADS {
+            // SAFETY: `m` is held or waited on, so it is alive.
+            let Some(owner) = (unsafe { (*m).owner.get() }) else { return };
+            let owner = owner.index as usize;
+            self.refresh(owner);
+            match self.threads[owner].state {
+                State::Locking(next) => m = next,
+                _ => return,
+            }
+        }
+    }
+
+    /// Releases jobs whose tick has come and flags active jobs past their
+    /// deadline.
+    fn release_jobs(&mut self, now: u64, tsc: u64) {
+        for index in 0..MAX_THREADS {
+            let t = &mut self.threads[index];
+            let (state, cpu, name) = (t.state, t.cpu_cycles, t.name);
+            let Some(job) = t.job.as_mut() else { continue };
+            if matches!(state, State::Released(at) if at <= now) {
+                job.start(tsc, cpu);
+                self.make_ready(index);
+            } else if job.active && now >= job.deadline && job.miss() {
+                rt::log_miss(name, job.stats.jobs);
+            }
+        }
+    }
+
+    /// Throttles the running job once it has used its budget.
+    fn charge_budget(&mut self) {
+        let cur = self.current;
+        let used = self.cpu_cycles(cur);
+        if let Some(job) = self.threads[cur].job.as_mut() {
+            if job.active && !job.throttled && used - job.cpu_start > timer::us_to_cycles(job.params.budget_us as u64) {
+                job.throttled = true;
+                job.stats.overruns += 1;
+            }
+        }
+    }
+
+    /// Charges the outgoing thread its CPU time and makes `next` current.
+    /// A running thread that is rotated out goes behind its equals; one
+    /// preempted by a higher rank keeps its place and runs first when that
+    /// rank is gone. Returns the `context_switch` arguments.
+    fn switch_to(&mut self, next: usize, rotate: bool) -> (*mut u64, u64) {
+        let now = timer::tsc();
+        let prev = self.current;
+        self.threads[prev].cpu_cycles += now - self.since;
+        if self.threads[prev].state == State::Running {
+            if rotate {
+                self.make_ready(prev);
+            } else {
+                self.threads[prev].state = State::Ready;
+            }
+        }
+        self.since = now;
+        self.slice_left = SLICE_TICKS;
+        self.current = next;
+        let n = &mut self.threads[next];
+        n.state = State::Running;
+        n.switches += 1;
+        (&mut self.threads[prev].rsp as *mut u64, self.threads[next].rsp)
+    }
+
+    /// CPU time of slot `index` so far, including the running slice.
+    fn cpu_cycles(&self, index: usize) -> u64 {
+        let t = &self.threads[index];
+        if index == self.current { t.cpu_cycles + (timer::tsc() - self.since) } else { t.cpu_cycles }
+    }
+}
+
+/// Switches to another thread if `Sched::pick` says so.
+///
+/// # Safety
+/// Interrupts must be off.
+unsafe fn reschedule(rotate: bool) {
+    let s = sched();
+    if !s.started { return; }
+    let Some(next) = s.pick(rotate) else { return };
+    let (save, load) = s.switch_to(next, rotate);
+    context_switch(save, load);
+}
+
+/// Turns `kmain` into thread 0 at `MAIN_PRIORITY`, starts the timer tick,
+/// creates the idle thread and enables interrupts. Without a tick the
+/// scheduler stays off: `kmain` runs alone, sleeps busy-wait on the PIT and
+/// `spawn` fails with `SpawnError::NoTick`.
+pub fn init() {
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        s.threads[0] = Thread { name: "kmain", state: State::Running, priority: MAIN_PRIORITY, gen: 1, ..Thread::FREE };
+        s.current = 0;
+        s.since = timer::tsc();
+        // SAFETY: the IDT's timer handler calls `tick`.
+        if !unsafe { timer::start(idt::TIMER_VECTOR) } {
+            SerialWriter::write("K: sched: no timer tick; threads disabled\n");
+            return;
+        }
+        s.started = true;
+        let _ = s.spawn("idle", IDLE_PRIORITY, idle, 0);
+    });
+    interrupts::enable();
+}
+
+/// Starts `entry(arg)` in a new thread, which runs as soon as it is the
+/// highest-priority ready thread (possibly before this returns).
+pub fn spawn(name: &'static str, priority: u8, entry: fn(usize), arg: usize) -> Result<Tid, SpawnError> {
+    if priority == IDLE_PRIORITY || priority > MAX_PRIORITY {
+        return Err(SpawnError::BadPriority);
+    }
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let tid = unsafe { sched() }.spawn(name, priority, entry, arg)?;
+        // SAFETY: as above.
+        unsafe { reschedule(false) };
+        Ok(tid)
+    })
+}
+
+/// The calling thread.
+pub fn current() -> Tid {
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        s.tid(s.current)
+    })
+}
+
+/// Lets other ready threads of the same priority run first.
+pub fn yield_now() {
+    // SAFETY: interrupts are off.
+    without_interrupts(|| unsafe { reschedule(true) });
+}
+
+/// Blocks for `ticks` timer ticks (the current, partial tick included);
+/// 0 just yields. Before `init`, or without a tick, it busy-waits instead.
+pub fn sleep_ticks(ticks: u64) {
+    if ticks == 0 {
+        yield_now();
+        return;
+    }
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        if !s.started {
+            pit::delay_us(ticks * 1_000_000 / timer::TICK_HZ);
+            return;
+        }
+        let cur = s.current;
+        s.threads[cur].state = State::Sleeping(timer::ticks() + ticks);
+        // SAFETY: as above.
+        unsafe { reschedule(false) };
+    });
+}
+
+/// Blocks for at least `ms` milliseconds, give or take a tick.
+pub fn sleep_ms(ms: u64) {
+    sleep_ticks(timer::ms_to_ticks(ms).max(1));
+}
+
+/// Waits until `tid` has exited. Returns false if `tid` is the caller.
+pub fn join(tid: Tid) -> bool {
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        if s.tid(s.current) == tid { return false; }
+        if s.alive(tid) {
+            let cur = s.current;
+            s.threads[cur].state = State::Joining(tid);
+            // SAFETY: as above.
+            unsafe { reschedule(false) };
+        }
+        true
+    })
+}
+
+/// Ends the calling thread and wakes the threads joining it. Panics if the
+/// thread still holds a mutex, which nothing could ever unlock.
+pub fn exit() -> ! {
+    interrupts::disable();
+    // SAFETY: interrupts are off. The slot is free once this switches away,
+    // and the switch is the last use of its stack.
+    unsafe {
+        let s = sched();
+        let t = &s.threads[s.current];
+        if t.held != 0 {
+            panic!("sched: {} exited holding {} mutex(es)", t.name, t.held);
+        }
+        if !s.started {
+            // No other thread exists to switch to.
+            idle(0);
+        }
+        let me = s.tid(s.current);
+        s.wake(|state| state == State::Joining(me));
+        let cur = s.current;
+        s.threads[cur].state = State::Free;
+        reschedule(false);
+    }
+    unreachable!("sched: exited thread resumed");
+}
+
+/// CPU time `tid` has used, in microseconds; `None` once it has exited.
+pub fn cpu_us(tid: Tid) -> Option<u64> {
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        s.alive(tid).then(|| timer::cycles_to_us(s.cpu_cycles(tid.index as usize)))
+    })
+}
+
+/// Whether this is the BSP, the only CPU scheduler state may be touched
+/// from. True before per-CPU blocks are set up, when only the BSP runs.
+fn on_bsp() -> bool {
+    percpu::current().is_none_or(|cpu| cpu.index.load(Ordering::Relaxed) == 0)
+}
+
+/// Takes `m` for the calling thread. While another thread holds it, waits
+/// and lends the owner this thread's rank.
+pub(crate) fn lock(m: &RawMutex) {
+    debug_assert!(on_bsp(), "sched: mutex locked off the BSP");
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        let me = s.current;
+        match m.owner.get() {
+            None => {
+                m.owner.set(Some(s.tid(me)));
+                s.threads[me].held += 1;
+            }
+            Some(owner) if owner == s.tid(me) => panic!("sched: {} locked a mutex it holds", s.threads[me].name),
+            Some(_) => {
+                s.seq += 1;
+                s.threads[me].ready_seq = s.seq;
+                s.threads[me].state = State::Locking(m);
+                s.propagate(m);
+                // SAFETY: as above. `unlock` hands `m` over before waking us.
+                unsafe { reschedule(false) };
+            }
+        }
+    });
+}
+
+/// Hands `m` to its most urgent waiter (the longest-waiting among equals)
+/// and drops the rank the calling thread inherited through it.
+pub(crate) fn unlock(m: &RawMutex) {
+    debug_assert!(on_bsp(), "sched: mutex unlocked off the BSP");
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        let me = s.current;
+        let waiting = State::Locking(m);
+        let next = s
+            .threads
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.state == waiting)
+            .max_by(|(_, a), (_, b)| a.rank().cmp(&b.rank()).then(b.ready_seq.cmp(&a.ready_seq)))
+            .map(|(index, _)| index);
+        m.owner.set(next.map(|index| s.tid(index)));
+        s.threads[me].held -= 1;
+        if let Some(next) = next {
+            s.threads[next].held += 1;
+            s.make_ready(next);
+            s.refresh(next);
+        }
+        s.refresh(me);
+        // SAFETY: as above.
+        unsafe { reschedule(false) };
+    });
+}
+
+/// Creates a periodic thread running `rt::periodic_main`. It first runs
+/// when its first job is released, at the next tick. Admission is `rt`'s
+/// job.
+pub(crate) fn spawn_job(name: &'static str, params: Periodic, run: fn(usize) -> bool, arg: usize) -> Result<Tid, SpawnError> {
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        let tid = s.spawn(name, JOB_IDLE_PRIORITY, rt::periodic_main, arg)?;
+        let release = timer::ticks() + 1;
+        let t = &mut s.threads[tid.index as usize];
+        t.job = Some(Job::new(params, run, release));
+        t.state = State::Released(release);
+        latency::clear(tid.index as usize);
+        Ok(tid)
+    })
+}
+
+/// The calling thread's job function, if it is periodic.
+pub(crate) fn job_fn() -> Option<fn(usize) -> bool> {
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        s.threads[s.current].job.map(|job| job.run)
+    })
+}
+
+/// Ends the calling periodic thread's current job and blocks until the
+/// next release. Records the time from the releasing tick to this thread
+/// running in `latency`. A release that is already due
+/// (the last job ran late) starts at once and is not sampled.
+pub(crate) fn next_release() {
+    let sample = without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        let cur = s.current;
+        let cpu = s.cpu_cycles(cur);
+        let t = &mut s.threads[cur];
+        let name = t.name;
+        let job = t.job.as_mut()?;
+        if job.active && job.finish() {
+            rt::log_miss(name, job.stats.jobs);
+        }
+        if job.release <= timer::ticks() {
+            job.start(timer::tsc(), cpu);
+            return None;
+        }
+        t.state = State::Released(job.release);
+        // SAFETY: as above.
+        unsafe { reschedule(false) };
+        // SAFETY: as above; this is a new borrow after the switch.
+        let s = unsafe { sched() };
+        s.threads[s.current].job.map(|job| timer::tsc() - job.release_tsc)
+    });
+    if let Some(cycles) = sample {
+        latency::record(current().index as usize, cycles);
+    }
+}
+
+/// Every live periodic thread.
+pub(crate) fn jobs() -> [Option<JobInfo>; MAX_THREADS] {
+    without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        core::array::from_fn(|index| {
+            let t = &s.threads[index];
+            match (t.state, t.job) {
+                (State::Free, _) | (_, None) => None,
+                (_, Some(job)) => Some(JobInfo { index, name: t.name, params: job.params, stats: job.stats }),
+            }
+        })
+    })
+}
+
+/// Timer interrupt: releases jobs and wakes sleepers whose tick has come,
+/// enforces job budgets, then lets a higher-ranked thread in, or an equal
+/// one when the slice is used up.
+pub fn tick() {
+    let tsc = timer::tsc();
+    let now = timer::advance();
+    // SAFETY: called from the timer interrupt, so interrupts are off.
+    unsafe {
+        let s = sched();
+        if !s.started { return; }
+        s.release_jobs(now, tsc);
+        s.wake(|state| matches!(state, State::Sleeping(until) if until <= now));
+        s.charge_budget();
+        s.slice_left = s.slice_left.saturating_sub(1);
+        let rotate = s.slice_left == 0;
+        if rotate {
+            s.slice_left = SLICE_TICKS;
+        }
+        reschedule(rotate);
+    }
+}
+
+/// Prints every thread with its priority, state and CPU time (`ps` on the
+/// serial monitor).
+pub fn report() {
+    let (threads, cycles, now) = without_interrupts(|| {
+        // SAFETY: interrupts are off.
+        let s = unsafe { sched() };
+        let mut cycles = [0u64; MAX_THREADS];
+        for (index, c) in cycles.iter_mut().enumerate() {
+            *c = s.cpu_cycles(index);
+        }
+        (s.threads, cycles, timer::ticks())
+    });
+    let _ = writeln!(SerialWriter, "K: threads at tick {}:", now);
+    let _ = writeln!(SerialWriter, "K:   tid prio state       cpu(us)  switches name");
+    for (index, t) in threads.iter().enumerate().filter(|(_, t)| t.state != State::Free) {
+        // Jobs, and owners lent a job's rank, show as `rt`.
+        let _ = match t.rank() {
+            Rank::Priority(p) => write!(SerialWriter, "K:   {:>3} {:>4}", index, p),
+            Rank::Job(_) => write!(SerialWriter, "K:   {:>3}   rt", index),
+        };
+        let _ = writeln!(
+            SerialWriter,
+            " {:<8} {:>10} {:>9} {}",
+            t.state.as_str(), timer::cycles_to_us(cycles[index]), t.switches, t.name,
+        );
+    }
+}
+
+/// `schedtest`: two CPU-bound threads share a priority for 200 ms while a
+/// higher-priority one wakes from 10 ms sleeps. Passes if each spinner got at
+/// least 60 ms of CPU and the sleeper never woke more than a tick late.
+pub fn self_test() {
+    static SPIN_US: [AtomicU64; 2] = [const { AtomicU64::new(0) }; 2];
+    static LATE_MAX: AtomicU64 = AtomicU64::new(0);
+
+    fn spin(i: usize) {
+        let end = timer::ticks() + timer::ms_to_ticks(200);
+        while timer::ticks() < end {
+            core::hint::spin_loop();
+        }
+        SPIN_US[i].store(cpu_us(current()).unwrap_or(0), Ordering::Relaxed);
+    }
+    fn sleeper(_: usize) {
+        for _ in 0..10 {
+            let due = timer::ticks() + timer::ms_to_ticks(10);
+            sleep_ms(10);
+            LATE_MAX.fetch_max(timer::ticks().saturating_sub(due), Ordering::Relaxed);
+        }
+    }
+
+    let tids = [spawn("spin-a", 4, spin, 0), spawn("spin-b", 4, spin, 1), spawn("sleeper", 16, sleeper, 0)];
+    for tid in tids.iter().flatten() {
+        join(*tid);
+    }
+    let (a, b) = (SPIN_US[0].load(Ordering::Relaxed) / 1000, SPIN_US[1].load(Ordering::Relaxed) / 1000);
+    let late = LATE_MAX.load(Ordering::Relaxed);
+    let ok = tids.iter().all(|t| t.is_ok()) && a >= 60 && b >= 60 && late <= 1;
+    let _ = writeln!(
+        SerialWriter,
+        "K: schedtest: spin-a {} ms, spin-b {} ms, sleeper at most {} ticks late: {}",
+        a, b, late, if ok { "ok" } else { "FAIL" },
+    );
+}
+
+/// First code of every spawned thread, reached through `thread_start`.
+extern "C" fn thread_main(index: usize) -> ! {
+    // SAFETY: interrupts are still off from the switch that got here.
+    let (entry, arg) = unsafe {
+        let t = &sched().threads[index];
+        (t.entry, t.arg)
+    };
+    interrupts::enable();
+    if let Some(entry) = entry {
+        entry(arg);
+    }
+    exit()
+}
+
+fn idle(_: usize) {
+    loop {
+        x64_utils::interrupts::sti_hlt();
+    }
+}
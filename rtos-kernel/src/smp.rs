@@ -0,0 +1,272 @@
+//! SMP bring-up. The MADT lists the processors. Each application processor
+//! is started with INIT-SIPI-SIPI into `ap_trampoline.asm`, copied to a free
+//! page below 1 MiB, and arrives in `ap_entry` on its own stack. There it
+//! loads the kernel's page tables, its own GDT/TSS, the IDT and GS base,
+//! and then idles in `sti; hlt` until `run_on` hands it work.
+//!
+//! APs are started one at a time and share the trampoline's parameter
+//! block; if one does not come up in time, bring-up stops there.
+
+use core::cell::UnsafeCell;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU64, Ordering};
+use rtos_types::memory_map::MemoryKind;
+use x64_utils::{control, interrupts, msr};
+use crate::cmdline::Cmdline;
+use crate::percpu::{self, MAX_CPUS};
+use crate::serial_writer::SerialWriter;
+use crate::{acpi, apic, idt, memmap, pit};
+
+const AP_STACK_SIZE: usize = 16 * 1024;
+const PAGE: u64 = 4096;
+/// SIPI vectors address pages below 1 MiB; page 0 holds the real-mode IVT.
+const LOW_LIMIT: u64 = 0x10_0000;
+/// CR4.LA57: five-level paging can't be entered from the trampoline.
+const CR4_LA57: u64 = 1 << 12;
+
+/// How long an AP gets to report in after its SIPIs.
+const START_TIMEOUT_US: u64 = 100_000;
+const POLL_US: u64 = 1_000;
+
+extern "C" {
+    static ap_trampoline_start: u8;
+    static ap_trampoline_params: u8;
+    static ap_trampoline_end: u8;
+}
+
+/// `ap_trampoline_params` in `ap_trampoline.asm`.
+#[repr(C)]
+struct TrampolineParams {
+    boot_cr3: u32,
+    efer: u32,
+    cr0: u64,
+    cr4: u64,
+    stack: u64,
+    entry: u64,
+    arg: u64,
+}
+
+/// Identity map of the first 4 GiB in 2 MiB pages: the trampoline can only
+/// load a 32-bit CR3 before long mode is active.
+#[repr(C, align(4096))]
+struct LowMap {
+    pml4: [u64; 512],
+    pdpt: [u64; 512],
+    pd: [[u64; 512]; 4],
+}
+
+struct Shared<T>(UnsafeCell<T>);
+
+// SAFETY: written by the BSP before any AP is started, then read only.
+unsafe impl<T> Sync for Shared<T> {}
+
+#[repr(C, align(16))]
+struct Stack([u8; AP_STACK_SIZE]);
+
+static LOW_MAP: Shared<LowMap> = Shared(UnsafeCell::new(LowMap { pml4: [0; 512], pdpt: [0; 512], pd: [[0; 512]; 4] }));
+// Index 0 is the BSP, which keeps the bootloader's stack.
+static AP_STACKS: Shared<[Stack; MAX_CPUS]> = Shared(UnsafeCell::new([const { Stack([0; AP_STACK_SIZE]) }; MAX_CPUS]));
+/// The kernel's page tables, loaded by each AP in `ap_entry`.
+static KERNEL_CR3: AtomicU64 = AtomicU64::new(0);
+
+/// Sets up the BSP as CPU 0: APIC, GDT/TSS, IDT and GS base. Call early in
+/// `kmain`, with interrupts off.
+pub fn init_bsp() {
+    apic::init();
+    // SAFETY: interrupts are off; nothing relies on the firmware's GDT. The
+    // IDT loaded by `init_cpu` handles the spurious vector.
+    unsafe {
+        percpu::init_cpu(0, apic::id());
+        apic::enable(idt::SPURIOUS_VECTOR);
+    }
+    percpu::cpu(0).online.store(true, Ordering::Release);
+}
+
+/// Starts every enabled processor in the MADT (`nosmp` skips this) and has
+/// each one report in. Returns the number of CPUs online, the BSP included.
+pub fn start_aps(args: &Cmdline) -> usize {
+    if args.has("nosmp") {
+        SerialWriter::write("K: smp: disabled (nosmp)\n");
+        return 1;
+    }
+    let Some(madt) = acpi::madt() else {
+        SerialWriter::write("K: smp: no MADT; BSP only\n");
+        return 1;
+    };
+    let Some(page) = trampoline_page() else {
+        SerialWriter::write("K: smp: no free page below 1 MiB; BSP only\n");
+        return 1;
+    };
+    if control::read_cr4() & CR4_LA57 != 0 || !below_4g() {
+        SerialWriter::write("K: smp: paging layout not supported by the trampoline; BSP only\n");
+        return 1;
+    }
+    // SAFETY: the page is usable RAM nobody else has claimed; APs are not
+    // running yet.
+    let params = unsafe { install_trampoline(page) };
+
+    let bsp = apic::id();
+    let mut listed = 0;
+    let mut online = 1;
+    for apic_id in madt.apic_ids() {
+        listed += 1;
+        if apic_id == bsp { continue; }
+        if online == MAX_CPUS {
+            let _ = writeln!(SerialWriter, "K: smp: more than {} CPUs; the rest stay off", MAX_CPUS);
+            break;
+        }
+        // SAFETY: `online` indexes an unused PerCpu block and stack.
+        if unsafe { start_one(params, page, online, apic_id) } {
+            online += 1;
+        } else {
+            let _ = writeln!(SerialWriter, "K: smp: CPU with APIC id {} did not start; stopping", apic_id);
+            break;
+        }
+    }
+    let _ = writeln!(
+        SerialWriter,
+        "K: smp: {} CPUs online ({} in MADT, {})",
+        online, listed, if apic::is_x2apic() { "x2APIC" } else { "xAPIC" },
+    );
+
+    for index in 1..online {
+        if run_on(index, report_in) {
+            wait_idle(index);
+        }
+    }
+    online
+}
+
+/// Hands `f` to idle CPU `index`, which calls it with its own index.
+/// Returns false if that CPU is offline or still busy.
+pub fn run_on(index: usize, f: fn(usize)) -> bool {
+    let cpu = percpu::cpu(index);
+    if index == 0 || !cpu.online.load(Ordering::Acquire) { return false; }
+    if cpu.work.compare_exchange(0, f as usize, Ordering::AcqRel, Ordering::Acquire).is_err() {
+        return false;
+    }
+    // SAFETY: every online AP's IDT handles WAKE_VECTOR.
+    unsafe { apic::send_ipi(cpu.apic_id.load(Ordering::Relaxed), idt::WAKE_VECTOR) };
+    true
+}
+
+/// Waits until CPU `index` has finished what `run_on` gave it.
+pub fn wait_idle(index: usize) {
+    while percpu::cpu(index).work.load(Ordering::Acquire) != 0 {
+        core::hint::spin_loop();
+    }
+}
+
+/// First work for every AP: proves it runs on its own GS block and stack.
+fn report_in(index: usize) {
+    let Some(cpu) = percpu::current() else { return };
+    let _ = writeln!(
+        SerialWriter,
+        "K: cpu {}: APIC id {}, GS base {:#x}",
+        index, cpu.apic_id.load(Ordering::Relaxed), msr::gs_base(),
+    );
+}
+
+/// Called by the trampoline on the low identity map with interrupts off.
+extern "C" fn ap_entry(index: usize) -> ! {
+    // SAFETY: the kernel's tables map everything this CPU touches; this is
+    // the only CPU using block `index`.
+    unsafe {
+        control::write_cr3(KERNEL_CR3.load(Ordering::Acquire));
+        percpu::init_cpu(index, apic::id());
+        apic::enable(idt::SPURIOUS_VECTOR);
+    }
+    let cpu = percpu::cpu(index);
+    cpu.online.store(true, Ordering::Release);
+    loop {
+        let work = cpu.work.load(Ordering::Acquire);
+        if work != 0 {
+            // SAFETY: `run_on` only stores `fn(usize)` pointers.
+            let f: fn(usize) = unsafe { core::mem::transmute(work) };
+            f(index);
+            cpu.work.store(0, Ordering::Release);
+            continue;
+        }
+        // A wake IPI between the check and `sti` stays pending and ends the
+        // `hlt` right away.
+        interrupts::sti_hlt();
+        interrupts::cli();
+    }
+}
+
+/// INIT, then SIPI (twice if needed), then waits for the AP to set `online`.
+unsafe fn start_one(params: *mut TrampolineParams, page: u64, index: usize, apic_id: u32) -> bool {
+    let stacks = &*AP_STACKS.0.get();
+    let top = stacks[index].0.as_ptr() as u64 + AP_STACK_SIZE as u64;
+    core::ptr::write_volatile(core::ptr::addr_of_mut!((*params).stack), top);
+    core::ptr::write_volatile(core::ptr::addr_of_mut!((*params).arg), index as u64);
+
+    let cpu = percpu::cpu(index);
+    apic::send_init(apic_id);
+    pit::delay_us(10_000);
+    for _ in 0..2 {
+        apic::send_startup(apic_id, (page / PAGE) as u8);
+        pit::delay_us(200);
+        if cpu.online.load(Ordering::Acquire) { return true; }
+    }
+    let mut waited = 0;
+    while waited < START_TIMEOUT_US {
+        if cpu.online.load(Ordering::Acquire) { return true; }
+        pit::delay_us(POLL_US);
+        waited += POLL_US;
+    }
+    cpu.online.load(Ordering::Acquire)
+}
+
+/// Copies the trampoline to `page`, fills in what every AP shares and
+/// returns its parameter block.
+unsafe fn install_trampoline(page: u64) -> *mut TrampolineParams {
+    let start = core::ptr::addr_of!(ap_trampoline_start);
+    let len = core::ptr::addr_of!(ap_trampoline_end) as usize - start as usize;
+    let params_off = core::ptr::addr_of!(ap_trampoline_params) as usize - start as usize;
+    core::ptr::copy_nonoverlapping(start, page as *mut u8, len);
+
+    let map = &mut *LOW_MAP.0.get();
+    const PRESENT_WRITABLE: u64 = 0b11;
+    const HUGE: u64 = 1 << 7;
+    map.pml4[0] = map.pdpt.as_ptr() as u64 | PRESENT_WRITABLE;
+    for (i, pd) in map.pd.iter_mut().enumerate() {
+        for (j, e) in pd.iter_mut().enumerate() {
+            *e = ((i * 512 + j) as u64) << 21 | PRESENT_WRITABLE | HUGE;
+        }
+        map.pdpt[i] = pd.as_ptr() as u64 | PRESENT_WRITABLE;
+    }
+    KERNEL_CR3.store(control::read_cr3(), Ordering::Release);
+
+    let params = (page as usize + params_off) as *mut TrampolineParams;
+    core::ptr::write_volatile(params, TrampolineParams {
+        boot_cr3: map.pml4.as_ptr() as u32,
+        efer: msr::read(msr::IA32_EFER) as u32,
+        cr0: control::read_cr0(),
+        cr4: control::read_cr4(),
+        stack: 0,
+        entry: ap_entry as usize as u64,
+        arg: 0,
+    });
+    params
+}
+
+/// A usable page below 1 MiB (not page 0).
+fn trampoline_page() -> Option<u64> {
+    memmap::regions()
+        .iter()
+        .filter(|r| r.kind == MemoryKind::Usable)
+        .find_map(|r| {
+            let start = r.base.max(PAGE).next_multiple_of(PAGE);
+            (start + PAGE <= r.end().min(LOW_LIMIT)).then_some(start)
+        })
+}
+
+/// Everything an AP touches before it loads the kernel's page tables (the
+/// identity map, its stack and `ap_entry`) must sit below 4 GiB.
+fn below_4g() -> bool {
+    let end = |p: *const u8, len: usize| p as u64 + len as u64;
+    end(LOW_MAP.0.get() as *const u8, core::mem::size_of::<LowMap>()) <= 1 << 32
+        && end(AP_STACKS.0.get() as *const u8, core::mem::size_of::<[Stack; MAX_CPUS]>()) <= 1 << 32
+        && (ap_entry as usize as u64) < 1 << 32
+}
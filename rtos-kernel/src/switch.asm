@@ -0,0 +1,46 @@
+; Kernel thread context switch, called from sched.rs with interrupts off.
+;
+;   context_switch(save: *mut u64 /* rdi */, load: u64 /* rsi */)
+;
+; Pushes the callee-saved registers, stores RSP in *save, switches to the
+; stack at `load` and pops that thread's registers. The caller-saved ones
+; are already on the stack by the System V ABI (or in the interrupt frame
+; when the switch comes from the timer).
+;
+; A new thread's stack is laid out by sched.rs so that the pops load
+; R12 = the function to call and R13 = its argument, and RET lands in
+; thread_start.
+
+BITS 64
+default rel
+
+global context_switch
+global thread_start
+
+section .text.context_switch align=16
+context_switch:
+    push    rbp
+    push    rbx
+    push    r12
+    push    r13
+    push    r14
+    push    r15
+    mov     [rdi], rsp
+    mov     rsp, rsi
+    pop     r15
+    pop     r14
+    pop     r13
+    pop     r12
+    pop     rbx
+    pop     rbp
+    ret
+
+; RSP is 16-byte aligned here, so the CALL gives the entry the alignment
+; the ABI expects. The entry never returns.
+thread_start:
+    mov     rdi, r13
+    xor     ebp, ebp
+    call    r12
+.hang:
+    hlt
+    jmp     .hang
@@ -0,0 +1,71 @@
+//! Scheduler time. The local APIC timer interrupts the BSP `TICK_HZ` times a
+//! second and `ticks()` counts those interrupts; the TSC gives the finer
+//! time used for CPU accounting. Both are calibrated against the PIT once,
+//! at start-up.
+
+use core::arch::x86_64::_rdtsc;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU64, Ordering};
+use crate::serial_writer::SerialWriter;
+use crate::{apic, pit};
+
+pub const TICK_HZ: u64 = 1000;
+/// How long the PIT is given to measure the TSC and the APIC timer.
+const CALIBRATE_US: u64 = 10_000;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+static TSC_PER_US: AtomicU64 = AtomicU64::new(1);
+
+/// Measures the TSC and APIC timer rates and starts the tick on `vector`.
+/// Returns false (and starts nothing) if the APIC timer does not count.
+///
+/// # Safety
+/// The IDT must handle `vector`, and its handler must call `advance`.
+pub unsafe fn start(vector: u8) -> bool {
+    apic::start_timer(u32::MAX, None, false);
+    let tsc0 = tsc();
+    pit::delay_us(CALIBRATE_US);
+    let tsc1 = tsc();
+    let apic_ticks = (u32::MAX - apic::timer_count()) as u64;
+    TSC_PER_US.store(((tsc1 - tsc0) / CALIBRATE_US).max(1), Ordering::Relaxed);
+
+    let per_tick = apic_ticks * 1_000_000 / CALIBRATE_US / TICK_HZ;
+    if per_tick == 0 || per_tick > u32::MAX as u64 {
+        SerialWriter::write("K: timer: APIC timer does not count; no tick\n");
+        return false;
+    }
+    apic::start_timer(per_tick as u32, Some(vector), true);
+    let _ = writeln!(
+        SerialWriter,
+        "K: timer: {} Hz tick ({} APIC counts), TSC {} MHz",
+        TICK_HZ, per_tick, TSC_PER_US.load(Ordering::Relaxed),
+    );
+    true
+}
+
+/// Timer interrupts so far.
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Acquire)
+}
+
+/// Counts one tick; called by the timer interrupt. Returns the new count.
+pub fn advance() -> u64 {
+    TICKS.fetch_add(1, Ordering::AcqRel) + 1
+}
+
+pub fn ms_to_ticks(ms: u64) -> u64 {
+    ms * TICK_HZ / 1000
+}
+
+pub fn tsc() -> u64 {
+    // SAFETY: RDTSC has no side effects.
+    unsafe { _rdtsc() }
+}
+
+pub fn cycles_to_us(cycles: u64) -> u64 {
+    cycles / TSC_PER_US.load(Ordering::Relaxed)
+}
+
+pub fn us_to_cycles(us: u64) -> u64 {
+    us * TSC_PER_US.load(Ordering::Relaxed)
+}
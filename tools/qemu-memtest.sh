@@ -0,0 +1,56 @@
+#!/usr/bin/env bash
+# Boots the OS under QEMU with several RAM sizes and checks that the kernel
+# receives a memory map from ExitBootServices at each of them.
+#
+#   tools/qemu-memtest.sh [sizes...]     (default: 128M 512M 1G 3G 8G)
+#
+# Needs everything tools/uefi-run.sh needs. Each boot's serial log is kept in
+# ${BUILD_ROOT:-build}/memtest/<size>.log.
+set -euo pipefail
+
+sizes=("$@")
+[[ ${#sizes[@]} -gt 0 ]] || sizes=(128M 512M 1G 3G 8G)
+BOOT_TIMEOUT="${BOOT_TIMEOUT:-90}"
+
+script_dir="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+log_dir="${BUILD_ROOT:-build}/memtest"
+mkdir -p "${log_dir}"
+
+to_mib() {
+  local v="${1%[MmGg]}"
+  case "$1" in
+    *[Gg]) echo $((v * 1024)) ;;
+    *)     echo "$v" ;;
+  esac
+}
+
+failed=0
+for size in "${sizes[@]}"; do
+  log="${log_dir}/${size}.log"
+  echo "== -m ${size}"
+  # The kernel halts after init, so QEMU is stopped by the timeout.
+  RTOS_QEMU_ARGS="-m ${size} -display none -no-reboot" \
+    timeout "${BOOT_TIMEOUT}" "${script_dir}/uefi-run.sh" > "${log}" 2>&1 || true
+
+  if grep -q "FATAL after ExitBootServices" "${log}"; then
+    echo "   FAIL: bootloader failed after ExitBootServices"; grep "FATAL" "${log}" | head -3
+    failed=1; continue
+  fi
+  line=$(grep -m1 "K: memory map:" "${log}" || true)
+  if [[ -z "$line" ]]; then
+    echo "   FAIL: kernel did not report a memory map (see ${log})"
+    failed=1; continue
+  fi
+  # "K: memory map: <n> regions, <m> MiB usable"
+  regions=$(sed -E 's/.*memory map: ([0-9]+) regions.*/\1/' <<< "$line")
+  usable=$(sed -E 's/.* ([0-9]+) MiB usable.*/\1/' <<< "$line")
+  total=$(to_mib "$size")
+  if (( regions == 0 || usable == 0 || usable > total )); then
+    echo "   FAIL: implausible map: ${line#K: }"
+    failed=1; continue
+  fi
+  retries=$(grep -c "stale map key" "${log}" || true)
+  echo "   ok: ${regions} regions, ${usable}/${total} MiB usable, ${retries} ExitBootServices retries"
+done
+
+exit "$failed"
@@ -0,0 +1,42 @@
+#!/usr/bin/env bash
+# Boots the OS under QEMU twice, with `rttest` and `rttest=edf` on the kernel
+# command line, and checks the real-time self-test for both classes:
+# admission control, budget overruns, deadline misses and priority
+# inheritance.
+#
+#   tools/qemu-rt.sh
+#
+# Needs everything tools/uefi-run.sh needs. The serial logs are kept in
+# ${BUILD_ROOT:-build}/rt/.
+set -euo pipefail
+
+BOOT_TIMEOUT="${BOOT_TIMEOUT:-90}"
+
+script_dir="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+log_dir="${BUILD_ROOT:-build}/rt"
+mkdir -p "${log_dir}"
+
+status=0
+for class in rm edf; do
+  log="${log_dir}/${class}.log"
+  cfg="${log_dir}/boot-${class}.cfg"
+  if [[ "$class" == rm ]]; then
+    echo 'cmdline = "rttest"' > "${cfg}"
+  else
+    echo 'cmdline = "rttest=edf"' > "${cfg}"
+  fi
+
+  # The kernel keeps running its monitor thread, so QEMU is stopped by the timeout.
+  RTOS_BOOT_CFG="${cfg}" RTOS_QEMU_ARGS="-display none -no-reboot" \
+    timeout "${BOOT_TIMEOUT}" "${script_dir}/uefi-run.sh" > "${log}" 2>&1 || true
+
+  line=$(grep -m1 "K: rttest (${class}):" "${log}" || true)
+  if [[ -z "$line" ]]; then
+    echo "FAIL: ${class} self-test did not finish (see ${log})"
+    status=1
+    continue
+  fi
+  echo "${line#K: }"
+  [[ "$line" == *": ok" ]] || status=1
+done
+exit "${status}"
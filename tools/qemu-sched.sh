@@ -0,0 +1,32 @@
+#!/usr/bin/env bash
+# Boots the OS under QEMU with `schedtest` on the kernel command line and
+# checks the scheduler self-test: two spinning threads of equal priority
+# share the CPU, and a higher-priority sleeper wakes on time.
+#
+#   tools/qemu-sched.sh
+#
+# Needs everything tools/uefi-run.sh needs. The serial log is kept in
+# ${BUILD_ROOT:-build}/sched/sched.log.
+set -euo pipefail
+
+BOOT_TIMEOUT="${BOOT_TIMEOUT:-90}"
+
+script_dir="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+log_dir="${BUILD_ROOT:-build}/sched"
+mkdir -p "${log_dir}"
+log="${log_dir}/sched.log"
+cfg="${log_dir}/boot.cfg"
+echo 'cmdline = "schedtest"' > "${cfg}"
+
+# The kernel keeps running its monitor thread, so QEMU is stopped by the timeout.
+RTOS_BOOT_CFG="${cfg}" RTOS_QEMU_ARGS="-display none -no-reboot" \
+  timeout "${BOOT_TIMEOUT}" "${script_dir}/uefi-run.sh" > "${log}" 2>&1 || true
+
+grep -m1 "K: timer:" "${log}" || { echo "FAIL: no timer tick (see ${log})"; exit 1; }
+line=$(grep -m1 "K: schedtest:" "${log}" || true)
+if [[ -z "$line" ]]; then
+  echo "FAIL: self-test did not finish (see ${log})"
+  exit 1
+fi
+echo "${line#K: }"
+[[ "$line" == *": ok" ]]
@@ -0,0 +1,50 @@
+#!/usr/bin/env bash
+# Boots the OS under QEMU with several CPUs and checks that the kernel brings
+# every one of them online and that each reports in from its own per-CPU block.
+#
+#   tools/qemu-smp.sh [cpus]     (default: 4)
+#
+# Needs everything tools/uefi-run.sh needs. The serial log is kept in
+# ${BUILD_ROOT:-build}/smp/<cpus>.log.
+set -euo pipefail
+
+cpus="${1:-4}"
+BOOT_TIMEOUT="${BOOT_TIMEOUT:-90}"
+
+script_dir="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+log_dir="${BUILD_ROOT:-build}/smp"
+mkdir -p "${log_dir}"
+log="${log_dir}/${cpus}.log"
+
+echo "== -smp ${cpus}"
+# The kernel halts after init, so QEMU is stopped by the timeout.
+RTOS_QEMU_ARGS="-smp ${cpus} -display none -no-reboot" \
+  timeout "${BOOT_TIMEOUT}" "${script_dir}/uefi-run.sh" > "${log}" 2>&1 || true
+
+line=$(grep -m1 "K: smp:" "${log}" || true)
+if [[ -z "$line" ]]; then
+  echo "   FAIL: kernel did not report SMP bring-up (see ${log})"
+  exit 1
+fi
+# "K: smp: <n> CPUs online (<m> in MADT, xAPIC)"
+online=$(sed -nE 's/.*smp: ([0-9]+) CPUs online.*/\1/p' <<< "$line")
+if [[ "${online}" != "${cpus}" ]]; then
+  echo "   FAIL: ${line#K: }"
+  exit 1
+fi
+
+failed=0
+for ((i = 1; i < cpus; i++)); do
+  if ! grep -q "K: cpu ${i}: APIC id" "${log}"; then
+    echo "   FAIL: cpu ${i} did not report in"
+    failed=1
+  fi
+done
+# Two CPUs on the same GS base would share one per-CPU block.
+dups=$(grep -oE "K: cpu [0-9]+: .*GS base 0x[0-9a-f]+" "${log}" | sed -E 's/.*GS base //' | sort | uniq -d)
+if [[ -n "$dups" ]]; then
+  echo "   FAIL: CPUs share a GS base: ${dups}"
+  failed=1
+fi
+[[ $failed == 0 ]] && echo "   ok: ${line#K: smp: }"
+exit "$failed"
@@ -1,15 +1,18 @@
 use std::{
     env,
-    fs::File,
+    fs::{self, File},
     io::{Read, Write},
     mem,
     path::PathBuf,
 };
 
-use goblin::elf::{program_header, section_header, Elf};
-use rtos_types::{constants::RTOSK_MAGIC, rtosk_header::RtoskHeader, rtosk_segment::RtoskSegment, constants::RTOSK_EXEC_FLAG};
+use goblin::elf::{program_header, section_header, Elf, SectionHeader};
+use rtos_types::{constants::RTOSK_MAGIC, rtosk_header::RtoskHeader, rtosk_segment::RtoskSegment};
+use rtos_types::crc32::crc32;
+use rtos_types::constants::{RTOSK_COMPRESSED, RTOSK_EXEC_FLAG, RTOSK_NOLOAD, RTOSK_READ_FLAG, RTOSK_TLS, RTOSK_WRITE_FLAG};
 
 fn align_up(x: usize, a: usize) -> usize { (x + (a - 1)) & !(a - 1) }
+fn align_down(x: usize, a: usize) -> usize { x & !(a - 1) }
 
 fn parse_u64(s: &str) -> u64 {
     if let Some(h) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
@@ -17,27 +20,118 @@ fn parse_u64(s: &str) -> u64 {
     } else { s.parse::<u64>().unwrap() }
 }
 
-fn crc32(bytes: &[u8]) -> u32 {
-    let mut crc: u32 = 0xFFFF_FFFF;
-    for &b in bytes {
-        let mut x = (crc ^ (b as u32)) & 0xFF;
-        for _ in 0..8 {
-            let m = (x & 1).wrapping_neg();
-            x = (x >> 1) ^ (0xEDB88320 & m);
+/// Segment built from one or more merged sections; owns its payload because
+/// merging pulls in inter-section padding that isn't contiguous in the ELF.
+struct MergedSegment {
+    mem_start: usize,
+    mem_end: usize,
+    bytes: Vec<u8>,
+    flags: u32,
+}
+
+fn ph_flags_to_rtosk(p_flags: u32) -> u32 {
+    let mut flags = 0;
+    if (p_flags & program_header::PF_R) != 0 { flags |= RTOSK_READ_FLAG; }
+    if (p_flags & program_header::PF_W) != 0 { flags |= RTOSK_WRITE_FLAG; }
+    if (p_flags & program_header::PF_X) != 0 { flags |= RTOSK_EXEC_FLAG; }
+    flags
+}
+
+fn sh_flags_to_rtosk(sh_flags: u64) -> u32 {
+    let mut flags = RTOSK_READ_FLAG;
+    if (sh_flags & section_header::SHF_WRITE as u64) != 0 { flags |= RTOSK_WRITE_FLAG; }
+    if (sh_flags & section_header::SHF_EXECINSTR as u64) != 0 { flags |= RTOSK_EXEC_FLAG; }
+    flags
+}
+
+/// Packs allocatable PROGBITS/NOBITS sections into page-aligned segments.
+///
+/// Sections are walked in address order. A section joins the current segment if
+/// it starts on a page the segment already covers (flags are OR-ed, since a page
+/// can only carry one set of permissions), or if it has identical flags and
+/// starts on the very next page. NOBITS sections only grow `mem_end`; their
+/// bytes are zero-filled by the loader unless a later PROGBITS section in the
+/// same segment forces them into the payload.
+fn pack_sections(sections: &[SectionHeader], blob: &[u8], page: usize) -> Vec<MergedSegment> {
+    let mut alloc: Vec<&SectionHeader> = sections.iter()
+        .filter(|sh| (sh.sh_flags & section_header::SHF_ALLOC as u64) != 0)
+        .filter(|sh| sh.sh_type == section_header::SHT_PROGBITS || sh.sh_type == section_header::SHT_NOBITS)
+        .filter(|sh| sh.sh_size != 0 && sh.sh_addr != 0)
+        // .tbss overlays the sections after it and is never backed by memory of its own.
+        .filter(|sh| !(sh.sh_type == section_header::SHT_NOBITS && (sh.sh_flags & section_header::SHF_TLS as u64) != 0))
+        .collect();
+    alloc.sort_by_key(|sh| sh.sh_addr);
+
+    let mut out: Vec<MergedSegment> = Vec::new();
+    for sh in alloc {
+        let start = sh.sh_addr as usize;
+        let end = start + sh.sh_size as usize;
+        let flags = sh_flags_to_rtosk(sh.sh_flags);
+        let nobits = sh.sh_type == section_header::SHT_NOBITS;
+
+        let src = if nobits { None } else {
+            let off = sh.sh_offset as usize;
+            if off.saturating_add(sh.sh_size as usize) > blob.len() { continue; }
+            Some(&blob[off..off + sh.sh_size as usize])
+        };
+
+        let joins = match out.last() {
+            Some(cur) => {
+                let cur_page_end = align_up(cur.mem_end, page);
+                let shares_page = align_down(start, page) < cur_page_end;
+                let next_page = align_down(start, page) == cur_page_end && flags == cur.flags;
+                shares_page || next_page
+            }
+            None => false,
+        };
+
+        if !joins {
+            out.push(MergedSegment { mem_start: align_down(start, page), mem_end: start, bytes: Vec::new(), flags });
+        }
+
+        let cur = out.last_mut().unwrap();
+        cur.flags |= flags;
+        cur.mem_end = cur.mem_end.max(end);
+        if let Some(src) = src {
+            // Zero-pad from the previous payload end up to this section's offset.
+            let at = start - cur.mem_start;
+            if cur.bytes.len() < at + src.len() { cur.bytes.resize(at + src.len(), 0); }
+            cur.bytes[at..at + src.len()].copy_from_slice(src);
         }
-        crc = (crc >> 8) ^ x;
     }
-    !crc
+
+    for seg in &mut out {
+        seg.mem_end = align_up(seg.mem_end, page);
+    }
+    out
 }
 
-#[derive(Clone, Copy)]
-struct PayloadSpec { src_off: usize, file_sz: usize }
+/// Reads a raw 64-byte Ed25519 secret key as written by `rtosk-sign keygen`.
+fn read_secret_key(path: &str) -> [u8; rtosk_sig::SECRET_KEY_LEN] {
+    let bytes = fs::read(path).expect("read signing key");
+    bytes.as_slice().try_into().expect("signing key must be 64 bytes")
+}
 
 fn main() {
-    // Usage: rtosk-gen <input> <output.rtosk> [entry_va] [page_size]
-    let mut args = env::args().skip(1);
-    let in_path  = PathBuf::from(args.next().expect("usage: rtosk-gen <input> <output.rtosk> [entry_va] [page_size]"));
-    let out_path = PathBuf::from(args.next().expect("usage: rtosk-gen <input> <output.rtosk> [entry_va] [page_size]"));
+    // Usage: rtosk-gen [--compress] [--sign <key>] <input> <output.rtosk> [entry_va] [page_size]
+    const USAGE: &str = "usage: rtosk-gen [--compress] [--sign <key>] <input> <output.rtosk> [entry_va] [page_size]";
+    let mut raw: Vec<String> = env::args().skip(1).collect();
+    let compress = match raw.iter().position(|a| a == "--compress") {
+        Some(i) => { raw.remove(i); true }
+        None => false,
+    };
+    let sign_key = match raw.iter().position(|a| a == "--sign") {
+        Some(i) => {
+            if i + 1 >= raw.len() { panic!("{}", USAGE); }
+            let path = raw.remove(i + 1);
+            raw.remove(i);
+            Some(read_secret_key(&path))
+        }
+        None => None,
+    };
+    let mut args = raw.into_iter();
+    let in_path  = PathBuf::from(args.next().expect(USAGE));
+    let out_path = PathBuf::from(args.next().expect(USAGE));
     let entry_override = args.next();
     let page_size: u32 = args.next().map(|s| parse_u64(&s) as u32).unwrap_or(4096);
 
@@ -47,7 +141,7 @@ fn main() {
     f.read_to_end(&mut blob).expect("read input");
 
     let mut segments: Vec<RtoskSegment> = Vec::new();
-    let mut payloads: Vec<PayloadSpec>  = Vec::new();
+    let mut payloads: Vec<Vec<u8>>      = Vec::new();
     let mut entry64: u64;
 
     // Try ELF first
@@ -58,7 +152,22 @@ fn main() {
             entry64 = entry_override.as_deref().map(parse_u64).unwrap_or(elf.header.e_entry);
 
             let mut total_ph_filesz: usize = 0;
+            let mut tls: Option<(RtoskSegment, Vec<u8>)> = None;
             for ph in &elf.program_headers {
+                if ph.p_type == program_header::PT_TLS && ph.p_memsz != 0 {
+                    // Template only: its .tdata bytes are already part of a PT_LOAD segment.
+                    let (off, sz) = (ph.p_offset as usize, ph.p_filesz as usize);
+                    if off.saturating_add(sz) > blob.len() { continue; }
+                    tls = Some((RtoskSegment {
+                        file_offset: 0,
+                        file_size: ph.p_filesz,
+                        memory_addr: ph.p_vaddr,
+                        memory_size: ph.p_memsz,
+                        flags: ph_flags_to_rtosk(ph.p_flags) | RTOSK_TLS,
+                        packed_size: 0,
+                    }, blob[off..off + sz].to_vec()));
+                    continue;
+                }
                 if ph.p_type != program_header::PT_LOAD || ph.p_memsz == 0 { continue; }
 
                 let src_off = ph.p_offset as usize;
@@ -66,16 +175,16 @@ fn main() {
                 // Ensure we don't read past file
                 if src_off.checked_add(file_sz).unwrap_or(usize::MAX) > blob.len() { continue; }
 
-                let flags = if (ph.p_flags & program_header::PF_X) != 0 { RTOSK_EXEC_FLAG } else { 0 };
-
+                // p_memsz > p_filesz is the BSS tail; the loader zero-fills it.
                 segments.push(RtoskSegment {
                     file_offset: 0,
                     file_size: ph.p_filesz,
                     memory_addr: ph.p_vaddr,
-                    memory_size: ph.p_memsz,
-                    flags,
+                    memory_size: ph.p_memsz.max(ph.p_filesz),
+                    flags: ph_flags_to_rtosk(ph.p_flags),
+                    packed_size: 0,
                 });
-                payloads.push(PayloadSpec { src_off, file_sz });
+                payloads.push(blob[src_off..src_off + file_sz].to_vec());
                 total_ph_filesz = total_ph_filesz.saturating_add(file_sz);
             }
 
@@ -84,36 +193,21 @@ fn main() {
             if segments.is_empty() || ph_pack_too_small {
                 segments.clear();
                 payloads.clear();
+                tls = None;
 
-                // Collect allocatable PROGBITS sections (.text/.rodata/.data etc.)
-                // Keep original VAs (sh_addr) so they map where you linked them (0x200000…).
-                let mut total_sh_filesz = 0usize;
-                for sh in &elf.section_headers {
-                    let sh_flags = sh.sh_flags;
-                    let sh_type  = sh.sh_type;
-
-                    let is_alloc   = (sh_flags & section_header::SHF_ALLOC as u64) != 0;
-                    let is_progbit = sh_type == section_header::SHT_PROGBITS;
-
-                    if !is_alloc || !is_progbit || sh.sh_size == 0 { continue; }
-
-                    let src_off = sh.sh_offset as usize;
-                    let file_sz = sh.sh_size as usize;
-                    if src_off.checked_add(file_sz).unwrap_or(usize::MAX) > blob.len() { continue; }
-
-                    // Mark exec if this looks like .text (has SHF_EXECINSTR)
-                    let exec = (sh_flags & section_header::SHF_EXECINSTR as u64) != 0;
-                    let flags = if exec { RTOSK_EXEC_FLAG } else { 0 };
-
+                // Collect allocatable PROGBITS (.text/.rodata/.data) and NOBITS (.bss) sections,
+                // merged into page-aligned segments at their original VAs (0x200000…).
+                let page = page_size.max(4096) as usize;
+                for seg in pack_sections(&elf.section_headers, &blob, page) {
                     segments.push(RtoskSegment {
                         file_offset: 0,
-                        file_size: file_sz as u64,
-                        memory_addr: sh.sh_addr,
-                        memory_size: sh.sh_size,
-                        flags,
+                        file_size: seg.bytes.len() as u64,
+                        memory_addr: seg.mem_start as u64,
+                        memory_size: (seg.mem_end - seg.mem_start) as u64,
+                        flags: seg.flags,
+                        packed_size: 0,
                     });
-                    payloads.push(PayloadSpec { src_off, file_sz });
-                    total_sh_filesz = total_sh_filesz.saturating_add(file_sz);
+                    payloads.push(seg.bytes);
                 }
 
                 // Entry selection: override > ELF e_entry > first alloc section > 0x200000
@@ -136,12 +230,18 @@ fn main() {
                         file_size: sz,
                         memory_addr: e,
                         memory_size: sz,
-                        flags: RTOSK_EXEC_FLAG,
+                        flags: RTOSK_READ_FLAG | RTOSK_WRITE_FLAG | RTOSK_EXEC_FLAG,
+                        packed_size: 0,
                     });
-                    payloads.push(PayloadSpec { src_off: 0, file_sz: sz as usize });
+                    payloads.push(blob.clone());
                 }
             }
 
+            if let Some((seg, bytes)) = tls {
+                segments.push(seg);
+                payloads.push(bytes);
+            }
+
             // If e_entry is still zero, default to first executable segment or first segment VA.
             if entry64 == 0 {
                 entry64 = segments.iter()
@@ -156,9 +256,9 @@ fn main() {
             entry64 = e;
             let sz = blob.len() as u64;
             segments.push(RtoskSegment {
-                file_offset: 0, file_size: sz, memory_addr: e, memory_size: sz, flags: RTOSK_EXEC_FLAG,
+                file_offset: 0, file_size: sz, memory_addr: e, memory_size: sz, flags: RTOSK_READ_FLAG | RTOSK_WRITE_FLAG | RTOSK_EXEC_FLAG, packed_size: 0,
             });
-            payloads.push(PayloadSpec { src_off: 0, file_sz: sz as usize });
+            payloads.push(blob.clone());
         }
     } else {
         // Not ELF → flat bin
@@ -166,9 +266,26 @@ fn main() {
         entry64 = e;
         let sz = blob.len() as u64;
         segments.push(RtoskSegment {
-            file_offset: 0, file_size: sz, memory_addr: e, memory_size: sz, flags: RTOSK_EXEC_FLAG,
+            file_offset: 0, file_size: sz, memory_addr: e, memory_size: sz, flags: RTOSK_READ_FLAG | RTOSK_WRITE_FLAG | RTOSK_EXEC_FLAG, packed_size: 0,
         });
-        payloads.push(PayloadSpec { src_off: 0, file_sz: sz as usize });
+        payloads.push(blob.clone());
+    }
+
+    // ---- Optional LZ4 compression ----
+    // file_size keeps the decompressed length; packed_size is what lands in the file.
+    // Only worth it when it actually shrinks; the TLS template stays raw.
+    if compress {
+        for (seg, p) in segments.iter_mut().zip(payloads.iter_mut()) {
+            if p.is_empty() || seg.flags & RTOSK_TLS != 0 { continue; }
+            let mut packed = vec![0u8; rtosk_lz4::max_compressed_len(p.len())];
+            let n = rtosk_lz4::compress_into(p, &mut packed);
+            packed.truncate(n);
+            if packed.len() < p.len() && packed.len() <= u32::MAX as usize {
+                seg.flags |= RTOSK_COMPRESSED;
+                seg.packed_size = packed.len() as u32;
+                *p = packed;
+            }
+        }
     }
 
     // ---- Layout RTOSK image ----
@@ -177,7 +294,8 @@ fn main() {
     for (seg, p) in segments.iter_mut().zip(payloads.iter()) {
         cur = align_up(cur, 16);
         seg.file_offset = cur as u64;
-        cur += p.file_sz;
+        cur += p.len();
+        if p.is_empty() { seg.flags |= RTOSK_NOLOAD; }
     }
 
     let mut out_buf = vec![0u8; cur];
@@ -209,9 +327,9 @@ fn main() {
 
     // Copy payload bytes
     for (i, p) in payloads.iter().enumerate() {
-        if p.file_sz == 0 { continue; }
+        if p.is_empty() { continue; }
         let dst = segments[i].file_offset as usize;
-        out_buf[dst..dst + p.file_sz].copy_from_slice(&blob[p.src_off..p.src_off + p.file_sz]);
+        out_buf[dst..dst + p.len()].copy_from_slice(p);
     }
 
     // Finalize CRC
@@ -221,6 +339,13 @@ fn main() {
         std::ptr::copy_nonoverlapping(hptr, out_buf.as_mut_ptr(), mem::size_of::<RtoskHeader>());
     }
 
+    // Optional signature: flag + CRC are rewritten first so both are covered.
+    if let Some(key) = sign_key {
+        rtosk_sig::mark_signed(&mut out_buf);
+        let sig = rtosk_sig::sign(&out_buf, &key).expect("invalid signing key");
+        out_buf.extend_from_slice(&sig.to_bytes());
+    }
+
     let mut out = File::create(&out_path).expect("create output");
     out.write_all(&out_buf).expect("write output");
     let _ = out.flush();
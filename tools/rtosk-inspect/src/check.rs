@@ -0,0 +1,104 @@
+use std::mem;
+
+use rtos_types::{rtosk_header::RtoskHeader, rtosk_segment::RtoskSegment};
+
+use crate::image::RtoskImage;
+
+/// Structural problems that would make the bootloader reject or mis-load the
+/// image. Empty means `--check` passes.
+pub fn problems(img: &RtoskImage) -> Vec<String> {
+    let mut out = Vec::new();
+    let h = &img.header;
+    let image_len = img.image_bytes().len() as u64;
+
+    if img.magic_offset != 0 {
+        out.push(format!("magic at file offset 0x{:x}, expected 0", img.magic_offset));
+    }
+    if h.ver_major != 1 {
+        out.push(format!("unsupported version {}.{}", h.ver_major, h.ver_minor));
+    }
+
+    let table_end = mem::size_of::<RtoskHeader>() as u64
+        + h.seg_count as u64 * mem::size_of::<RtoskSegment>() as u64;
+    if img.segments.len() != h.seg_count as usize {
+        out.push(format!("segment table truncated: {} of {} entries readable", img.segments.len(), h.seg_count));
+    }
+    if (h.header_len as u64) < mem::size_of::<RtoskHeader>() as u64 || h.header_len as u64 > image_len {
+        out.push(format!("header_len 0x{:x} out of range", h.header_len));
+    }
+    if h.page_size == 0 || !h.page_size.is_power_of_two() || h.page_size < 4096 {
+        out.push(format!("page_size 0x{:x} is not a power of two >= 0x1000", h.page_size));
+    }
+    if h.seg_count == 0 {
+        out.push("image has no segments".into());
+    }
+
+    for (i, s) in img.segments.iter().enumerate() {
+        let file_end = s.file_offset.checked_add(s.stored_size());
+        if s.stored_size() > 0 && !s.is_noload() {
+            match file_end {
+                Some(end) if end <= image_len => {
+                    if s.file_offset < table_end {
+                        out.push(format!("segment[{}] payload overlaps header/segment table", i));
+                    }
+                }
+                _ => out.push(format!("segment[{}] file range 0x{:x}+0x{:x} past end of image (0x{:x})",
+                                      i, s.file_offset, s.stored_size(), image_len)),
+            }
+        }
+        if s.is_noload() && s.file_size != 0 {
+            out.push(format!("segment[{}] is NOLOAD but has file_size 0x{:x}", i, s.file_size));
+        }
+        if s.is_compressed() && !s.is_noload() {
+            if let Err(e) = img.decoded_payload(i) {
+                out.push(format!("{}", e));
+            }
+        }
+        if s.file_size > s.memory_size {
+            out.push(format!("segment[{}] file_size 0x{:x} > memory_size 0x{:x}", i, s.file_size, s.memory_size));
+        }
+        if s.memory_addr.checked_add(s.memory_size).is_none() {
+            out.push(format!("segment[{}] memory range overflows", i));
+        }
+        if !s.is_tls() && s.memory_size != 0 && !s.is_readable() && !s.is_writable() && !s.is_executable() {
+            out.push(format!("segment[{}] has no R/W/X permissions", i));
+        }
+    }
+
+    // Mapped segments must not claim the same bytes of memory.
+    let mapped: Vec<(usize, &RtoskSegment)> = img.segments.iter().enumerate()
+        .filter(|(_, s)| !s.is_tls() && s.memory_size != 0)
+        .collect();
+    for (n, (i, a)) in mapped.iter().enumerate() {
+        for (j, b) in &mapped[n + 1..] {
+            let a_end = a.memory_addr.saturating_add(a.memory_size);
+            let b_end = b.memory_addr.saturating_add(b.memory_size);
+            if a.memory_addr < b_end && b.memory_addr < a_end {
+                out.push(format!("segment[{}] and segment[{}] overlap in memory", i, j));
+            }
+        }
+    }
+
+    if h.entry64 == 0 {
+        out.push("entry64 is zero".into());
+    } else {
+        match img.entry_segment() {
+            None => out.push(format!("entry64 0x{:x} is not inside any segment", h.entry64)),
+            Some(i) if !img.segments[i].is_executable() => {
+                out.push(format!("entry64 0x{:x} is inside non-executable segment[{}]", h.entry64, i))
+            }
+            Some(_) => {}
+        }
+    }
+
+    if h.is_signed() && img.signature().is_none() {
+        out.push("header is marked signed but the signature block is missing".into());
+    }
+
+    let crc = img.crc_status();
+    if !crc.ok() {
+        out.push(format!("image_crc32 mismatch: stored 0x{:08x}, computed 0x{:08x}", crc.stored, crc.computed));
+    }
+
+    out
+}
@@ -0,0 +1,68 @@
+use crate::image::{perms_str, RtoskImage};
+
+/// Prints header and per-segment differences between two images.
+/// Returns true if anything differs (including segment payload bytes).
+pub fn print_diff(a: &RtoskImage, b: &RtoskImage) -> bool {
+    let mut changed = false;
+    let (ha, hb) = (&a.header, &b.header);
+
+    println!("Header:");
+    let mut field = |name: &str, va: String, vb: String| {
+        if va != vb {
+            println!("  {:<11} {} -> {}", name, va, vb);
+            changed = true;
+        }
+    };
+    field("version", format!("{}.{}", ha.ver_major, ha.ver_minor), format!("{}.{}", hb.ver_major, hb.ver_minor));
+    field("header_len", format!("0x{:x}", ha.header_len), format!("0x{:x}", hb.header_len));
+    field("entry64", format!("0x{:016x}", ha.entry64), format!("0x{:016x}", hb.entry64));
+    field("page_size", format!("0x{:x}", ha.page_size), format!("0x{:x}", hb.page_size));
+    field("seg_count", ha.seg_count.to_string(), hb.seg_count.to_string());
+    field("image_crc32", format!("0x{:08x}", ha.image_crc32), format!("0x{:08x}", hb.image_crc32));
+    field("flags", format!("0x{:08x}", ha.flags), format!("0x{:08x}", hb.flags));
+    field("footprint", format!("0x{:x}", a.memory_footprint()), format!("0x{:x}", b.memory_footprint()));
+    if !changed { println!("  (identical)"); }
+
+    let count = a.segments.len().max(b.segments.len());
+    for i in 0..count {
+        match (a.segments.get(i), b.segments.get(i)) {
+            (Some(sa), None) => {
+                println!("Segment[{}]: removed (0x{:x}+0x{:x} {})", i, sa.memory_addr, sa.memory_size, perms_str(sa));
+                changed = true;
+            }
+            (None, Some(sb)) => {
+                println!("Segment[{}]: added (0x{:x}+0x{:x} {})", i, sb.memory_addr, sb.memory_size, perms_str(sb));
+                changed = true;
+            }
+            (Some(sa), Some(sb)) => {
+                let mut lines = Vec::new();
+                let mut cmp = |name: &str, va: String, vb: String| {
+                    if va != vb { lines.push(format!("  {:<12} {} -> {}", name, va, vb)); }
+                };
+                cmp("memory_addr", format!("0x{:x}", sa.memory_addr), format!("0x{:x}", sb.memory_addr));
+                cmp("memory_size", format!("0x{:x}", sa.memory_size), format!("0x{:x}", sb.memory_size));
+                cmp("file_offset", format!("0x{:x}", sa.file_offset), format!("0x{:x}", sb.file_offset));
+                cmp("file_size", format!("0x{:x}", sa.file_size), format!("0x{:x}", sb.file_size));
+                cmp("packed_size", format!("0x{:x}", sa.stored_size()), format!("0x{:x}", sb.stored_size()));
+                cmp("flags", format!("0x{:x} ({})", sa.flags, perms_str(sa)), format!("0x{:x} ({})", sb.flags, perms_str(sb)));
+
+                // Payloads are compared by content, independent of where they sit in the file.
+                if a.payload(i) != b.payload(i) {
+                    let show = |c: Option<u32>| c.map(|c| format!("0x{:08x}", c)).unwrap_or_else(|| "<out of range>".into());
+                    lines.push(format!("  {:<12} crc {} -> {}", "payload", show(a.payload_crc32(i)), show(b.payload_crc32(i))));
+                }
+
+                if lines.is_empty() {
+                    println!("Segment[{}]: unchanged", i);
+                } else {
+                    println!("Segment[{}]:", i);
+                    for l in lines { println!("{}", l); }
+                    changed = true;
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    changed
+}
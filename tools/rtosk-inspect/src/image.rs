@@ -0,0 +1,162 @@
+use std::fs::File;
+use std::io::Read;
+use std::mem;
+
+use rtos_types::crc32::{crc32, rtosk_image_crc32};
+use rtos_types::{constants::RTOSK_MAGIC, rtosk_header::RtoskHeader, rtosk_segment::RtoskSegment};
+use rtos_types::rtosk_signature::RtoskSignature;
+
+use crate::{InspectError, Result};
+
+/// An RTOSK file read into memory, with the header and as much of the segment
+/// table as fits in the file. Nothing here is validated beyond "readable";
+/// structural checks live in `check.rs`.
+pub struct RtoskImage {
+    pub blob: Vec<u8>,
+    pub magic_offset: usize,
+    pub header: RtoskHeader,
+    pub segments: Vec<RtoskSegment>,
+}
+
+pub struct CrcStatus {
+    pub stored: u32,
+    pub computed: u32,
+}
+
+impl CrcStatus {
+    pub fn ok(&self) -> bool { self.stored == self.computed }
+}
+
+pub fn read_struct<T: Copy>(buffer: &[u8], offset: usize) -> Option<T> {
+    let size = mem::size_of::<T>();
+    if offset + size > buffer.len() { return None; }
+    let mut tmp = mem::MaybeUninit::<T>::uninit();
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            buffer.as_ptr().add(offset),
+            tmp.as_mut_ptr() as *mut u8,
+            size,
+        );
+        Some(tmp.assume_init())
+    }
+}
+
+pub fn find_magic(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if haystack.len() < needle.len() { return None; }
+    (0..=haystack.len() - needle.len()).find(|&i| &haystack[i..i + needle.len()] == needle)
+}
+
+impl RtoskImage {
+    pub fn load(path: &str) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let mut blob = Vec::new();
+        file.read_to_end(&mut blob)?;
+        Self::parse(blob)
+    }
+
+    pub fn parse(blob: Vec<u8>) -> Result<Self> {
+        // Friendly guard: if this is an ELF, bail with a clear hint.
+        if blob.len() >= 4 && &blob[0..4] == b"\x7FELF" {
+            return Err(InspectError("this looks like an ELF file; pack it with tools/rtosk-gen to produce KERNEL.RTOSK".into()).into());
+        }
+        if blob.len() < mem::size_of::<RtoskHeader>() {
+            return Err(InspectError("file too small for header".into()).into());
+        }
+
+        let magic_offset = find_magic(&blob, &RTOSK_MAGIC)
+            .ok_or_else(|| InspectError("RTOSK magic not found".into()))?;
+        let header: RtoskHeader = read_struct(&blob, magic_offset)
+            .ok_or_else(|| InspectError(format!("failed to read header at offset 0x{:x}", magic_offset)))?;
+
+        let mut segments = Vec::new();
+        let mut off = magic_offset + mem::size_of::<RtoskHeader>();
+        for _ in 0..header.seg_count {
+            match read_struct::<RtoskSegment>(&blob, off) {
+                Some(s) => segments.push(s),
+                None => break,
+            }
+            off += mem::size_of::<RtoskSegment>();
+        }
+
+        Ok(RtoskImage { blob, magic_offset, header, segments })
+    }
+
+    /// The image bytes starting at the magic (what the bootloader parses).
+    pub fn image_bytes(&self) -> &[u8] { &self.blob[self.magic_offset..] }
+
+    /// File payload of segment `i` as stored (compressed or not), if its range lies inside the file.
+    pub fn payload(&self, i: usize) -> Option<&[u8]> {
+        let seg = self.segments.get(i)?;
+        let img = self.image_bytes();
+        let start = usize::try_from(seg.file_offset).ok()?;
+        let end = start.checked_add(usize::try_from(seg.stored_size()).ok()?)?;
+        img.get(start..end)
+    }
+
+    /// Payload of segment `i` as the loader writes it to memory (decompressed if needed).
+    pub fn decoded_payload(&self, i: usize) -> Result<Vec<u8>> {
+        let raw = self.payload(i)
+            .ok_or_else(|| InspectError(format!("segment {} payload lies outside the file", i)))?;
+        let seg = &self.segments[i];
+        if !seg.is_compressed() { return Ok(raw.to_vec()); }
+
+        let len = usize::try_from(seg.file_size)
+            .map_err(|_| InspectError(format!("segment {} file_size too large", i)))?;
+        let mut out = vec![0u8; len];
+        rtosk_lz4::decompress_exact(raw, &mut out, len)
+            .map_err(|e| InspectError(format!("segment {}: {}", i, e.as_str())))?;
+        Ok(out)
+    }
+
+    pub fn payload_crc32(&self, i: usize) -> Option<u32> {
+        self.payload(i).map(crc32)
+    }
+
+    /// Index of the mapped segment containing `entry64`.
+    pub fn entry_segment(&self) -> Option<usize> {
+        let entry = self.header.entry64;
+        self.segments.iter().position(|s| {
+            !s.is_tls() && entry >= s.memory_addr && entry < s.memory_addr.saturating_add(s.memory_size)
+        })
+    }
+
+    /// Bytes of memory the loader allocates: every mapped segment rounded out to pages.
+    pub fn memory_footprint(&self) -> u64 {
+        let page = (self.header.page_size as u64).max(4096);
+        self.segments.iter()
+            .filter(|s| !s.is_tls() && s.memory_size != 0)
+            .map(|s| {
+                let start = s.memory_addr & !(page - 1);
+                let end = s.memory_addr.saturating_add(s.memory_size).saturating_add(page - 1) & !(page - 1);
+                end - start
+            })
+            .sum()
+    }
+
+    /// Trailing signature block, if the header is marked signed and the block is intact.
+    /// The signature itself is checked by `rtosk-sign verify`, not here.
+    pub fn signature(&self) -> Option<RtoskSignature> {
+        if !self.header.is_signed() { return None; }
+        RtoskSignature::from_tail(self.image_bytes())
+    }
+
+    pub fn crc_status(&self) -> CrcStatus {
+        CrcStatus { stored: self.header.image_crc32, computed: rtosk_image_crc32(self.image_bytes()) }
+    }
+}
+
+/// `R-X` / `RW-` style rendering of segment flags, plus markers.
+pub fn perms_str(seg: &RtoskSegment) -> String {
+    let mut s = String::with_capacity(16);
+    s.push(if seg.is_readable() { 'R' } else { '-' });
+    s.push(if seg.is_writable() { 'W' } else { '-' });
+    s.push(if seg.is_executable() { 'X' } else { '-' });
+    if seg.is_noload() { s.push_str(" NOLOAD"); }
+    if seg.is_tls() { s.push_str(" TLS"); }
+    if seg.is_compressed() { s.push_str(" LZ4"); }
+    s
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,76 @@
+use std::fmt::Write;
+
+use crate::check::problems;
+use crate::image::{hex, perms_str, RtoskImage};
+
+/// Minimal JSON string escaping (quotes, backslashes, control characters).
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Header, segments and derived facts as a single JSON object. Addresses are
+/// emitted as hex strings so 64-bit values survive JSON number parsing.
+pub fn image_to_json(img: &RtoskImage) -> String {
+    let h = &img.header;
+    let mut o = String::new();
+
+    o.push_str("{\n");
+    let _ = writeln!(o, "  \"magic_offset\": {},", img.magic_offset);
+    o.push_str("  \"header\": {\n");
+    let _ = writeln!(o, "    \"magic\": {},", escape(&String::from_utf8_lossy(&h.magic)));
+    let _ = writeln!(o, "    \"version\": \"{}.{}\",", h.ver_major, h.ver_minor);
+    let _ = writeln!(o, "    \"header_len\": {},", h.header_len);
+    let _ = writeln!(o, "    \"entry64\": \"0x{:x}\",", h.entry64);
+    let _ = writeln!(o, "    \"page_size\": {},", h.page_size);
+    let _ = writeln!(o, "    \"seg_count\": {},", h.seg_count);
+    let _ = writeln!(o, "    \"image_crc32\": \"0x{:08x}\",", h.image_crc32);
+    let _ = writeln!(o, "    \"flags\": \"0x{:08x}\"", h.flags);
+    o.push_str("  },\n");
+
+    o.push_str("  \"segments\": [");
+    for (i, s) in img.segments.iter().enumerate() {
+        o.push_str(if i == 0 { "\n" } else { ",\n" });
+        let payload_crc = img.payload_crc32(i)
+            .map(|c| format!("\"0x{:08x}\"", c))
+            .unwrap_or_else(|| "null".into());
+        let _ = write!(
+            o,
+            "    {{ \"index\": {}, \"file_offset\": \"0x{:x}\", \"file_size\": {}, \"packed_size\": {}, \"memory_addr\": \"0x{:x}\", \
+             \"memory_size\": {}, \"flags\": \"0x{:x}\", \"perms\": {}, \"payload_crc32\": {} }}",
+            i, s.file_offset, s.file_size, s.stored_size(), s.memory_addr, s.memory_size, s.flags, escape(&perms_str(s)), payload_crc,
+        );
+    }
+    o.push_str(if img.segments.is_empty() { "],\n" } else { "\n  ],\n" });
+
+    let crc = img.crc_status();
+    let entry = img.entry_segment().map(|i| i.to_string()).unwrap_or_else(|| "null".into());
+    let issues = problems(img);
+    o.push_str("  \"derived\": {\n");
+    let _ = writeln!(o, "    \"entry_segment\": {},", entry);
+    let _ = writeln!(o, "    \"memory_footprint\": {},", img.memory_footprint());
+    let _ = writeln!(
+        o,
+        "    \"crc\": {{ \"stored\": \"0x{:08x}\", \"computed\": \"0x{:08x}\", \"ok\": {} }},",
+        crc.stored, crc.computed, crc.ok()
+    );
+    let signer = img.signature().map(|s| format!("\"{}\"", hex(&s.public_key))).unwrap_or_else(|| "null".into());
+    let _ = writeln!(o, "    \"signed\": {},", h.is_signed());
+    let _ = writeln!(o, "    \"signer_key\": {},", signer);
+    let list: Vec<String> = issues.iter().map(|p| escape(p)).collect();
+    let _ = writeln!(o, "    \"problems\": [{}]", list.join(", "));
+    o.push_str("  }\n");
+    o.push('}');
+    o
+}
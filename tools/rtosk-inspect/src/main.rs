@@ -2,17 +2,35 @@
 // If you don't have a cargo package for this, you can also:
 // rustc rtos_inspect.rs -O -o rtos_inspect && ./rtos_inspect KERNEL.RTOSK
 
+mod check;
+mod diff;
+mod image;
+mod json;
+
 use std::env;
 use std::error::Error;
 use std::fmt;
-use std::fs::File;
-use std::io::Read;
+use std::fs;
 use std::mem;
+use std::process::ExitCode;
+
+use rtos_types::{rtosk_header::RtoskHeader, rtosk_segment::RtoskSegment};
+
+use crate::image::{hex, perms_str, RtoskImage};
+
+const USAGE: &str = "\
+usage: rtosk-inspect <KERNEL.RTOSK> [--json] [--check]
+       rtosk-inspect <KERNEL.RTOSK> --dump-segment <N> --out <FILE>
+       rtosk-inspect diff <A.RTOSK> <B.RTOSK>
 
-use rtos_types::{constants::RTOSK_MAGIC, rtosk_header::RtoskHeader, rtosk_segment::RtoskSegment};
+  --json          print header, segments and derived facts as JSON
+  --check         validate structure and CRC; exit 2 if any problem is found
+  --dump-segment  write segment N's file payload (decompressed) to --out
+  diff            show header and per-segment changes; exit 1 if they differ,
+                  2 if an image can't be read";
 
 #[derive(Debug)]
-struct InspectError(String);
+pub struct InspectError(String);
 
 impl fmt::Display for InspectError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.0) }
@@ -23,21 +41,7 @@ macro_rules! bail {
     ($($t:tt)*) => { return Err(Box::<dyn Error>::from(InspectError(format!($($t)*)))) };
 }
 
-type Result<T> = std::result::Result<T, Box<dyn Error>>;
-
-fn read_struct<T: Copy>(buffer: &[u8], offset: usize) -> Option<T> {
-    let size = mem::size_of::<T>();
-    if offset + size > buffer.len() { return None; }
-    let mut tmp = mem::MaybeUninit::<T>::uninit();
-    unsafe {
-        std::ptr::copy_nonoverlapping(
-            buffer.as_ptr().add(offset),
-            tmp.as_mut_ptr() as *mut u8,
-            size,
-        );
-        Some(tmp.assume_init())
-    }
-}
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
 fn hex_dump_prefix(bytes: &[u8]) {
     for (i, b) in bytes.iter().enumerate() {
@@ -57,37 +61,104 @@ fn dump_bytes_at(data: &[u8], offset: usize, length: usize) {
     println!();
 }
 
-fn find_magic(haystack: &[u8], needle: &[u8]) -> Option<usize> {
-    if haystack.len() < needle.len() { return None; }
-    for i in 0..=haystack.len() - needle.len() {
-        if &haystack[i..i + needle.len()] == needle { return Some(i); }
+
+struct Options {
+    path: String,
+    json: bool,
+    check: bool,
+    dump_segment: Option<usize>,
+    out: Option<String>,
+}
+
+fn parse_options(args: Vec<String>) -> Result<Options> {
+    let mut opts = Options { path: String::new(), json: false, check: false, dump_segment: None, out: None };
+    let mut it = args.into_iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--json" => opts.json = true,
+            "--check" => opts.check = true,
+            "--dump-segment" => {
+                let n = it.next().ok_or_else(|| InspectError("--dump-segment needs an index".into()))?;
+                opts.dump_segment = Some(n.parse().map_err(|_| InspectError(format!("bad segment index '{}'", n)))?);
+            }
+            "--out" => opts.out = Some(it.next().ok_or_else(|| InspectError("--out needs a path".into()))?),
+            "-h" | "--help" => bail!("{}", USAGE),
+            s if s.starts_with("--") => bail!("unknown option '{}'\n{}", s, USAGE),
+            _ if opts.path.is_empty() => opts.path = arg,
+            _ => bail!("unexpected argument '{}'\n{}", arg, USAGE),
+        }
+    }
+    if opts.path.is_empty() { bail!("{}", USAGE); }
+    if opts.dump_segment.is_some() != opts.out.is_some() { bail!("--dump-segment and --out go together\n{}", USAGE); }
+    Ok(opts)
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("rtosk-inspect: {}", e);
+            ExitCode::FAILURE
+        }
     }
-    None
 }
 
-fn main() -> Result<()> {
-    let mut args = env::args().skip(1);
-    let path = args.next().ok_or_else(|| InspectError("usage: rtosk-inspect <KERNEL.RTOSK>".into()))?;
+fn run_diff(args: &[String]) -> Result<bool> {
+    if args.len() != 2 { bail!("{}", USAGE); }
+    let a = RtoskImage::load(&args[0])?;
+    let b = RtoskImage::load(&args[1])?;
+    Ok(diff::print_diff(&a, &b))
+}
+
+fn run() -> Result<ExitCode> {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.first().map(String::as_str) == Some("diff") {
+        // Like diff(1)/cmp(1): 1 means the images differ, 2 means trouble.
+        return Ok(match run_diff(&args[1..]) {
+            Ok(false) => ExitCode::SUCCESS,
+            Ok(true) => ExitCode::from(1),
+            Err(e) => {
+                eprintln!("rtosk-inspect: {}", e);
+                ExitCode::from(2)
+            }
+        });
+    }
+
+    let opts = parse_options(args)?;
+    let img = RtoskImage::load(&opts.path)?;
 
-    let mut file = File::open(&path)?;
-    let mut blob = Vec::new();
-    file.read_to_end(&mut blob)?;
+    if let (Some(n), Some(out)) = (opts.dump_segment, opts.out.as_deref()) {
+        if n >= img.segments.len() { bail!("segment {} out of range (image has {})", n, img.segments.len()); }
+        let bytes = img.decoded_payload(n)?;
+        fs::write(out, &bytes)?;
+        eprintln!("wrote {} bytes of segment[{}] to {}", bytes.len(), n, out);
+        return Ok(ExitCode::SUCCESS);
+    }
 
-    // Friendly guard: if this is an ELF, bail with a clear hint.
-    if blob.len() >= 4 && &blob[0..4] == b"\x7FELF" {
-        bail!("this looks like an ELF file; pack it with tools/rtosk-gen to produce KERNEL.RTOSK");
+    if opts.json {
+        println!("{}", json::image_to_json(&img));
+    } else if !opts.check {
+        print_human(&img);
     }
 
-    if blob.len() < mem::size_of::<RtoskHeader>() {
-        bail!("file too small for header");
+    if opts.check {
+        let issues = check::problems(&img);
+        if !opts.json {
+            for p in &issues { println!("FAIL: {}", p); }
+            if issues.is_empty() { println!("OK: {} passes structural checks", opts.path); }
+        }
+        if !issues.is_empty() { return Ok(ExitCode::from(2)); }
     }
 
-    let magic_offset = find_magic(&blob, &RTOSK_MAGIC)
-        .ok_or_else(|| InspectError("RTOSK magic not found".into()))?;
-    println!("RTOSK magic offset in file: 0x{:x}", magic_offset);
+    Ok(ExitCode::SUCCESS)
+}
 
-    let header: RtoskHeader = read_struct(&blob, magic_offset)
-        .ok_or_else(|| InspectError(format!("failed to read header at offset 0x{:x}", magic_offset)))?;
+fn print_human(img: &RtoskImage) {
+    let blob = &img.blob;
+    let magic_offset = img.magic_offset;
+    let header = img.header;
+    println!("RTOSK magic offset in file: 0x{:x}", magic_offset);
 
     let magic_str = String::from_utf8_lossy(&header.magic);
     println!("Header:");
@@ -108,31 +179,27 @@ fn main() -> Result<()> {
         println!("WARNING: file too small for declared header_len+segments (moff+hdr_bytes+seg_bytes > file_len)");
     }
 
-    let mut segments: Vec<RtoskSegment> = Vec::new();
-    let mut seg_table_off = magic_offset + mem::size_of::<RtoskHeader>();
-    for i in 0..header.seg_count as usize {
-        match read_struct::<RtoskSegment>(&blob, seg_table_off) {
-            Some(s) => segments.push(s),
-            None => {
-                println!("Failed to read segment[{}] at 0x{:x}", i, seg_table_off);
-                break;
-            }
-        }
-        seg_table_off += mem::size_of::<RtoskSegment>();
+    let segments = &img.segments;
+    if segments.len() < header.seg_count as usize {
+        let seg_table_off = magic_offset + mem::size_of::<RtoskHeader>() + segments.len() * mem::size_of::<RtoskSegment>();
+        println!("Failed to read segment[{}] at 0x{:x}", segments.len(), seg_table_off);
     }
 
     for (i, seg) in segments.iter().enumerate() {
         println!("Segment[{}]:", i);
         println!("  file_offset  = 0x{:x}", seg.file_offset);
         println!("  file_size    = 0x{:x}", seg.file_size);
+        if seg.is_compressed() {
+            println!("  packed_size  = 0x{:x}", seg.packed_size);
+        }
         println!("  memory_addr  = 0x{:x}", seg.memory_addr);
         println!("  memory_size  = 0x{:x}", seg.memory_size);
-        println!("  flags        = 0x{:x}", seg.flags);
+        println!("  flags        = 0x{:x} ({})", seg.flags, perms_str(seg));
         if (seg.file_offset as usize) < blob.len() {
             let fo = seg.file_offset as usize;
             let want = 64.min(blob.len().saturating_sub(fo));
             println!("  -> file payload at 0x{:x} (first 0x{:x} bytes):", fo, want);
-            dump_bytes_at(&blob, fo, want);
+            dump_bytes_at(blob, fo, want);
         } else {
             println!("  -> file_offset out of range (file len {})", blob.len());
         }
@@ -142,7 +209,7 @@ fn main() -> Result<()> {
     if header.entry64 == 0 {
         println!("header.entry64 is zero — packer didn't set entry VA.");
     } else {
-        let entry = header.entry64 as u64;
+        let entry = header.entry64;
         println!("Checking header.entry64 = 0x{:x}", entry);
         let mut found = false;
         for (i, seg) in segments.iter().enumerate() {
@@ -160,7 +227,7 @@ fn main() -> Result<()> {
                     let off = mapped_file_offset as usize;
                     let want = 128.min(blob.len().saturating_sub(off));
                     println!("     bytes at file offset ->");
-                    dump_bytes_at(&blob, off, want);
+                    dump_bytes_at(blob, off, want);
                 } else {
                     println!("     mapped file offset out of file range (file len {})", blob.len());
                 }
@@ -171,7 +238,7 @@ fn main() -> Result<()> {
             let want = 128.min(blob.len());
             println!();
             println!("File head (first {} bytes):", want);
-            dump_bytes_at(&blob, 0, want);
+            dump_bytes_at(blob, 0, want);
         }
     }
 
@@ -182,8 +249,21 @@ fn main() -> Result<()> {
         magic_offset + total_head_len
     );
     if total_head_len > 0 {
-        dump_bytes_at(&blob, magic_offset, total_head_len.min(256));
+        dump_bytes_at(blob, magic_offset, total_head_len.min(256));
     }
 
-    Ok(())
+    let crc = img.crc_status();
+    println!();
+    println!("Derived:");
+    match img.entry_segment() {
+        Some(i) => println!("  entry segment     = {}", i),
+        None => println!("  entry segment     = none"),
+    }
+    println!("  memory footprint  = 0x{:x}", img.memory_footprint());
+    println!("  image crc32       = 0x{:08x} ({})", crc.computed, if crc.ok() { "OK" } else { "MISMATCH" });
+    match img.signature() {
+        Some(sig) => println!("  signed by         = {}", hex(&sig.public_key)),
+        None if img.header.is_signed() => println!("  signed by         = <signature block missing>"),
+        None => println!("  signed by         = unsigned"),
+    }
 }
@@ -0,0 +1,94 @@
+use std::{env, fs, io::Read, process::ExitCode};
+
+use ed25519_compact::{KeyPair, Seed};
+use rtos_types::rtosk_header::signed_len;
+use rtosk_sig::{PUBLIC_KEY_LEN, SECRET_KEY_LEN};
+
+const USAGE: &str = "\
+usage:
+  rtosk-sign keygen <name>                 write <name>.key (secret) and <name>.pub
+  rtosk-sign sign <name.key> <in> [out]    sign an RTOSK image (in place if no out)
+  rtosk-sign verify <image> [name.pub]...  verify against the given keys (or the embedded one)";
+
+type Result<T> = std::result::Result<T, String>;
+
+fn read_key<const N: usize>(path: &str) -> Result<[u8; N]> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    bytes.as_slice().try_into().map_err(|_| format!("{}: expected {} bytes, got {}", path, N, bytes.len()))
+}
+
+fn keygen(name: &str) -> Result<()> {
+    let mut seed = [0u8; Seed::BYTES];
+    fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut seed))
+        .map_err(|e| format!("/dev/urandom: {}", e))?;
+    let kp = KeyPair::from_seed(Seed::new(seed));
+
+    let key_path = format!("{}.key", name);
+    let pub_path = format!("{}.pub", name);
+    fs::write(&key_path, *kp).map_err(|e| format!("{}: {}", key_path, e))?;
+    fs::write(&pub_path, *kp.pk).map_err(|e| format!("{}: {}", pub_path, e))?;
+    println!("wrote {} and {}", key_path, pub_path);
+    println!("trust with: RTOS_TRUSTED_KEYS={}", hex(&kp.pk[..]));
+    Ok(())
+}
+
+fn sign(key_path: &str, in_path: &str, out_path: &str) -> Result<()> {
+    let key = read_key::<SECRET_KEY_LEN>(key_path)?;
+    let mut image = fs::read(in_path).map_err(|e| format!("{}: {}", in_path, e))?;
+    if !image.starts_with(&rtos_types::constants::RTOSK_MAGIC) {
+        return Err(format!("{}: not an RTOSK image", in_path));
+    }
+    // Re-signing replaces any existing block rather than stacking a second one.
+    let len = signed_len(&image);
+    image.truncate(len);
+
+    rtosk_sig::mark_signed(&mut image);
+    let sig = rtosk_sig::sign(&image, &key).map_err(|e| e.as_str().to_string())?;
+    image.extend_from_slice(&sig.to_bytes());
+    fs::write(out_path, &image).map_err(|e| format!("{}: {}", out_path, e))?;
+    println!("signed {} with key {}", out_path, hex(&sig.public_key));
+    Ok(())
+}
+
+fn verify(image_path: &str, pub_paths: &[String]) -> Result<()> {
+    let image = fs::read(image_path).map_err(|e| format!("{}: {}", image_path, e))?;
+    let mut trusted = Vec::new();
+    for p in pub_paths {
+        trusted.push(read_key::<PUBLIC_KEY_LEN>(p)?);
+    }
+    if trusted.is_empty() {
+        // Integrity only: trust whatever key the image carries.
+        let block = rtosk_sig::signature_block(&image).map_err(|e| e.as_str().to_string())?;
+        trusted.push(block.public_key);
+    }
+    let block = rtosk_sig::verify(&image, &trusted).map_err(|e| e.as_str().to_string())?;
+    println!("OK: signed by {}", hex(&block.public_key));
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn run(args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        Some("keygen") if args.len() == 2 => keygen(&args[1]),
+        Some("sign") if args.len() == 3 || args.len() == 4 => {
+            sign(&args[1], &args[2], args.get(3).unwrap_or(&args[2]))
+        }
+        Some("verify") if args.len() >= 2 => verify(&args[1], &args[2..]),
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("rtosk-sign: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -14,16 +14,32 @@ KERNEL_TARGET="x86_64-unknown-none"
 
 BUILD_ROOT="${BUILD_ROOT:-build}"
 
+# ---- signing -----------------------------------------------------------------
+# RTOS_SIGN_KEY=<name.key> signs KERNEL.RTOSK and bakes the matching public key
+# into the bootloader. Without it the bootloader is built with `dev-unsigned`.
+SIGN_KEY="${RTOS_SIGN_KEY:-}"
+boot_features=()
+if [[ -n "$SIGN_KEY" ]]; then
+  [[ -f "$SIGN_KEY" ]] || { echo "ERROR: RTOS_SIGN_KEY not found: $SIGN_KEY" >&2; exit 5; }
+  # Secret key file is seed(32) || public key(32).
+  sign_pub=$(od -An -tx1 -v -j32 -N32 "$SIGN_KEY" | tr -d ' \n')
+  export RTOS_TRUSTED_KEYS="${RTOS_TRUSTED_KEYS:+${RTOS_TRUSTED_KEYS},}${sign_pub}"
+  echo "Signing enabled; trusted key ${sign_pub}"
+else
+  boot_features+=(--features dev-unsigned)
+  echo "WARN: RTOS_SIGN_KEY not set; bootloader built with dev-unsigned"
+fi
+
 # ---- build BOOTLOADER --------------------------------------------------------
 BOOT_BUILD_DIR="${BUILD_ROOT}/${boot_crate}"
 export CARGO_TARGET_DIR="${BOOT_BUILD_DIR}/target"
 mkdir -p "${CARGO_TARGET_DIR}"
 
 if [[ "$profile" == "release" ]]; then
-  cargo +nightly build -p "$boot_crate" --release --target "$target" -Z build-std=core,compiler_builtins
+  cargo +nightly build -p "$boot_crate" --release --target "$target" -Z build-std=core,compiler_builtins "${boot_features[@]}"
   out_dir="release"
 else
-  cargo +nightly build -p "$boot_crate" --target "$target" -Z build-std=core,compiler_builtins
+  cargo +nightly build -p "$boot_crate" --target "$target" -Z build-std=core,compiler_builtins "${boot_features[@]}"
   out_dir="debug"
 fi
 
@@ -47,16 +63,6 @@ echo "Kernel ELF: ${kernel_elf}"
 [[ -f "${kernel_elf}" ]] || { echo "ERROR: kernel ELF not found at ${kernel_elf}" >&2; exit 2; }
 echo "Kernel ELF size: $(stat -c%s "${kernel_elf}") bytes"
 
-# ---- make a FLAT BIN from sections we actually need -------------------------
-KERNEL_BIN_DIR="${BUILD_ROOT}/${KERNEL_CRATE}"
-mkdir -p "${KERNEL_BIN_DIR}"
-kernel_bin="${KERNEL_BIN_DIR}/kernel.bin"
-
-# Extract .text + .rodata + .data as a contiguous flat image.
-# (Avoids weird PHDR/SHDR edge cases in tiny tests; matches future non-ELF world.)
-objcopy -O binary -j .text -j .rodata -j .data "${kernel_elf}" "${kernel_bin}"
-echo "Kernel BIN: ${kernel_bin} ($(stat -c%s "${kernel_bin}") bytes)"
-
 # ---- fresh ESP staging -------------------------------------------------------
 ESP_DIR="${BOOT_BUILD_DIR}/esp"
 rm -rf "${ESP_DIR}"
@@ -73,9 +79,13 @@ packer_bin="${PACKER_BUILD_DIR}/target/release/rtosk-gen"
 KERNEL_RK_CANON="${BUILD_ROOT}/${KERNEL_CRATE}/KERNEL.RTOSK"
 rm -f "${KERNEL_RK_CANON}"
 
-# Args: <input> <output.rtosk> [entry_va] [page_size]
-# We pass entry 0x200000 to match your linker & bootloader jump.
-"${packer_bin}" "${kernel_bin}" "${KERNEL_RK_CANON}" 0x200000 0x1000
+# Args: [--compress] [--sign <key>] <input> <output.rtosk> [entry_va] [page_size]
+# Pack the ELF directly so .bss (NOBITS / p_memsz > p_filesz) is described and zeroed by the loader.
+# Entry comes from e_entry (rtos_entry at 0x200000 per linker.ld).
+sign_args=()
+[[ -n "$SIGN_KEY" ]] && sign_args=(--sign "$SIGN_KEY")
+# Payloads are LZ4-compressed; the bootloader decompresses them straight into the segment pages.
+"${packer_bin}" --compress "${sign_args[@]}" "${kernel_elf}" "${KERNEL_RK_CANON}"
 
 [[ -f "${KERNEL_RK_CANON}" ]] || { echo "ERROR: ${KERNEL_RK_CANON} not produced by packer" >&2; exit 4; }
 rk_size=$(stat -c%s "${KERNEL_RK_CANON}")
@@ -83,8 +93,22 @@ echo "Packed: ${KERNEL_RK_CANON} (size ${rk_size} bytes)"
 
 cp -f "${KERNEL_RK_CANON}" "${ESP_DIR}/EFI/BOOT/KERNEL.RTOSK"
 
+# Optional bootloader config (\EFI\RTOS\boot.cfg)
+if [[ -n "${RTOS_BOOT_CFG:-}" ]]; then
+  [[ -f "${RTOS_BOOT_CFG}" ]] || { echo "ERROR: RTOS_BOOT_CFG not found: ${RTOS_BOOT_CFG}" >&2; exit 6; }
+  mkdir -p "${ESP_DIR}/EFI/RTOS"
+  cp -f "${RTOS_BOOT_CFG}" "${ESP_DIR}/EFI/RTOS/boot.cfg"
+fi
+
+# Optional initrd archive (\EFI\RTOS\INITRD.TAR); list it under `initrd` in boot.cfg
+if [[ -n "${RTOS_INITRD:-}" ]]; then
+  [[ -f "${RTOS_INITRD}" ]] || { echo "ERROR: RTOS_INITRD not found: ${RTOS_INITRD}" >&2; exit 6; }
+  mkdir -p "${ESP_DIR}/EFI/RTOS"
+  cp -f "${RTOS_INITRD}" "${ESP_DIR}/EFI/RTOS/INITRD.TAR"
+fi
+
 echo "ESP ready:"
-find "${ESP_DIR}/EFI/BOOT" -maxdepth 1 -type f -printf '  %f (%s bytes)\n' | sort
+find "${ESP_DIR}/EFI" -mindepth 2 -maxdepth 2 -type f -printf '  %f (%s bytes)\n' | sort
 
 # ---- optional: rtosk-inspect (best-effort) ----------------------------------
 INSPECT_BUILD_DIR="${BUILD_ROOT}/rtosk-inspect"
@@ -125,6 +149,11 @@ for f in "${ovmf_vars_candidates[@]}";  do [[ -n "$f" && -f "$f" ]] && { ovmf_va
 [[ -n "$ovmf_code" ]] || { echo "OVMF CODE not found. Install 'ovmf' or set OVMF_CODE=/path/to/OVMF_CODE*.fd"; exit 7; }
 
 extra_qemu_args=()
+# RTOS_QEMU_ARGS: extra QEMU flags, split on whitespace (e.g. "-m 512M -display none")
+if [[ -n "${RTOS_QEMU_ARGS:-}" ]]; then
+  read -r -a qemu_env_args <<< "${RTOS_QEMU_ARGS}"
+  extra_qemu_args+=("${qemu_env_args[@]}")
+fi
 if [[ "$debug_mode" == "on" ]]; then
   extra_qemu_args+=(-s -S)
   echo "debug: QEMU started paused; attach with 'gdb -ex \"target remote :1234\"' then 'c'"